@@ -1,6 +1,5 @@
 const NATIVE_HOST_NAME = "org.savebutton.nativehost";
 let nativePort = null;
-let knownBookmarkedUrls = new Set();
 let pendingResponses = new Map();
 let messageId = 0;
 
@@ -27,7 +26,6 @@ function connectToNativeHost() {
       }
 
       if (message.type === "bookmarks") {
-        knownBookmarkedUrls = new Set(message.urls || []);
         updateIconForActiveTab();
       }
     });
@@ -75,15 +73,15 @@ async function sendToNativeHost(message) {
   });
 }
 
-function generateTimestamp() {
-  const now = new Date();
-  const year = now.getUTCFullYear();
-  const month = String(now.getUTCMonth() + 1).padStart(2, "0");
-  const day = String(now.getUTCDate()).padStart(2, "0");
-  const hours = String(now.getUTCHours()).padStart(2, "0");
-  const minutes = String(now.getUTCMinutes()).padStart(2, "0");
-  const seconds = String(now.getUTCSeconds()).padStart(2, "0");
-  return `${year}-${month}-${day}T${hours}${minutes}${seconds}`;
+async function isUrlBookmarked(url) {
+  if (!url || !/^https?:/.test(url)) {
+    return false;
+  }
+  const response = await sendToNativeHost({
+    message: "bookmark_status",
+    url: url,
+  });
+  return response.bookmarked === true;
 }
 
 async function updateIconForActiveTab() {
@@ -95,7 +93,7 @@ async function updateIconForActiveTab() {
     if (tabs.length === 0) return;
 
     const tab = tabs[0];
-    const isBookmarked = tab.url && knownBookmarkedUrls.has(tab.url);
+    const isBookmarked = await isUrlBookmarked(tab.url);
 
     const iconPath = isBookmarked
       ? {
@@ -133,22 +131,17 @@ browser.contextMenus.create({
 });
 
 browser.contextMenus.onClicked.addListener(async (info, tab) => {
-  const timestamp = generateTimestamp();
-
   try {
     if (info.menuItemId === "save-to-kaya-text" && info.selectionText) {
-      const filename = `${timestamp}-quote.md`;
       const message = {
-        message: "anga",
-        filename: filename,
-        type: "text",
+        message: "save_quote",
         text: info.selectionText,
       };
 
       await sendToNativeHost(message);
       showNotification("Text added to Save Button");
     } else if (info.menuItemId === "save-to-kaya-image" && info.srcUrl) {
-      await saveImage(info.srcUrl, timestamp);
+      await saveImage(info.srcUrl);
     }
   } catch (error) {
     console.error("Failed to save:", error);
@@ -156,7 +149,7 @@ browser.contextMenus.onClicked.addListener(async (info, tab) => {
   }
 });
 
-async function saveImage(imageUrl, timestamp) {
+async function saveImage(imageUrl) {
   try {
     const response = await fetch(imageUrl);
     if (!response.ok) {
@@ -167,20 +160,11 @@ async function saveImage(imageUrl, timestamp) {
     const arrayBuffer = await blob.arrayBuffer();
     const base64 = arrayBufferToBase64(arrayBuffer);
 
-    let filename;
-    try {
-      const urlObj = new URL(imageUrl);
-      const originalFilename = urlObj.pathname.split("/").pop() || "image";
-      filename = `${timestamp}-${originalFilename}`;
-    } catch (e) {
-      const ext = blob.type.split("/")[1] || "png";
-      filename = `${timestamp}-image.${ext}`;
-    }
-
+    // The daemon names the file after the image URL
     const message = {
-      message: "anga",
-      filename: filename,
-      type: "base64",
+      message: "save_image",
+      url: imageUrl,
+      mime_type: blob.type,
       base64: base64,
     };
 
@@ -239,6 +223,17 @@ browser.runtime.onMessage.addListener((request, sender, sendResponse) => {
     return true;
   }
 
+  if (request.action === "checkBookmark") {
+    sendToNativeHost({
+      message: "bookmark_status",
+      url: request.url,
+      include_preview: true,
+    })
+      .then((response) => sendResponse(response))
+      .catch((error) => sendResponse({ error: error.message }));
+    return true;
+  }
+
   if (request.action === "checkConfigStatus") {
     sendToNativeHost({ message: "config_status" })
       .then((response) => sendResponse(response))
@@ -13,35 +13,16 @@
   const statusText = document.getElementById("status-text");
   const noteContainer = document.getElementById("note-container");
   const noteInput = document.getElementById("note-input");
+  const duplicateContainer = document.getElementById("duplicate-container");
+  const saveAgainBtn = document.getElementById("save-again-btn");
   const errorContainer = document.getElementById("error-container");
   const errorText = document.getElementById("error-text");
 
   let autoCloseTimeout = null;
   let noteFocused = false;
   let bookmarkSaved = false;
-  let currentTimestamp = null;
   let currentFilename = null;
 
-  function generateTimestamp() {
-    const now = new Date();
-    const year = now.getUTCFullYear();
-    const month = String(now.getUTCMonth() + 1).padStart(2, "0");
-    const day = String(now.getUTCDate()).padStart(2, "0");
-    const hours = String(now.getUTCHours()).padStart(2, "0");
-    const minutes = String(now.getUTCMinutes()).padStart(2, "0");
-    const seconds = String(now.getUTCSeconds()).padStart(2, "0");
-    return `${year}-${month}-${day}T${hours}${minutes}${seconds}`;
-  }
-
-  function urlToDomainSlug(url) {
-    try {
-      const urlObj = new URL(url);
-      return urlObj.hostname.replace(/[^a-zA-Z0-9]/g, "-");
-    } catch (e) {
-      return "unknown";
-    }
-  }
-
   function showSetupError(message) {
     setupError.textContent = message;
     setupError.classList.remove("hidden");
@@ -68,6 +49,13 @@
     statusText.textContent = "Saving bookmark...";
   }
 
+  function showDuplicate() {
+    statusIcon.className = "duplicate";
+    statusText.textContent = "Already saved";
+    noteContainer.classList.add("hidden");
+    duplicateContainer.classList.remove("hidden");
+  }
+
   function startAutoCloseTimer() {
     if (autoCloseTimeout) {
       clearTimeout(autoCloseTimeout);
@@ -138,7 +126,7 @@
       // Setup complete, now save the bookmark
       setupView.classList.add("hidden");
       bookmarkView.classList.remove("hidden");
-      saveBookmark();
+      checkDuplicateAndSave();
     } catch (error) {
       showSetupError("Error: " + error.message);
       setupSaveBtn.textContent = "Save & Continue";
@@ -146,32 +134,49 @@
     }
   }
 
+  async function getActiveTab() {
+    const tabs = await browser.tabs.query({
+      active: true,
+      currentWindow: true,
+    });
+    return tabs[0];
+  }
+
+  async function checkDuplicateAndSave() {
+    try {
+      const tab = await getActiveTab();
+      if (tab && tab.url) {
+        const response = await browser.runtime.sendMessage({
+          action: "checkBookmark",
+          url: tab.url,
+        });
+        if (response && response.bookmarked) {
+          showDuplicate();
+          return;
+        }
+      }
+    } catch (error) {
+      // The duplicate check is advisory; save anyway if it fails
+      console.error("Failed to check for duplicate:", error);
+    }
+    saveBookmark();
+  }
+
   async function saveBookmark() {
     showSaving();
 
     try {
-      const tabs = await browser.tabs.query({
-        active: true,
-        currentWindow: true,
-      });
-      const tab = tabs[0];
+      const tab = await getActiveTab();
 
       if (!tab || !tab.url) {
         showError("No active tab found");
         return;
       }
 
-      currentTimestamp = generateTimestamp();
-      const domainSlug = urlToDomainSlug(tab.url);
-      currentFilename = `${currentTimestamp}-${domainSlug}.url`;
-
-      const urlFileContent = `[InternetShortcut]\nURL=${tab.url}\n`;
-
       const message = {
-        message: "anga",
-        filename: currentFilename,
-        type: "text",
-        text: urlFileContent,
+        message: "save_url",
+        url: tab.url,
+        title: tab.title,
       };
 
       const response = await browser.runtime.sendMessage({
@@ -182,6 +187,7 @@
       if (response && response.error) {
         showError(response.error);
       } else {
+        currentFilename = response.filename;
         bookmarkSaved = true;
         showSuccess("Bookmark saved!");
         startAutoCloseTimer();
@@ -192,17 +198,16 @@
   }
 
   async function saveNote(noteText) {
-    if (!currentTimestamp || !currentFilename) {
+    if (!currentFilename) {
       showError("No bookmark to attach note to");
       return;
     }
 
-    const metaFilename = `${currentTimestamp}-note.toml`;
+    // The daemon names the meta file when no filename is given
     const metaContent = `[anga]\nfilename = "${currentFilename}"\n\n[meta]\nnote = '''${noteText}'''`;
 
     const message = {
       message: "meta",
-      filename: metaFilename,
       type: "text",
       text: metaContent,
     };
@@ -235,6 +240,12 @@
   });
 
   // Bookmark view event listeners
+  saveAgainBtn.addEventListener("click", () => {
+    duplicateContainer.classList.add("hidden");
+    noteContainer.classList.remove("hidden");
+    saveBookmark();
+  });
+
   noteInput.addEventListener("focus", () => {
     noteFocused = true;
     if (autoCloseTimeout) {
@@ -268,7 +279,7 @@
 
     if (isConfigured) {
       bookmarkView.classList.remove("hidden");
-      saveBookmark();
+      checkDuplicateAndSave();
     } else {
       setupView.classList.remove("hidden");
       // Pre-fill server with default
@@ -6,7 +6,7 @@
 .DESCRIPTION
     This script builds and installs the Save Button Sync Daemon native messaging host
     for the Firefox extension. It installs the binary to Program Files and
-    registers the native messaging manifest in the Windows Registry.
+    runs `install-host`, which registers it with each browser for the current user.
 
 .NOTES
     Requires Administrator privileges.
@@ -16,7 +16,6 @@
 $ErrorActionPreference = "Stop"
 
 $BinaryName = "savebutton-sync-daemon.exe"
-$ManifestName = "org.savebutton.nativehost"
 $InstallDir = "$env:ProgramFiles\Save Button"
 $KayaDataDir = "$env:USERPROFILE\.kaya"
 
@@ -41,17 +40,12 @@ $BinarySource = Join-Path $PSScriptRoot "target\release\$BinaryName"
 $BinaryDest = Join-Path $InstallDir $BinaryName
 Copy-Item -Path $BinarySource -Destination $BinaryDest -Force
 
-Write-Host "Installing native messaging manifest..." -ForegroundColor Cyan
-$ManifestSource = Join-Path $PSScriptRoot "manifests\org.savebutton.nativehost.windows.json"
-$ManifestDest = Join-Path $InstallDir "org.savebutton.nativehost.json"
-Copy-Item -Path $ManifestSource -Destination $ManifestDest -Force
-
-Write-Host "Registering native messaging host in registry..." -ForegroundColor Cyan
-$RegistryPath = "HKLM:\SOFTWARE\Mozilla\NativeMessagingHosts\$ManifestName"
-if (-not (Test-Path $RegistryPath)) {
-    New-Item -Path $RegistryPath -Force | Out-Null
+Write-Host "Registering native messaging host..." -ForegroundColor Cyan
+# Writes a manifest and HKCU registry key for each browser found
+& $BinaryDest install-host
+if ($LASTEXITCODE -ne 0) {
+    throw "Registering the native messaging host failed"
 }
-Set-ItemProperty -Path $RegistryPath -Name "(Default)" -Value $ManifestDest
 
 Write-Host "Creating data directories..." -ForegroundColor Cyan
 $AngaDir = Join-Path $KayaDataDir "anga"
@@ -67,8 +61,7 @@ Write-Host ""
 Write-Host "Installation complete!" -ForegroundColor Green
 Write-Host ""
 Write-Host "Binary installed to: $BinaryDest" -ForegroundColor White
-Write-Host "Manifest installed to: $ManifestDest" -ForegroundColor White
-Write-Host "Registry key created: $RegistryPath" -ForegroundColor White
+Write-Host "Check the registration with: $BinaryName install-host --check" -ForegroundColor White
 Write-Host "Data directory: $KayaDataDir" -ForegroundColor White
 Write-Host ""
 Write-Host "Next steps:" -ForegroundColor Yellow
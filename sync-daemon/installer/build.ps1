@@ -34,11 +34,10 @@ Push-Location $ScriptDir
 try {
     # Set paths for WiX
     $BinaryPath = Join-Path $ProjectDir "target\x86_64-pc-windows-msvc\release"
-    $ManifestPath = Join-Path $ProjectDir "manifests"
 
     $XpiPath = Join-Path (Split-Path $ProjectDir -Parent) "savebutton.xpi"
 
-    dotnet build -c Release -p:BinaryPath="$BinaryPath" -p:ManifestPath="$ManifestPath" -p:XpiPath="$XpiPath"
+    dotnet build -c Release -p:BinaryPath="$BinaryPath" -p:XpiPath="$XpiPath"
     if ($LASTEXITCODE -ne 0) {
         throw "WiX build failed"
     }
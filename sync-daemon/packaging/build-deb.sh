@@ -27,7 +27,6 @@ rm -rf "$PKG_ROOT"
 # Create directory structure
 mkdir -p "$PKG_ROOT/DEBIAN"
 mkdir -p "$PKG_ROOT/usr/lib/savebutton"
-mkdir -p "$PKG_ROOT/usr/lib/mozilla/native-messaging-hosts"
 mkdir -p "$PKG_ROOT/etc/skel/.kaya/anga"
 mkdir -p "$PKG_ROOT/etc/skel/.kaya/meta"
 
@@ -39,17 +38,6 @@ chmod 755 "$PKG_ROOT/usr/lib/savebutton/savebutton-sync-daemon"
 cp "$XPI_PATH" "$PKG_ROOT/usr/lib/savebutton/savebutton.xpi"
 chmod 644 "$PKG_ROOT/usr/lib/savebutton/savebutton.xpi"
 
-# Create native messaging manifest with correct path
-cat > "$PKG_ROOT/usr/lib/mozilla/native-messaging-hosts/org.savebutton.nativehost.json" << 'EOF'
-{
-  "name": "org.savebutton.nativehost",
-  "description": "Save Button Sync Daemon - Native messaging host for the Save Button Firefox extension",
-  "path": "/usr/lib/savebutton/savebutton-sync-daemon",
-  "type": "stdio",
-  "allowed_extensions": ["org.savebutton@savebutton.org"]
-}
-EOF
-
 # Create control file
 cat > "$PKG_ROOT/DEBIAN/control" << EOF
 Package: ${PACKAGE_NAME}
@@ -75,13 +63,29 @@ if [ -n "$SUDO_USER" ]; then
     REAL_HOME=$(getent passwd "$SUDO_USER" | cut -d: -f6)
     if [ -n "$REAL_HOME" ]; then
         su "$SUDO_USER" -c "mkdir -p '$REAL_HOME/.kaya/anga' '$REAL_HOME/.kaya/meta'"
+        # Register the host with each browser the user has
+        su "$SUDO_USER" -c "/usr/lib/savebutton/savebutton-sync-daemon install-host" || true
         # Open the .xpi in Firefox to prompt extension installation
         su "$SUDO_USER" -c "xdg-open /usr/lib/savebutton/savebutton.xpi 2>/dev/null || true" &
     fi
+else
+    echo "Run /usr/lib/savebutton/savebutton-sync-daemon install-host as each user to register the host with their browsers."
 fi
 POSTINST
 chmod 755 "$PKG_ROOT/DEBIAN/postinst"
 
+# Create prerm script
+cat > "$PKG_ROOT/DEBIAN/prerm" << 'PRERM'
+#!/bin/bash
+set -e
+
+# Remove the manifests install-host wrote, unless this is an upgrade
+if [ "$1" = "remove" ] && [ -n "$SUDO_USER" ]; then
+    su "$SUDO_USER" -c "/usr/lib/savebutton/savebutton-sync-daemon uninstall-host" || true
+fi
+PRERM
+chmod 755 "$PKG_ROOT/DEBIAN/prerm"
+
 # Build the .deb
 dpkg-deb --build --root-owner-group "$PKG_ROOT"
 
@@ -28,7 +28,6 @@ rm -rf "$BUILD_DIR"
 # Create directory structure
 mkdir -p "$PKG_ROOT/usr/local/bin"
 mkdir -p "$PKG_ROOT/usr/local/lib/savebutton"
-mkdir -p "$PKG_ROOT/Library/Application Support/Mozilla/NativeMessagingHosts"
 
 # Copy binary
 cp "$BINARY_PATH" "$PKG_ROOT/usr/local/bin/savebutton-sync-daemon"
@@ -38,17 +37,6 @@ chmod 755 "$PKG_ROOT/usr/local/bin/savebutton-sync-daemon"
 cp "$XPI_PATH" "$PKG_ROOT/usr/local/lib/savebutton/savebutton.xpi"
 chmod 644 "$PKG_ROOT/usr/local/lib/savebutton/savebutton.xpi"
 
-# Create native messaging manifest with correct path
-cat > "$PKG_ROOT/Library/Application Support/Mozilla/NativeMessagingHosts/org.savebutton.nativehost.json" << 'EOF'
-{
-  "name": "org.savebutton.nativehost",
-  "description": "Save Button Sync Daemon - Native messaging host for the Save Button Firefox extension",
-  "path": "/usr/local/bin/savebutton-sync-daemon",
-  "type": "stdio",
-  "allowed_extensions": ["org.savebutton@savebutton.org"]
-}
-EOF
-
 # Create postinstall script
 mkdir -p "$SCRIPTS_DIR"
 cat > "$SCRIPTS_DIR/postinstall" << 'POSTINSTALL'
@@ -64,6 +52,9 @@ fi
 # Create data directories
 su "$REAL_USER" -c "mkdir -p '$REAL_HOME/.kaya/anga' '$REAL_HOME/.kaya/meta'"
 
+# Register the host with each browser the user has
+su "$REAL_USER" -c "/usr/local/bin/savebutton-sync-daemon install-host" || true
+
 # Open the .xpi in Firefox to prompt extension installation
 su "$REAL_USER" -c "open /usr/local/lib/savebutton/savebutton.xpi 2>/dev/null || true" &
 
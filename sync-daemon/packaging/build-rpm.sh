@@ -31,17 +31,6 @@ mkdir -p "$TARBALL_DIR"
 cp "$BINARY_PATH" "$TARBALL_DIR/savebutton-sync-daemon"
 cp "$XPI_PATH" "$TARBALL_DIR/savebutton.xpi"
 
-# Create native messaging manifest
-cat > "$TARBALL_DIR/org.savebutton.nativehost.json" << 'EOF'
-{
-  "name": "org.savebutton.nativehost",
-  "description": "Save Button Sync Daemon - Native messaging host for the Save Button Firefox extension",
-  "path": "/usr/lib/savebutton/savebutton-sync-daemon",
-  "type": "stdio",
-  "allowed_extensions": ["org.savebutton@savebutton.org"]
-}
-EOF
-
 tar -czf "$RPMBUILD_DIR/SOURCES/${PACKAGE_NAME}-${VERSION}.tar.gz" \
     -C "$RPMBUILD_DIR/SOURCES" "${PACKAGE_NAME}-${VERSION}"
 
@@ -65,13 +54,11 @@ with the Save Button server.
 
 %install
 mkdir -p %{buildroot}/usr/lib/savebutton
-mkdir -p %{buildroot}/usr/lib/mozilla/native-messaging-hosts
 mkdir -p %{buildroot}/etc/skel/.kaya/anga
 mkdir -p %{buildroot}/etc/skel/.kaya/meta
 
 install -m 755 savebutton-sync-daemon %{buildroot}/usr/lib/savebutton/savebutton-sync-daemon
 install -m 644 savebutton.xpi %{buildroot}/usr/lib/savebutton/savebutton.xpi
-install -m 644 org.savebutton.nativehost.json %{buildroot}/usr/lib/mozilla/native-messaging-hosts/org.savebutton.nativehost.json
 
 %post
 # Create data directories for the current user if running interactively
@@ -79,15 +66,24 @@ if [ -n "\$SUDO_USER" ]; then
     REAL_HOME=\$(getent passwd "\$SUDO_USER" | cut -d: -f6)
     if [ -n "\$REAL_HOME" ]; then
         su "\$SUDO_USER" -c "mkdir -p '\$REAL_HOME/.kaya/anga' '\$REAL_HOME/.kaya/meta'"
+        # Register the host with each browser the user has
+        su "\$SUDO_USER" -c "/usr/lib/savebutton/savebutton-sync-daemon install-host" || true
         su "\$SUDO_USER" -c "xdg-open /usr/lib/savebutton/savebutton.xpi 2>/dev/null || true" &
     fi
+else
+    echo "Run /usr/lib/savebutton/savebutton-sync-daemon install-host as each user to register the host with their browsers."
+fi
+
+%preun
+# Remove the manifests install-host wrote, unless this is an upgrade
+if [ "\$1" -eq 0 ] && [ -n "\$SUDO_USER" ]; then
+    su "\$SUDO_USER" -c "/usr/lib/savebutton/savebutton-sync-daemon uninstall-host" || true
 fi
 
 %files
 %dir /usr/lib/savebutton
 /usr/lib/savebutton/savebutton-sync-daemon
 /usr/lib/savebutton/savebutton.xpi
-/usr/lib/mozilla/native-messaging-hosts/org.savebutton.nativehost.json
 %dir /etc/skel/.kaya
 %dir /etc/skel/.kaya/anga
 %dir /etc/skel/.kaya/meta
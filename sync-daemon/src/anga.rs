@@ -0,0 +1,247 @@
+use crate::meta::{cmp_meta_filenames, read_meta_dir};
+use base64::{engine::general_purpose::STANDARD as BASE64, Engine};
+use chrono::{DateTime, NaiveDate, NaiveDateTime, TimeZone, Utc};
+use serde::{Deserialize, Serialize};
+use std::collections::HashSet;
+use std::fs;
+use std::io::{self, Read};
+use std::path::Path;
+
+/// Largest page `list_anga` will return, however many are asked for.
+pub const MAX_PAGE_SIZE: usize = 500;
+pub const DEFAULT_PAGE_SIZE: usize = 50;
+
+/// Broad categories of anga, decided by file extension.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum AngaKind {
+    Bookmark,
+    Note,
+    Image,
+    Pdf,
+    File,
+}
+
+impl AngaKind {
+    pub fn for_filename(filename: &str) -> Self {
+        let ext = filename.rsplit('.').next().unwrap_or("").to_lowercase();
+        match ext.as_str() {
+            "url" => AngaKind::Bookmark,
+            "md" | "txt" => AngaKind::Note,
+            "png" | "jpg" | "jpeg" | "gif" | "webp" | "svg" => AngaKind::Image,
+            "pdf" => AngaKind::Pdf,
+            _ => AngaKind::File,
+        }
+    }
+
+    fn is_text(self) -> bool {
+        matches!(self, AngaKind::Bookmark | AngaKind::Note)
+    }
+}
+
+/// Reads the UTC time from an ADR 0001 filename, in either the
+/// `YYYY-mm-ddTHHMMSS` or `YYYY-mm-ddTHHMMSS_SSSSSSSSS` form.
+pub fn parse_filename_timestamp(filename: &str) -> Option<DateTime<Utc>> {
+    let seconds = filename.get(..17)?;
+    let at = NaiveDateTime::parse_from_str(seconds, "%Y-%m-%dT%H%M%S").ok()?;
+    let nanos = filename
+        .get(17..27)
+        .and_then(|s| s.strip_prefix('_'))
+        .filter(|s| s.len() == 9 && s.chars().all(|c| c.is_ascii_digit()))
+        .and_then(|s| s.parse::<u32>().ok())
+        .unwrap_or(0);
+    Some(Utc.from_utc_datetime(&at) + chrono::Duration::nanoseconds(nanos as i64))
+}
+
+/// Parses the `since`/`until` filter values: RFC 3339 times or `YYYY-mm-dd`
+/// dates. A bare date as `until` covers that whole day.
+pub fn parse_date_bound(value: &str, end_of_day: bool) -> Option<DateTime<Utc>> {
+    if let Ok(at) = DateTime::parse_from_rfc3339(value) {
+        return Some(at.with_timezone(&Utc));
+    }
+    let date = NaiveDate::parse_from_str(value, "%Y-%m-%d").ok()?;
+    let date = if end_of_day { date.succ_opt()? } else { date };
+    Some(Utc.from_utc_datetime(&date.and_hms_opt(0, 0, 0)?))
+}
+
+/// Rejects names that could escape the anga or meta directory.
+pub fn is_plain_filename(filename: &str) -> bool {
+    !filename.is_empty()
+        && !filename.starts_with('.')
+        && !filename.contains(['/', '\\'])
+        && Path::new(filename).file_name().map(|n| n == filename) == Some(true)
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct ListQuery {
+    pub kind: Option<AngaKind>,
+    /// Inclusive lower bound on the filename timestamp.
+    pub since: Option<DateTime<Utc>>,
+    /// Exclusive upper bound on the filename timestamp.
+    pub until: Option<DateTime<Utc>>,
+    pub offset: usize,
+    pub limit: Option<usize>,
+    /// Filenames to leave out, such as hidden anga.
+    pub exclude: HashSet<String>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AngaEntry {
+    pub filename: String,
+    pub kind: AngaKind,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub timestamp: Option<DateTime<Utc>>,
+    pub size: u64,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AngaPage {
+    pub items: Vec<AngaEntry>,
+    /// Number of anga matching the filters, across all pages.
+    pub total: usize,
+}
+
+/// Lists anga newest first, filtered by `query`. Files without a timestamped
+/// name are only included when no date filter is given.
+pub fn list_anga(anga_dir: &Path, query: &ListQuery) -> io::Result<AngaPage> {
+    if !anga_dir.exists() {
+        return Ok(AngaPage {
+            items: Vec::new(),
+            total: 0,
+        });
+    }
+
+    let mut filenames: Vec<String> = fs::read_dir(anga_dir)?
+        .filter_map(|e| e.ok())
+        .filter(|e| e.path().is_file())
+        .filter_map(|e| e.file_name().into_string().ok())
+        .filter(|n| !n.starts_with('.') && !query.exclude.contains(n))
+        .collect();
+    filenames.sort_by(|a, b| b.cmp(a));
+
+    let has_date_filter = query.since.is_some() || query.until.is_some();
+    let matching: Vec<(String, Option<DateTime<Utc>>)> = filenames
+        .into_iter()
+        .filter(|n| query.kind.is_none_or(|k| AngaKind::for_filename(n) == k))
+        .map(|n| {
+            let at = parse_filename_timestamp(&n);
+            (n, at)
+        })
+        .filter(|(_, at)| match at {
+            Some(at) => {
+                query.since.is_none_or(|since| *at >= since)
+                    && query.until.is_none_or(|until| *at < until)
+            }
+            None => !has_date_filter,
+        })
+        .collect();
+
+    let limit = query
+        .limit
+        .unwrap_or(DEFAULT_PAGE_SIZE)
+        .clamp(1, MAX_PAGE_SIZE);
+    let items = matching
+        .iter()
+        .skip(query.offset)
+        .take(limit)
+        .map(|(filename, at)| AngaEntry {
+            filename: filename.clone(),
+            kind: AngaKind::for_filename(filename),
+            timestamp: *at,
+            size: fs::metadata(anga_dir.join(filename))
+                .map(|m| m.len())
+                .unwrap_or(0),
+        })
+        .collect();
+
+    Ok(AngaPage {
+        items,
+        total: matching.len(),
+    })
+}
+
+/// The contents of one anga. Text anga come back as `text`, everything else
+/// as `base64`. Files larger than the inline limit are cut short to it and
+/// marked as a `preview`.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AngaContent {
+    #[serde(flatten)]
+    pub entry: AngaEntry,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub text: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub base64: Option<String>,
+    pub preview: bool,
+    /// A `data:` URL of the anga's thumbnail, in place of `base64` for
+    /// images too large to send whole. `read_anga` leaves it to the caller,
+    /// since thumbnails live in the cache.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub thumbnail: Option<String>,
+}
+
+pub fn read_anga(anga_dir: &Path, filename: &str, max_inline: u64) -> io::Result<AngaContent> {
+    if !is_plain_filename(filename) {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidInput,
+            format!("invalid filename: {}", filename),
+        ));
+    }
+
+    let path = anga_dir.join(filename);
+    let size = fs::metadata(&path)?.len();
+    let kind = AngaKind::for_filename(filename);
+    let preview = size > max_inline;
+
+    let mut bytes = Vec::new();
+    fs::File::open(&path)?
+        .take(max_inline)
+        .read_to_end(&mut bytes)?;
+    let (text, base64) = if kind.is_text() {
+        (Some(String::from_utf8_lossy(&bytes).into_owned()), None)
+    } else {
+        (None, Some(BASE64.encode(&bytes)))
+    };
+
+    Ok(AngaContent {
+        entry: AngaEntry {
+            filename: filename.to_string(),
+            kind,
+            timestamp: parse_filename_timestamp(filename),
+            size,
+        },
+        text,
+        base64,
+        preview,
+        thumbnail: None,
+    })
+}
+
+/// A meta file, as stored on disk.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct MetaFile {
+    pub filename: String,
+    pub text: String,
+}
+
+/// Finds the meta files whose `[anga] filename` refers to `anga_filename`,
+/// in the order they fold in. Invalid meta files are skipped.
+pub fn meta_files_for(meta_dir: &Path, anga_filename: &str) -> io::Result<Vec<MetaFile>> {
+    let mut filenames: Vec<String> = read_meta_dir(meta_dir)?
+        .into_iter()
+        .filter(|(_, meta)| {
+            meta.as_ref()
+                .is_ok_and(|m| m.anga.filename == anga_filename)
+        })
+        .map(|(filename, _)| filename)
+        .collect();
+    filenames.sort_by(|a, b| cmp_meta_filenames(a, b));
+
+    let mut found = Vec::new();
+    for filename in filenames {
+        // Skipped if it went away since it was read
+        if let Ok(text) = fs::read_to_string(meta_dir.join(&filename)) {
+            found.push(MetaFile { filename, text });
+        }
+    }
+    Ok(found)
+}
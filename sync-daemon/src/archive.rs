@@ -0,0 +1,531 @@
+use crate::anga::is_plain_filename;
+use crate::html::{decode_entities, read_attributes, tag_name_end};
+use crate::naming::write_atomically;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+use url::Url;
+
+/// The snapshot's own name inside `cache/{bookmark}/`.
+pub const SNAPSHOT_FILENAME: &str = "index.html";
+
+/// Whether and how pages are archived when a bookmark is saved. Stored in
+/// `~/.kaya/.config` under `[archive]`; missing keys take their default
+/// values.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct ArchiveOptions {
+    /// Archive every saved bookmark. A message can still ask for (or skip)
+    /// a snapshot with `archive = true/false`.
+    pub enabled: bool,
+    /// Fetch the page when the extension didn't send its HTML.
+    pub fetch: bool,
+    /// Download images, stylesheets, scripts and icons next to the snapshot
+    /// and point the page at them.
+    pub store_assets: bool,
+    /// Assets beyond this many are left pointing at the live site.
+    pub max_assets: usize,
+    /// Pages and assets larger than this are skipped.
+    pub max_bytes: u64,
+}
+
+impl Default for ArchiveOptions {
+    fn default() -> Self {
+        ArchiveOptions {
+            enabled: false,
+            fetch: true,
+            store_assets: true,
+            max_assets: 100,
+            max_bytes: 10 * 1024 * 1024,
+        }
+    }
+}
+
+/// An attribute in a page that points at another URL.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Reference {
+    /// Byte range of the attribute value in the HTML, without quotes.
+    pub start: usize,
+    pub end: usize,
+    /// The value resolved against the page URL.
+    pub url: Url,
+}
+
+/// Attributes that load something the page needs to render, by tag. `link`
+/// only counts for stylesheets and icons; other `link`s are metadata.
+const ASSET_ATTRIBUTES: &[(&str, &str)] = &[
+    ("img", "src"),
+    ("script", "src"),
+    ("link", "href"),
+    ("source", "src"),
+    ("video", "poster"),
+    ("input", "src"),
+];
+
+/// Attributes that navigate somewhere else, by tag.
+const LINK_ATTRIBUTES: &[(&str, &str)] = &[("a", "href"), ("area", "href"), ("form", "action")];
+
+/// Where a reference sits, which decides how it's read and how its
+/// replacement has to be written.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Context {
+    /// An attribute holding one URL.
+    Attribute,
+    /// One candidate in a `srcset`.
+    Srcset,
+    /// A `url()` or `@import` in a `style` attribute.
+    StyleAttribute,
+    /// A `url()` or `@import` in a `<style>` element or a stylesheet.
+    Css,
+}
+
+/// Finds the assets a page loads, in document order: the attributes in
+/// `ASSET_ATTRIBUTES`, each candidate in an `img` or `source` `srcset`, and
+/// the `url()`s and `@import`s in `style` attributes and `<style>`
+/// elements. Values that are already inline (`data:`) or aren't http(s)
+/// once resolved are left out, as is anything inside comments.
+pub fn asset_references(html: &str, page_url: &Url) -> Vec<Reference> {
+    page_assets(html, page_url)
+        .into_iter()
+        .map(|(r, _)| r)
+        .collect()
+}
+
+fn page_assets(html: &str, page_url: &Url) -> Vec<(Reference, Context)> {
+    let base = page_base(html, page_url);
+    let mut references: Vec<(Reference, Context)> = find_values(html, ASSET_ATTRIBUTES)
+        .into_iter()
+        .map(|(start, end, value)| (start, end, value, Context::Attribute))
+        .chain(find_embedded(html))
+        .filter_map(|(start, end, value, context)| {
+            let value = match context {
+                Context::Css => value.trim().to_string(),
+                _ => decode_entities(value.trim()),
+            };
+            // A quoted url() in a style attribute may have its quotes as entities
+            let url = resolve(&base, value.trim_matches(['"', '\'']))?;
+            Some((Reference { start, end, url }, context))
+        })
+        .collect();
+    references.sort_by_key(|(r, _)| r.start);
+    references
+}
+
+/// The assets a stylesheet loads through `url()` and `@import`, resolved
+/// against `css_url`, in order. CSS escapes in URLs aren't decoded.
+pub fn css_references(css: &str, css_url: &Url) -> Vec<Reference> {
+    css_values(css)
+        .into_iter()
+        .filter_map(|(start, end)| {
+            let url = resolve(css_url, css[start..end].trim())?;
+            Some(Reference { start, end, url })
+        })
+        .collect()
+}
+
+/// Points a stored stylesheet's references at the assets in `stored`, and
+/// everything else at the absolute live URL, since relative ones would no
+/// longer resolve once the stylesheet is next to the snapshot.
+pub fn rewrite_css(css: &str, css_url: &Url, stored: &HashMap<Url, String>) -> String {
+    splice(
+        css,
+        css_references(css, css_url)
+            .into_iter()
+            .map(|r| (r.start, r.end, replacement(&r.url, stored, Context::Css)))
+            .collect(),
+    )
+}
+
+/// Finds links and form targets, in document order.
+/// Links within the page (`#section`) are left out.
+pub fn link_references(html: &str, page_url: &Url) -> Vec<Reference> {
+    find_references(html, page_url, LINK_ATTRIBUTES)
+}
+
+fn find_references(html: &str, page_url: &Url, wanted: &[(&str, &str)]) -> Vec<Reference> {
+    let base = page_base(html, page_url);
+    find_values(html, wanted)
+        .into_iter()
+        .filter_map(|(start, end, value)| {
+            let url = resolve(&base, &decode_entities(value.trim()))?;
+            Some(Reference { start, end, url })
+        })
+        .collect()
+}
+
+/// What relative URLs in the page resolve against: its `<base>`, or the
+/// page itself.
+fn page_base(html: &str, page_url: &Url) -> Url {
+    base_href(html)
+        .and_then(|href| page_url.join(&href).ok())
+        .unwrap_or_else(|| page_url.clone())
+}
+
+/// Resolves a reference, leaving out inline data, links within the page
+/// and anything that isn't http(s).
+fn resolve(base: &Url, value: &str) -> Option<Url> {
+    if value.is_empty() || value.starts_with("data:") || value.starts_with('#') {
+        return None;
+    }
+    let url = base.join(value).ok()?;
+    matches!(url.scheme(), "http" | "https").then_some(url)
+}
+
+/// The byte range and raw value of each wanted attribute, in document
+/// order, skipping comments.
+fn find_values<'a>(html: &'a str, wanted: &[(&str, &str)]) -> Vec<(usize, usize, &'a str)> {
+    let mut values = Vec::new();
+    let mut i = 0;
+    while let Some(offset) = html[i..].find('<') {
+        i += offset + 1;
+        if html[i..].starts_with("!--") {
+            i = html[i..]
+                .find("-->")
+                .map(|n| i + n + 3)
+                .unwrap_or(html.len());
+            continue;
+        }
+
+        let name_end = tag_name_end(html, i);
+        let tag = html[i..name_end].to_ascii_lowercase();
+        let Some(&(_, attribute)) = wanted.iter().find(|(t, _)| *t == tag) else {
+            continue;
+        };
+
+        let (attributes, end) = read_attributes(html, name_end);
+        i = end;
+
+        if tag == "link" {
+            let rel = attributes
+                .iter()
+                .find(|a| a.name == "rel")
+                .and_then(|a| a.value)
+                .map(|(_, _, v)| v.to_ascii_lowercase())
+                .unwrap_or_default();
+            if !rel
+                .split_whitespace()
+                .any(|r| r == "stylesheet" || r == "icon")
+            {
+                continue;
+            }
+        }
+
+        if let Some(value) = attributes
+            .iter()
+            .find(|a| a.name == attribute)
+            .and_then(|a| a.value)
+        {
+            values.push(value);
+        }
+    }
+    values
+}
+
+/// The URLs inside `srcset` and `style` attributes and `<style>` elements,
+/// as byte ranges in the HTML, skipping comments and scripts.
+fn find_embedded(html: &str) -> Vec<(usize, usize, &str, Context)> {
+    // ASCII lowercasing keeps byte offsets identical to `html`
+    let lower = html.to_ascii_lowercase();
+    let mut values = Vec::new();
+    let mut i = 0;
+    while let Some(offset) = html[i..].find('<') {
+        i += offset + 1;
+        if html[i..].starts_with("!--") {
+            i = html[i..]
+                .find("-->")
+                .map(|n| i + n + 3)
+                .unwrap_or(html.len());
+            continue;
+        }
+        if !html[i..].starts_with(|c: char| c.is_ascii_alphabetic()) {
+            continue;
+        }
+
+        let name_end = tag_name_end(html, i);
+        let tag = lower[i..name_end].to_string();
+        let (attributes, end) = read_attributes(html, name_end);
+        i = end;
+
+        for attribute in &attributes {
+            let Some((start, _, value)) = attribute.value else {
+                continue;
+            };
+            let ranges = match attribute.name.as_str() {
+                "srcset" if tag == "img" || tag == "source" => srcset_values(value)
+                    .into_iter()
+                    .map(|r| (r, Context::Srcset))
+                    .collect(),
+                "style" => css_values(value)
+                    .into_iter()
+                    .map(|r| (r, Context::StyleAttribute))
+                    .collect(),
+                _ => Vec::new(),
+            };
+            for ((s, e), context) in ranges {
+                values.push((start + s, start + e, &html[start + s..start + e], context));
+            }
+        }
+
+        if tag == "style" || tag == "script" {
+            let close = lower[i..]
+                .find(&format!("</{}", tag))
+                .map(|n| i + n)
+                .unwrap_or(html.len());
+            if tag == "style" {
+                for (s, e) in css_values(&html[i..close]) {
+                    values.push((i + s, i + e, &html[i + s..i + e], Context::Css));
+                }
+            }
+            i = close;
+        }
+    }
+    values
+}
+
+/// The byte range of each candidate URL in a `srcset`. A candidate is a URL
+/// followed by an optional width or density, and candidates are separated
+/// by commas.
+fn srcset_values(srcset: &str) -> Vec<(usize, usize)> {
+    let bytes = srcset.as_bytes();
+    let mut ranges = Vec::new();
+    let mut i = 0;
+    loop {
+        while i < bytes.len() && (bytes[i].is_ascii_whitespace() || bytes[i] == b',') {
+            i += 1;
+        }
+        if i >= bytes.len() {
+            return ranges;
+        }
+        let start = i;
+        while i < bytes.len() && !bytes[i].is_ascii_whitespace() {
+            i += 1;
+        }
+        let mut end = i;
+        while end > start && bytes[end - 1] == b',' {
+            end -= 1;
+        }
+        ranges.push((start, end));
+        // A trailing comma ends the candidate; otherwise skip its descriptor
+        if end == i {
+            while i < bytes.len() && bytes[i] != b',' {
+                i += 1;
+            }
+        }
+    }
+}
+
+/// The byte range of each URL in CSS: the inside of `url()`, without
+/// quotes, and the string after `@import`. Comments are skipped.
+fn css_values(css: &str) -> Vec<(usize, usize)> {
+    let lower = css.to_ascii_lowercase();
+    let bytes = css.as_bytes();
+    let skip_whitespace = |mut i: usize| {
+        while i < bytes.len() && bytes[i].is_ascii_whitespace() {
+            i += 1;
+        }
+        i
+    };
+    // The inside of a quoted string starting at `i`, and the offset past it
+    let quoted = |i: usize| -> Option<((usize, usize), usize)> {
+        let quote = *bytes.get(i).filter(|b| **b == b'"' || **b == b'\'')? as char;
+        let start = i + 1;
+        let end = css[start..]
+            .find(quote)
+            .map(|n| start + n)
+            .unwrap_or(css.len());
+        Some(((start, end), (end + 1).min(css.len())))
+    };
+
+    let mut ranges = Vec::new();
+    let mut i = 0;
+    while i < css.len() {
+        let next = ["/*", "url(", "@import"]
+            .iter()
+            .filter_map(|token| lower[i..].find(token).map(|n| (i + n, *token)))
+            .min();
+        let Some((at, token)) = next else {
+            break;
+        };
+        i = at + token.len();
+        match token {
+            "/*" => {
+                i = css[i..].find("*/").map(|n| i + n + 2).unwrap_or(css.len());
+            }
+            "url(" => {
+                let part_of_name = css[..at]
+                    .chars()
+                    .next_back()
+                    .is_some_and(|c| c.is_alphanumeric() || c == '-' || c == '_');
+                if part_of_name {
+                    continue;
+                }
+                let j = skip_whitespace(i);
+                if let Some((range, after)) = quoted(j) {
+                    ranges.push(range);
+                    i = after;
+                } else {
+                    let end = css[j..].find(')').map(|n| j + n).unwrap_or(css.len());
+                    let value = css[j..end].trim_end();
+                    ranges.push((j, j + value.len()));
+                    i = end;
+                }
+            }
+            _ => {
+                // `@import url(...)` is picked up as a url()
+                if let Some((range, after)) = quoted(skip_whitespace(i)) {
+                    ranges.push(range);
+                    i = after;
+                }
+            }
+        }
+    }
+    ranges
+}
+
+/// The page's own `<base href>`, if it has one.
+fn base_href(html: &str) -> Option<String> {
+    find_values(html, &[("base", "href")])
+        .first()
+        .map(|(_, _, v)| decode_entities(v.trim()))
+}
+
+/// Turns a page into a snapshot that works from disk. Assets in `stored`
+/// point at their local filenames; every other asset and link points at
+/// the live site, and any `<base>` is emptied so the local names resolve
+/// next to the snapshot.
+pub fn build_snapshot(html: &str, page_url: &Url, stored: &HashMap<Url, String>) -> String {
+    let mut replacements: Vec<(usize, usize, String)> = page_assets(html, page_url)
+        .into_iter()
+        .chain(
+            link_references(html, page_url)
+                .into_iter()
+                .map(|r| (r, Context::Attribute)),
+        )
+        .map(|(r, context)| (r.start, r.end, replacement(&r.url, stored, context)))
+        .collect();
+    replacements.extend(
+        find_values(html, &[("base", "href")])
+            .into_iter()
+            .map(|(start, end, _)| (start, end, String::new())),
+    );
+    splice(html, replacements)
+}
+
+/// What a reference to `url` becomes: the stored asset's local name, or the
+/// absolute URL escaped for where it sits.
+fn replacement(url: &Url, stored: &HashMap<Url, String>, context: Context) -> String {
+    if let Some(local) = stored.get(url) {
+        return urlencoding::encode(local).into_owned();
+    }
+    let url = url.as_str();
+    // Characters that would end an unquoted url() or the string around it
+    let css = || {
+        url.replace('(', "%28")
+            .replace(')', "%29")
+            .replace('\'', "%27")
+            .replace('"', "%22")
+    };
+    match context {
+        Context::Attribute => url.replace('&', "&amp;").replace('"', "&quot;"),
+        Context::Srcset => url
+            .replace(',', "%2C")
+            .replace('&', "&amp;")
+            .replace('"', "&quot;"),
+        Context::StyleAttribute => css().replace('&', "&amp;"),
+        Context::Css => css(),
+    }
+}
+
+/// Replaces each byte range in `text`. Ranges must not overlap.
+fn splice(text: &str, mut replacements: Vec<(usize, usize, String)>) -> String {
+    replacements.sort_by_key(|(start, _, _)| *start);
+    let mut out = String::with_capacity(text.len());
+    let mut last = 0;
+    for (start, end, value) in replacements {
+        out.push_str(&text[last..start]);
+        out.push_str(&value);
+        last = end;
+    }
+    out.push_str(&text[last..]);
+    out
+}
+
+/// A file extension for something downloaded from `url`: the one in the URL
+/// if it looks like one, or else one for its content type.
+pub fn file_extension(url: &Url, content_type: Option<&str>) -> Option<String> {
+    let from_url = url
+        .path_segments()
+        .and_then(|mut s| s.next_back())
+        .and_then(|name| name.rsplit_once('.'))
+        .map(|(_, ext)| ext.to_ascii_lowercase())
+        .filter(|ext| {
+            !ext.is_empty() && ext.len() <= 5 && ext.chars().all(|c| c.is_ascii_alphanumeric())
+        });
+    from_url.or_else(|| extension_for_type(content_type?).map(String::from))
+}
+
+/// The usual file extension for a `Content-Type` header value.
+pub fn extension_for_type(content_type: &str) -> Option<&'static str> {
+    let mime = content_type.split(';').next()?.trim().to_ascii_lowercase();
+    Some(match mime.as_str() {
+        "text/css" => "css",
+        "text/javascript" | "application/javascript" => "js",
+        "image/png" => "png",
+        "image/jpeg" => "jpg",
+        "image/gif" => "gif",
+        "image/webp" => "webp",
+        "image/avif" => "avif",
+        "image/svg+xml" => "svg",
+        "image/x-icon" | "image/vnd.microsoft.icon" => "ico",
+        _ => return None,
+    })
+}
+
+/// A flat, synced filename for the `index`th stored asset.
+pub fn asset_filename(index: usize, url: &Url, content_type: Option<&str>) -> String {
+    match file_extension(url, content_type) {
+        Some(ext) => format!("asset-{:03}.{}", index, ext),
+        None => format!("asset-{:03}", index),
+    }
+}
+
+/// Where the snapshot of `bookmark` lives.
+pub fn snapshot_path(cache_dir: &Path, bookmark: &str) -> PathBuf {
+    cache_dir.join(bookmark).join(SNAPSHOT_FILENAME)
+}
+
+pub fn has_snapshot(cache_dir: &Path, bookmark: &str) -> bool {
+    snapshot_path(cache_dir, bookmark).is_file()
+}
+
+/// Writes a snapshot and its assets to `cache/{bookmark}/`. Assets go first
+/// and the page last, so a snapshot that exists is always complete.
+pub fn write_snapshot(
+    cache_dir: &Path,
+    bookmark: &str,
+    html: &str,
+    assets: &[(String, Vec<u8>)],
+) -> io::Result<PathBuf> {
+    let bad_asset = assets
+        .iter()
+        .map(|(name, _)| name.as_str())
+        .find(|n| !is_plain_filename(n) || *n == SNAPSHOT_FILENAME);
+    let bad_bookmark = Some(bookmark).filter(|b| !is_plain_filename(b));
+    if let Some(bad) = bad_bookmark.or(bad_asset) {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidInput,
+            format!("invalid cache filename: {}", bad),
+        ));
+    }
+
+    let dir = cache_dir.join(bookmark);
+    fs::create_dir_all(&dir)?;
+    for (name, content) in assets {
+        write_atomically(&dir.join(name), content)?;
+    }
+    let path = dir.join(SNAPSHOT_FILENAME);
+    write_atomically(&path, html.as_bytes())?;
+    Ok(path)
+}
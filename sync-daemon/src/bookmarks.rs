@@ -0,0 +1,93 @@
+use crate::normalize::{normalize_url, NormalizeOptions};
+use crate::shortcut::UrlShortcut;
+use std::collections::{HashMap, HashSet};
+use std::fs;
+use std::io;
+use std::path::Path;
+
+/// Maps normalized URLs to the `.url` anga that bookmark them, so that
+/// `https://x.com/a/` and `http://x.com/a?utm_source=feed` find the same
+/// bookmark.
+#[derive(Debug, Default)]
+pub struct BookmarkIndex {
+    opts: NormalizeOptions,
+    by_url: HashMap<String, Vec<String>>,
+}
+
+impl BookmarkIndex {
+    pub fn new(opts: NormalizeOptions) -> Self {
+        BookmarkIndex {
+            opts,
+            by_url: HashMap::new(),
+        }
+    }
+
+    /// Builds the index from every `.url` file in `anga_dir`. A missing
+    /// directory produces an empty index.
+    pub fn load(anga_dir: &Path, opts: NormalizeOptions) -> io::Result<Self> {
+        Self::load_excluding(anga_dir, opts, &HashSet::new())
+    }
+
+    /// Like `load`, but leaves out the given filenames, such as hidden anga.
+    pub fn load_excluding(
+        anga_dir: &Path,
+        opts: NormalizeOptions,
+        exclude: &HashSet<String>,
+    ) -> io::Result<Self> {
+        let mut index = BookmarkIndex::new(opts);
+        if !anga_dir.exists() {
+            return Ok(index);
+        }
+
+        let mut filenames: Vec<String> = fs::read_dir(anga_dir)?
+            .filter_map(|e| e.ok())
+            .filter_map(|e| e.file_name().into_string().ok())
+            .filter(|n| n.ends_with(".url") && !n.starts_with('.'))
+            .filter(|n| !exclude.contains(n))
+            .collect();
+        // Timestamped names sort chronologically, so lookups list the oldest first
+        filenames.sort();
+
+        for filename in filenames {
+            if let Ok(shortcut) = read_shortcut(&anga_dir.join(&filename)) {
+                index.insert(&shortcut.url, &filename);
+            }
+        }
+
+        Ok(index)
+    }
+
+    pub fn insert(&mut self, url: &str, filename: &str) {
+        self.by_url
+            .entry(normalize_url(url, &self.opts))
+            .or_default()
+            .push(filename.to_string());
+    }
+
+    /// Returns the filenames of every bookmark matching `url`, oldest first.
+    pub fn lookup(&self, url: &str) -> &[String] {
+        self.by_url
+            .get(&normalize_url(url, &self.opts))
+            .map(|v| v.as_slice())
+            .unwrap_or(&[])
+    }
+
+    pub fn contains(&self, url: &str) -> bool {
+        !self.lookup(url).is_empty()
+    }
+
+    pub fn len(&self) -> usize {
+        self.by_url.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.by_url.is_empty()
+    }
+}
+
+/// Reads and parses a `.url` file. Malformed files are reported as
+/// `io::ErrorKind::InvalidData`.
+pub fn read_shortcut(path: &Path) -> io::Result<UrlShortcut> {
+    let bytes = fs::read(path)?;
+    UrlShortcut::from_bytes(&bytes).map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))
+}
@@ -0,0 +1,80 @@
+use crate::manifest::BrowserFamily;
+
+/// The browser extension that launched the native host.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Caller {
+    pub family: BrowserFamily,
+    /// The extension id (Firefox) or `chrome-extension://{id}/` origin
+    /// (Chromium) the browser passed.
+    pub extension: String,
+    /// Short lowercase browser name recorded in meta, such as `firefox`,
+    /// `librewolf`, `chrome`, `brave` or `edge`.
+    pub browser: String,
+}
+
+/// Browser names recognised in the parent process name, most specific
+/// first since Chromium forks tend to include "chrome" in theirs.
+const BROWSER_PROCESSES: &[(&str, &str)] = &[
+    ("librewolf", "librewolf"),
+    ("firefox", "firefox"),
+    ("brave", "brave"),
+    ("msedge", "edge"),
+    ("microsoft-edge", "edge"),
+    ("vivaldi", "vivaldi"),
+    ("chromium", "chromium"),
+    ("chrome", "chrome"),
+];
+
+/// Works out who launched the host from its arguments. Firefox passes the
+/// manifest path and then the extension id; Chromium-family browsers pass
+/// the extension's origin, plus `--parent-window` on Windows. The parent
+/// process name, when known, narrows down which browser of the family it
+/// is. Returns `None` when the arguments aren't a browser's.
+pub fn detect_caller(args: &[String], parent_process: Option<&str>) -> Option<Caller> {
+    let (family, extension) = match args {
+        [origin, ..] if origin.starts_with("chrome-extension://") => {
+            (BrowserFamily::Chromium, origin.clone())
+        }
+        [manifest, id, ..] if manifest.ends_with(".json") => (BrowserFamily::Firefox, id.clone()),
+        _ => return None,
+    };
+
+    let named = parent_process.map(str::to_lowercase).and_then(|process| {
+        BROWSER_PROCESSES
+            .iter()
+            .find(|(pattern, _)| process.contains(pattern))
+            .map(|(_, name)| *name)
+    });
+    let browser = match (family, named) {
+        (BrowserFamily::Firefox, Some(name @ ("firefox" | "librewolf"))) => name,
+        (BrowserFamily::Firefox, _) => "firefox",
+        (BrowserFamily::Chromium, Some(name)) if name != "firefox" && name != "librewolf" => name,
+        (BrowserFamily::Chromium, _) => "chromium",
+    };
+
+    Some(Caller {
+        family,
+        extension,
+        browser: browser.to_string(),
+    })
+}
+
+/// The name of this process's parent, which for a native host is usually
+/// the browser.
+pub fn parent_process_name() -> Option<String> {
+    #[cfg(unix)]
+    {
+        let ppid = std::os::unix::process::parent_id();
+        let out = std::process::Command::new("ps")
+            .args(["-o", "comm=", "-p", &ppid.to_string()])
+            .output()
+            .ok()
+            .filter(|out| out.status.success())?;
+        let name = String::from_utf8_lossy(&out.stdout).trim().to_string();
+        Some(name).filter(|n| !n.is_empty())
+    }
+    #[cfg(not(unix))]
+    {
+        None
+    }
+}
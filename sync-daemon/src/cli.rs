@@ -0,0 +1,369 @@
+use crate::anga::AngaKind;
+use std::path::PathBuf;
+
+pub const USAGE: &str = "\
+Usage: savebutton-sync-daemon [COMMAND]
+
+With no command, serves native messages from the browser on stdin/stdout.
+
+Commands:
+  sync                       Sync with the server now
+  status                     Show configuration, daemon and sync status
+  config show                Show the server, email and whether a password is set
+  config set KEY [VALUE]     Set server, email or password (read from stdin if omitted)
+  add-url URL [--title T] [--archive]
+                             Save a bookmark, with --archive also snapshotting
+                             the page into the cache
+  add-note [TEXT]            Save a note (read from stdin if omitted)
+  add-file PATH              Save a copy of a file
+  import-bookmarks PATH [--dry-run]
+                             Save the bookmarks in an exported bookmarks.html
+                             that aren't bookmarked yet
+  import-places PATH [--history MIN_VISITS] [--dry-run]
+                             Save the bookmarks in a Firefox profile or its
+                             places.sqlite, with --history also the pages
+                             visited at least MIN_VISITS times
+  list [--kind K] [--since DATE] [--until DATE] [--limit N]
+                             List anga, newest first
+  search QUERY [--tag T]... [--limit N]
+                             Search anga and their meta
+  find-in-pdfs PHRASE [--limit N]
+                             List PDFs containing PHRASE, with the pages it's on
+  dead-links [--check]       List bookmarks whose links are dead, with --check
+                             first checking the links that are due
+  verify                     Check local bookmarks and meta for problems
+  install-host [--check] [--chromium-origin ORIGIN]...
+                             Register the native messaging host with every
+                             browser found, or with --check only report on it
+  uninstall-host             Remove the host's registrations
+  --daemon                   Run standalone, without a browser
+  help                       Show this message
+";
+
+/// Keys accepted by `config set`.
+pub const CONFIG_KEYS: &[&str] = &["server", "email", "password"];
+
+/// What the binary was asked to do, decided from its arguments.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Command {
+    /// Launched by a browser: serve native messages on stdin/stdout.
+    NativeHost,
+    /// `--daemon`: run without a browser until told to stop.
+    Daemon,
+    Help,
+    Sync,
+    Status,
+    ConfigShow,
+    ConfigSet {
+        key: String,
+        value: Option<String>,
+    },
+    AddUrl {
+        url: String,
+        title: Option<String>,
+        /// Snapshot the page, whatever `[archive] enabled` says.
+        archive: bool,
+    },
+    AddNote {
+        text: Option<String>,
+    },
+    AddFile {
+        path: PathBuf,
+    },
+    ImportBookmarks {
+        path: PathBuf,
+        /// Report what would be imported without saving anything.
+        dry_run: bool,
+    },
+    ImportPlaces {
+        /// A Firefox profile directory or its `places.sqlite`.
+        path: PathBuf,
+        /// Also import pages in history visited at least this often.
+        history_min_visits: Option<u32>,
+        dry_run: bool,
+    },
+    List {
+        kind: Option<AngaKind>,
+        since: Option<String>,
+        until: Option<String>,
+        limit: Option<usize>,
+    },
+    Search {
+        query: String,
+        tags: Vec<String>,
+        limit: Option<usize>,
+    },
+    FindInPdfs {
+        phrase: String,
+        limit: Option<usize>,
+    },
+    DeadLinks {
+        /// Check the links that are due before listing.
+        check: bool,
+    },
+    Verify,
+    InstallHost {
+        /// Only report on existing registrations.
+        check: bool,
+        /// `chrome-extension://{id}/` origins for Chromium-family browsers,
+        /// which are skipped when there are none.
+        chromium_origins: Vec<String>,
+    },
+    UninstallHost,
+}
+
+/// Whether `arg` is one browsers pass when launching a native host: Firefox
+/// passes the manifest path, Chromium-family browsers the caller's origin.
+fn is_browser_arg(arg: &str) -> bool {
+    arg.ends_with(".json") || arg.contains("://")
+}
+
+/// Positional arguments, `--name value` (or `--name=value`) options and
+/// `--name` flags.
+#[derive(Debug, Default)]
+struct Parsed {
+    positional: Vec<String>,
+    options: Vec<(String, String)>,
+    flags: Vec<String>,
+}
+
+impl Parsed {
+    fn new(args: &[String], allowed: &[&str]) -> Result<Self, String> {
+        Self::with_flags(args, allowed, &[])
+    }
+
+    fn with_flags(args: &[String], allowed: &[&str], flags: &[&str]) -> Result<Self, String> {
+        let mut parsed = Parsed::default();
+        let mut args = args.iter();
+
+        while let Some(arg) = args.next() {
+            let Some(option) = arg.strip_prefix("--") else {
+                parsed.positional.push(arg.clone());
+                continue;
+            };
+            if flags.contains(&option) {
+                parsed.flags.push(option.to_string());
+                continue;
+            }
+            let (name, value) = match option.split_once('=') {
+                Some((name, value)) => (name, value.to_string()),
+                None => {
+                    let value = args
+                        .next()
+                        .ok_or_else(|| format!("--{} needs a value", option))?;
+                    (option, value.clone())
+                }
+            };
+            if !allowed.contains(&name) {
+                return Err(format!("Unknown option: --{}", name));
+            }
+            parsed.options.push((name.to_string(), value));
+        }
+
+        Ok(parsed)
+    }
+
+    fn option(&self, name: &str) -> Option<String> {
+        self.options
+            .iter()
+            .rev()
+            .find(|(n, _)| n == name)
+            .map(|(_, v)| v.clone())
+    }
+
+    fn flag(&self, name: &str) -> bool {
+        self.flags.iter().any(|f| f == name)
+    }
+
+    fn all(&self, name: &str) -> Vec<String> {
+        self.options
+            .iter()
+            .filter(|(n, _)| n == name)
+            .map(|(_, v)| v.clone())
+            .collect()
+    }
+
+    fn limit(&self) -> Result<Option<usize>, String> {
+        self.option("limit")
+            .map(|v| v.parse().map_err(|_| format!("Invalid limit: {}", v)))
+            .transpose()
+    }
+
+    /// The positional arguments, which must number between `min` and `max`.
+    fn positional(&self, min: usize, max: usize) -> Result<&[String], String> {
+        let n = self.positional.len();
+        if n < min {
+            Err("Missing argument".to_string())
+        } else if n > max {
+            Err(format!("Unexpected argument: {}", self.positional[max]))
+        } else {
+            Ok(&self.positional)
+        }
+    }
+}
+
+/// Decides what to do from the arguments after the program name. Anything a
+/// browser might pass starts the native messaging host, as does no argument
+/// at all.
+pub fn parse_args(args: &[String]) -> Result<Command, String> {
+    if args.iter().any(|a| a == "--daemon") {
+        return Ok(Command::Daemon);
+    }
+    let Some((command, rest)) = args.split_first() else {
+        return Ok(Command::NativeHost);
+    };
+    if is_browser_arg(command) {
+        return Ok(Command::NativeHost);
+    }
+    if rest.iter().any(|a| a == "--help" || a == "-h") {
+        return Ok(Command::Help);
+    }
+
+    match command.as_str() {
+        "help" | "--help" | "-h" => Ok(Command::Help),
+        "sync" => {
+            Parsed::new(rest, &[])?.positional(0, 0)?;
+            Ok(Command::Sync)
+        }
+        "status" => {
+            Parsed::new(rest, &[])?.positional(0, 0)?;
+            Ok(Command::Status)
+        }
+        "config" => {
+            let parsed = Parsed::new(rest, &[])?;
+            match parsed.positional(1, 3)? {
+                [action] if action == "show" => Ok(Command::ConfigShow),
+                [action, key, value @ ..] if action == "set" && value.len() <= 1 => {
+                    if !CONFIG_KEYS.contains(&key.as_str()) {
+                        return Err(format!(
+                            "Unknown config key: {} (expected one of {})",
+                            key,
+                            CONFIG_KEYS.join(", ")
+                        ));
+                    }
+                    Ok(Command::ConfigSet {
+                        key: key.clone(),
+                        value: value.first().cloned(),
+                    })
+                }
+                _ => Err("Expected `config show` or `config set KEY [VALUE]`".to_string()),
+            }
+        }
+        "add-url" => {
+            let parsed = Parsed::with_flags(rest, &["title"], &["archive"])?;
+            let url = parsed.positional(1, 1)?[0].clone();
+            Ok(Command::AddUrl {
+                url,
+                title: parsed.option("title"),
+                archive: parsed.flag("archive"),
+            })
+        }
+        "add-note" => {
+            let parsed = Parsed::new(rest, &[])?;
+            Ok(Command::AddNote {
+                text: parsed.positional(0, 1)?.first().cloned(),
+            })
+        }
+        "add-file" => {
+            let parsed = Parsed::new(rest, &[])?;
+            Ok(Command::AddFile {
+                path: PathBuf::from(&parsed.positional(1, 1)?[0]),
+            })
+        }
+        "import-bookmarks" => {
+            let parsed = Parsed::with_flags(rest, &[], &["dry-run"])?;
+            Ok(Command::ImportBookmarks {
+                path: PathBuf::from(&parsed.positional(1, 1)?[0]),
+                dry_run: parsed.flag("dry-run"),
+            })
+        }
+        "import-places" => {
+            let parsed = Parsed::with_flags(rest, &["history"], &["dry-run"])?;
+            let history_min_visits = parsed
+                .option("history")
+                .map(|v| match v.parse() {
+                    Ok(n) if n > 0 => Ok(n),
+                    _ => Err(format!("Invalid visit count: {}", v)),
+                })
+                .transpose()?;
+            Ok(Command::ImportPlaces {
+                path: PathBuf::from(&parsed.positional(1, 1)?[0]),
+                history_min_visits,
+                dry_run: parsed.flag("dry-run"),
+            })
+        }
+        "list" => {
+            let parsed = Parsed::new(rest, &["kind", "since", "until", "limit"])?;
+            parsed.positional(0, 0)?;
+            let kind = parsed
+                .option("kind")
+                .map(|k| {
+                    serde_json::from_value(serde_json::Value::String(k.clone()))
+                        .map_err(|_| format!("Unknown kind: {}", k))
+                })
+                .transpose()?;
+            Ok(Command::List {
+                kind,
+                since: parsed.option("since"),
+                until: parsed.option("until"),
+                limit: parsed.limit()?,
+            })
+        }
+        "search" => {
+            let parsed = Parsed::new(rest, &["tag", "limit"])?;
+            let tags = parsed.all("tag");
+            let query = parsed.positional.join(" ");
+            if query.is_empty() && tags.is_empty() {
+                return Err("Missing search query".to_string());
+            }
+            Ok(Command::Search {
+                query,
+                tags,
+                limit: parsed.limit()?,
+            })
+        }
+        "find-in-pdfs" => {
+            let parsed = Parsed::new(rest, &["limit"])?;
+            let phrase = parsed.positional(1, usize::MAX)?.join(" ");
+            Ok(Command::FindInPdfs {
+                phrase,
+                limit: parsed.limit()?,
+            })
+        }
+        "dead-links" => {
+            let parsed = Parsed::with_flags(rest, &[], &["check"])?;
+            parsed.positional(0, 0)?;
+            Ok(Command::DeadLinks {
+                check: parsed.flag("check"),
+            })
+        }
+        "verify" => {
+            Parsed::new(rest, &[])?.positional(0, 0)?;
+            Ok(Command::Verify)
+        }
+        // `install-manifest` is kept as an alias for compatibility
+        "install-host" | "install-manifest" => {
+            let parsed = Parsed::with_flags(rest, &["chromium-origin"], &["check"])?;
+            parsed.positional(0, 0)?;
+            let chromium_origins = parsed.all("chromium-origin");
+            if let Some(bad) = chromium_origins
+                .iter()
+                .find(|o| !o.starts_with("chrome-extension://") || !o.ends_with('/'))
+            {
+                return Err(format!(
+                    "Invalid origin: {} (expected chrome-extension://ID/)",
+                    bad
+                ));
+            }
+            Ok(Command::InstallHost {
+                check: parsed.flag("check"),
+                chromium_origins,
+            })
+        }
+        "uninstall-host" => {
+            Parsed::new(rest, &[])?.positional(0, 0)?;
+            Ok(Command::UninstallHost)
+        }
+        other => Err(format!("Unknown command: {}", other)),
+    }
+}
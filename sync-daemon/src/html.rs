@@ -0,0 +1,276 @@
+/// One piece of a page.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Token<'a> {
+    /// A start tag. The name is lowercased, as are attribute names;
+    /// attribute values have their entities decoded.
+    Start {
+        name: String,
+        attributes: Vec<(String, String)>,
+    },
+    /// An end tag, lowercased.
+    End(String),
+    /// Text between tags, with entities still encoded. The contents of
+    /// `script`, `style`, `textarea` and `title` come through as a single
+    /// `Text`.
+    Text(&'a str),
+}
+
+impl Token<'_> {
+    /// The value of a start tag's attribute.
+    pub fn attribute(&self, name: &str) -> Option<&str> {
+        match self {
+            Token::Start { attributes, .. } => attributes
+                .iter()
+                .find(|(n, _)| n == name)
+                .map(|(_, v)| v.as_str()),
+            _ => None,
+        }
+    }
+}
+
+/// Elements that never have an end tag.
+pub const VOID_ELEMENTS: &[&str] = &[
+    "area", "base", "br", "col", "embed", "hr", "img", "input", "link", "meta", "param", "source",
+    "track", "wbr",
+];
+
+/// Elements whose contents are not markup.
+const RAW_TEXT_ELEMENTS: &[&str] = &["script", "style", "textarea", "title"];
+
+/// One `name=value` pair from a start tag. `value` is the byte range and
+/// raw text of the value, or `None` for a bare attribute.
+pub(crate) struct Attribute<'a> {
+    pub name: String,
+    pub value: Option<(usize, usize, &'a str)>,
+}
+
+/// Reads the attributes of the start tag whose name ends at `i`, and
+/// returns them with the offset just past the closing `>`.
+pub(crate) fn read_attributes(html: &str, mut i: usize) -> (Vec<Attribute<'_>>, usize) {
+    let bytes = html.as_bytes();
+    let mut attributes = Vec::new();
+
+    loop {
+        while i < bytes.len() && (bytes[i].is_ascii_whitespace() || bytes[i] == b'/') {
+            i += 1;
+        }
+        if i >= bytes.len() {
+            return (attributes, i);
+        }
+        if bytes[i] == b'>' {
+            return (attributes, i + 1);
+        }
+
+        let name_start = i;
+        while i < bytes.len() && !bytes[i].is_ascii_whitespace() && !b"=>/".contains(&bytes[i]) {
+            i += 1;
+        }
+        let name = html[name_start..i].to_ascii_lowercase();
+        while i < bytes.len() && bytes[i].is_ascii_whitespace() {
+            i += 1;
+        }
+
+        let mut value = None;
+        if i < bytes.len() && bytes[i] == b'=' {
+            i += 1;
+            while i < bytes.len() && bytes[i].is_ascii_whitespace() {
+                i += 1;
+            }
+            let (start, end) = match bytes.get(i) {
+                Some(&quote @ (b'"' | b'\'')) => {
+                    let start = i + 1;
+                    let end = html[start..]
+                        .find(quote as char)
+                        .map(|n| start + n)
+                        .unwrap_or(html.len());
+                    i = (end + 1).min(html.len());
+                    (start, end)
+                }
+                _ => {
+                    let start = i;
+                    while i < bytes.len() && !bytes[i].is_ascii_whitespace() && bytes[i] != b'>' {
+                        i += 1;
+                    }
+                    (start, i)
+                }
+            };
+            value = Some((start, end, &html[start..end]));
+        }
+
+        if name.is_empty() {
+            // Stray `=` or similar; step over it
+            i += 1;
+        } else {
+            attributes.push(Attribute { name, value });
+        }
+    }
+}
+
+/// Where the tag name starting at `i` ends.
+pub(crate) fn tag_name_end(html: &str, i: usize) -> usize {
+    html[i..]
+        .find(|c: char| !c.is_ascii_alphanumeric() && c != '-' && c != ':')
+        .map(|n| i + n)
+        .unwrap_or(html.len())
+}
+
+/// Splits a page into tags and text, with no attempt at building a tree.
+/// Real pages are often malformed, so anything unexpected is read as text
+/// rather than rejected. Comments, doctypes and processing instructions are
+/// dropped.
+pub fn tokenize(html: &str) -> Vec<Token<'_>> {
+    // ASCII lowercasing keeps byte offsets identical to `html`
+    let lower = html.to_ascii_lowercase();
+    let mut tokens = Vec::new();
+    let mut i = 0;
+    let mut text_start = 0;
+
+    while let Some(offset) = html[i..].find('<') {
+        let lt = i + offset;
+        let rest = &html[lt + 1..];
+        let starts_tag = rest.starts_with(|c: char| c.is_ascii_alphabetic());
+        let ends_tag =
+            rest.starts_with('/') && rest[1..].starts_with(|c: char| c.is_ascii_alphabetic());
+        let is_markup = rest.starts_with('!') || rest.starts_with('?');
+
+        if !starts_tag && !ends_tag && !is_markup {
+            i = lt + 1;
+            continue;
+        }
+        if text_start < lt {
+            tokens.push(Token::Text(&html[text_start..lt]));
+        }
+
+        if rest.starts_with("!--") {
+            i = html[lt..]
+                .find("-->")
+                .map(|n| lt + n + 3)
+                .unwrap_or(html.len());
+        } else if is_markup {
+            i = html[lt..]
+                .find('>')
+                .map(|n| lt + n + 1)
+                .unwrap_or(html.len());
+        } else if ends_tag {
+            let name_end = tag_name_end(html, lt + 2);
+            tokens.push(Token::End(html[lt + 2..name_end].to_ascii_lowercase()));
+            i = html[name_end..]
+                .find('>')
+                .map(|n| name_end + n + 1)
+                .unwrap_or(html.len());
+        } else {
+            let name_end = tag_name_end(html, lt + 1);
+            let name = html[lt + 1..name_end].to_ascii_lowercase();
+            let (attributes, end) = read_attributes(html, name_end);
+            let attributes = attributes
+                .into_iter()
+                .map(|a| {
+                    let value = a.value.map(|(_, _, v)| decode_entities(v));
+                    (a.name, value.unwrap_or_default())
+                })
+                .collect();
+            i = end;
+
+            let raw = RAW_TEXT_ELEMENTS.contains(&name.as_str());
+            tokens.push(Token::Start {
+                name: name.clone(),
+                attributes,
+            });
+            if raw {
+                let close = format!("</{}", name);
+                let content_end = lower[i..].find(&close).map(|n| i + n).unwrap_or(html.len());
+                if i < content_end {
+                    tokens.push(Token::Text(&html[i..content_end]));
+                }
+                tokens.push(Token::End(name));
+                i = html[content_end..]
+                    .find('>')
+                    .map(|n| content_end + n + 1)
+                    .unwrap_or(html.len());
+            }
+        }
+        text_start = i;
+    }
+
+    if text_start < html.len() {
+        tokens.push(Token::Text(&html[text_start..]));
+    }
+    tokens
+}
+
+/// Decodes numeric character references and the named entities that are
+/// common in prose. Unknown entities are left as they are.
+pub fn decode_entities(text: &str) -> String {
+    if !text.contains('&') {
+        return text.to_string();
+    }
+
+    let mut out = String::with_capacity(text.len());
+    let mut rest = text;
+    while let Some(amp) = rest.find('&') {
+        out.push_str(&rest[..amp]);
+        rest = &rest[amp..];
+
+        let decoded = rest[1..].find(';').filter(|&n| n <= 10).and_then(|n| {
+            let entity = &rest[1..1 + n];
+            let c = match entity.strip_prefix('#') {
+                Some(num) => match num.strip_prefix(['x', 'X']) {
+                    Some(hex) => u32::from_str_radix(hex, 16).ok().and_then(char::from_u32),
+                    None => num.parse().ok().and_then(char::from_u32),
+                },
+                None => named_entity(entity),
+            }?;
+            Some((c, n + 2))
+        });
+
+        match decoded {
+            Some((c, len)) => {
+                out.push(c);
+                rest = &rest[len..];
+            }
+            None => {
+                out.push('&');
+                rest = &rest[1..];
+            }
+        }
+    }
+    out.push_str(rest);
+    out
+}
+
+fn named_entity(name: &str) -> Option<char> {
+    Some(match name {
+        "amp" => '&',
+        "lt" => '<',
+        "gt" => '>',
+        "quot" => '"',
+        "apos" => '\'',
+        "nbsp" => '\u{a0}',
+        "ndash" => '–',
+        "mdash" => '—',
+        "hellip" => '…',
+        "lsquo" => '‘',
+        "rsquo" => '’',
+        "ldquo" => '“',
+        "rdquo" => '”',
+        "laquo" => '«',
+        "raquo" => '»',
+        "middot" => '·',
+        "bull" => '•',
+        "copy" => '©',
+        "reg" => '®',
+        "trade" => '™',
+        "deg" => '°',
+        "times" => '×',
+        _ => return None,
+    })
+}
+
+/// Collapses runs of whitespace, including non-breaking spaces, into single
+/// spaces and trims the ends.
+pub fn collapse_whitespace(text: &str) -> String {
+    text.split(|c: char| c.is_whitespace())
+        .filter(|w| !w.is_empty())
+        .collect::<Vec<_>>()
+        .join(" ")
+}
@@ -0,0 +1,252 @@
+use crate::bookmarks::BookmarkIndex;
+use crate::html::{collapse_whitespace, decode_entities, tokenize, Token};
+use crate::meta::{tag_key, Meta, MetaError};
+use crate::naming::{bookmark_slug, create_timestamped_file};
+use crate::shortcut::UrlShortcut;
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::io;
+use std::path::Path;
+
+/// URL schemes worth keeping as bookmarks. Bookmarklets (`javascript:`)
+/// and Firefox's saved queries (`place:`) are left out.
+const IMPORTED_SCHEMES: &[&str] = &["http", "https", "ftp", "file"];
+
+/// A bookmark read from another browser or service, before it becomes an
+/// anga.
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub struct ImportedBookmark {
+    pub url: String,
+    pub title: Option<String>,
+    /// When the bookmark was first saved, if the export says.
+    pub added: Option<DateTime<Utc>>,
+    /// Its own tags followed by the folders it was filed in, outermost first.
+    pub tags: Vec<String>,
+    pub note: Option<String>,
+    /// A Firefox keyword, typed in the address bar to open the bookmark.
+    pub keyword: Option<String>,
+}
+
+/// Reads an `ADD_DATE`. Most exporters write seconds since the epoch, but
+/// some write milliseconds or microseconds, which are told apart by size.
+pub fn parse_add_date(value: &str) -> Option<DateTime<Utc>> {
+    let n: i64 = value.trim().parse().ok().filter(|n| *n > 0)?;
+    let micros = if n >= 100_000_000_000_000 {
+        n
+    } else if n >= 100_000_000_000 {
+        n.checked_mul(1_000)?
+    } else {
+        n.checked_mul(1_000_000)?
+    };
+    DateTime::from_timestamp_micros(micros)
+}
+
+/// Adds `tag` to `tags` unless it's blank or already there, ignoring case.
+pub(crate) fn push_tag(tags: &mut Vec<String>, tag: &str) {
+    let tag = collapse_whitespace(tag);
+    if !tag.is_empty() && !tags.iter().any(|t| tag_key(t) == tag_key(&tag)) {
+        tags.push(tag);
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Reading {
+    Nothing,
+    FolderName,
+    LinkTitle,
+    Description,
+}
+
+/// Reads a Netscape bookmark file, the `bookmarks.html` that Firefox,
+/// Chrome, Safari and Pinboard export. Folders become tags, except the
+/// toolbar and "other bookmarks" roots that every browser has. `TAGS` are
+/// split on commas, `SHORTCUTURL` is a keyword, and a `<DD>` after a link
+/// becomes its note.
+pub fn parse_netscape_bookmarks(html: &str) -> Vec<ImportedBookmark> {
+    let mut bookmarks: Vec<ImportedBookmark> = Vec::new();
+    // One entry per open <DL>: the folder it lists, if it counts as a tag
+    let mut folders: Vec<Option<String>> = Vec::new();
+    let mut next_folder: Option<String> = None;
+    let mut is_root = false;
+    let mut reading = Reading::Nothing;
+    let mut text = String::new();
+    // Whether a <DD> here would describe the last link
+    let mut after_link = false;
+
+    for token in tokenize(html) {
+        let (name, is_start) = match &token {
+            Token::Text(t) => {
+                if reading != Reading::Nothing {
+                    text.push_str(t);
+                }
+                continue;
+            }
+            Token::Start { name, .. } => (name.as_str(), true),
+            Token::End(name) => (name.as_str(), false),
+        };
+
+        if reading == Reading::Description && name != "br" {
+            let note = decode_entities(&text).trim().to_string();
+            if let Some(bookmark) = bookmarks.last_mut().filter(|_| !note.is_empty()) {
+                bookmark.note = Some(note);
+            }
+            reading = Reading::Nothing;
+            after_link = false;
+        }
+
+        match (name, is_start) {
+            ("h3", true) => {
+                is_root = token.attribute("personal_toolbar_folder").is_some()
+                    || token.attribute("unfiled_bookmarks_folder").is_some();
+                reading = Reading::FolderName;
+                text.clear();
+                after_link = false;
+            }
+            ("h3", false) if reading == Reading::FolderName => {
+                let folder = collapse_whitespace(&decode_entities(&text));
+                next_folder = Some(folder).filter(|f| !is_root && !f.is_empty());
+                reading = Reading::Nothing;
+            }
+            ("dl", true) => {
+                folders.push(next_folder.take());
+                after_link = false;
+            }
+            ("dl", false) => {
+                folders.pop();
+                after_link = false;
+            }
+            ("a", true) => {
+                let Some(url) = token
+                    .attribute("href")
+                    .map(str::trim)
+                    .filter(|u| !u.is_empty())
+                else {
+                    continue;
+                };
+                let mut tags = Vec::new();
+                for tag in token.attribute("tags").unwrap_or("").split(',') {
+                    push_tag(&mut tags, tag);
+                }
+                for folder in folders.iter().flatten() {
+                    push_tag(&mut tags, folder);
+                }
+                bookmarks.push(ImportedBookmark {
+                    url: url.to_string(),
+                    added: token.attribute("add_date").and_then(parse_add_date),
+                    tags,
+                    keyword: token
+                        .attribute("shortcuturl")
+                        .map(str::trim)
+                        .filter(|k| !k.is_empty())
+                        .map(str::to_string),
+                    ..Default::default()
+                });
+                reading = Reading::LinkTitle;
+                text.clear();
+                after_link = true;
+            }
+            ("a", false) if reading == Reading::LinkTitle => {
+                let title = collapse_whitespace(&decode_entities(&text));
+                if let Some(bookmark) = bookmarks.last_mut() {
+                    bookmark.title = Some(title).filter(|t| !t.is_empty());
+                }
+                reading = Reading::Nothing;
+            }
+            ("dd", true) if after_link => {
+                reading = Reading::Description;
+                text.clear();
+            }
+            _ => {}
+        }
+    }
+
+    bookmarks
+}
+
+/// How an import went.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ImportSummary {
+    /// Bookmarks saved as new anga.
+    pub imported: usize,
+    /// Bookmarks left out because their URL is already bookmarked, or
+    /// appeared earlier in the same import.
+    pub existing: usize,
+    /// Bookmarks left out because they aren't links to pages, such as
+    /// bookmarklets.
+    pub unsupported: usize,
+}
+
+/// Saves each bookmark whose URL isn't in `index` as a `.url` anga named
+/// for the time it was added, or `now` if that isn't known. Tags, note and
+/// keyword go into a meta file with the same timestamp. Each new bookmark is added
+/// to `index`, so duplicates within `bookmarks` are skipped too. With
+/// `dry_run`, nothing is written but the summary is the same.
+pub fn import_bookmarks(
+    anga_dir: &Path,
+    meta_dir: &Path,
+    index: &mut BookmarkIndex,
+    bookmarks: &[ImportedBookmark],
+    now: DateTime<Utc>,
+    dry_run: bool,
+) -> io::Result<ImportSummary> {
+    if !dry_run {
+        fs::create_dir_all(anga_dir)?;
+        fs::create_dir_all(meta_dir)?;
+    }
+
+    let mut summary = ImportSummary::default();
+    for bookmark in bookmarks {
+        let supported =
+            url::Url::parse(&bookmark.url).is_ok_and(|u| IMPORTED_SCHEMES.contains(&u.scheme()));
+        if !supported {
+            summary.unsupported += 1;
+            continue;
+        }
+        if index.contains(&bookmark.url) {
+            summary.existing += 1;
+            continue;
+        }
+
+        let filename = if dry_run {
+            String::new()
+        } else {
+            write_bookmark(anga_dir, meta_dir, bookmark, now)?
+        };
+        index.insert(&bookmark.url, &filename);
+        summary.imported += 1;
+    }
+    Ok(summary)
+}
+
+fn write_bookmark(
+    anga_dir: &Path,
+    meta_dir: &Path,
+    bookmark: &ImportedBookmark,
+    now: DateTime<Utc>,
+) -> io::Result<String> {
+    let at = bookmark.added.unwrap_or(now);
+    let mut shortcut = UrlShortcut::new(&bookmark.url);
+    if let Some(title) = &bookmark.title {
+        shortcut = shortcut.with_title(title);
+    }
+    let filename = create_timestamped_file(
+        anga_dir,
+        at,
+        &bookmark_slug(&bookmark.url, bookmark.title.as_deref()),
+        "url",
+        shortcut.to_string().as_bytes(),
+    )?;
+
+    if !bookmark.tags.is_empty() || bookmark.note.is_some() || bookmark.keyword.is_some() {
+        let mut meta = Meta::new(&filename);
+        meta.meta.tags = bookmark.tags.clone();
+        meta.meta.note = bookmark.note.clone();
+        meta.meta.keyword = bookmark.keyword.clone();
+        let invalid = |e: MetaError| io::Error::new(io::ErrorKind::InvalidData, e);
+        meta.validate().map_err(invalid)?;
+        let text = meta.to_toml().map_err(invalid)?;
+        create_timestamped_file(meta_dir, at, "import", "toml", text.as_bytes())?;
+    }
+    Ok(filename)
+}
@@ -0,0 +1,109 @@
+use interprocess::local_socket::prelude::*;
+use interprocess::local_socket::{ListenerOptions, Name};
+use std::io::{self, Read, Write};
+use std::path::{Path, PathBuf};
+
+pub use interprocess::local_socket::{Listener, Stream};
+
+/// Largest message accepted on stdin or the socket. Firefox caps messages
+/// to the host at 4 GB, but nothing the extension sends comes close.
+pub const MAX_FRAME_LEN: usize = 100 * 1024 * 1024;
+
+/// Where the standalone daemon listens on Unix. Windows uses a per-user
+/// named pipe instead, since named pipes don't live in the filesystem.
+pub fn socket_path(kaya_dir: &Path) -> PathBuf {
+    kaya_dir.join(".daemon.sock")
+}
+
+#[cfg(unix)]
+fn socket_name(kaya_dir: &Path) -> io::Result<Name<'static>> {
+    use interprocess::local_socket::GenericFilePath;
+    socket_path(kaya_dir).to_fs_name::<GenericFilePath>()
+}
+
+#[cfg(windows)]
+fn socket_name(_kaya_dir: &Path) -> io::Result<Name<'static>> {
+    use interprocess::local_socket::GenericNamespaced;
+    let user = std::env::var("USERNAME").unwrap_or_default();
+    format!("savebutton-sync-daemon-{}", user).to_ns_name::<GenericNamespaced>()
+}
+
+/// Connects to the daemon serving `kaya_dir`. Fails if none is running.
+pub fn connect(kaya_dir: &Path) -> io::Result<Stream> {
+    Stream::connect(socket_name(kaya_dir)?)
+}
+
+/// Starts listening for clients of `kaya_dir`. Returns `None` if another
+/// daemon is already listening; a socket left behind by one that crashed
+/// is replaced.
+pub fn listen(kaya_dir: &Path) -> io::Result<Option<Listener>> {
+    if connect(kaya_dir).is_ok() {
+        return Ok(None);
+    }
+
+    let options = ListenerOptions::new()
+        .name(socket_name(kaya_dir)?)
+        .try_overwrite(true);
+    // Anyone who can connect can read and write the user's anga
+    #[cfg(unix)]
+    let options = {
+        use interprocess::os::unix::local_socket::ListenerOptionsExt;
+        options.mode(0o600)
+    };
+    #[cfg(windows)]
+    let options = {
+        use interprocess::os::windows::local_socket::ListenerOptionsExt;
+        use interprocess::os::windows::security_descriptor::SecurityDescriptor;
+        // A protected DACL with one entry: full access for the pipe's
+        // owner, which is the user running the daemon. The default DACL
+        // would also let Everyone read.
+        let sddl = widestring::U16CString::from_str("D:P(A;;GA;;;OW)")
+            .map_err(|e| io::Error::new(io::ErrorKind::InvalidInput, e))?;
+        options.security_descriptor(SecurityDescriptor::deserialize(&sddl)?)
+    };
+
+    options.create_sync().map(Some)
+}
+
+/// Removes the socket file so clients stop trying to connect. Listeners
+/// do this themselves when dropped, but a daemon that exits from a signal
+/// handler never drops its listener.
+pub fn remove_socket(kaya_dir: &Path) {
+    #[cfg(unix)]
+    let _ = std::fs::remove_file(socket_path(kaya_dir));
+    #[cfg(windows)]
+    let _ = kaya_dir;
+}
+
+/// Reads one length-prefixed message, as used by native messaging: a
+/// native-endian `u32` length followed by that many bytes of JSON. Returns
+/// `None` when the other end closes the connection between messages.
+pub fn read_frame(reader: &mut impl Read) -> io::Result<Option<Vec<u8>>> {
+    let mut len_bytes = [0u8; 4];
+    match reader.read_exact(&mut len_bytes) {
+        Ok(_) => {}
+        Err(e) if e.kind() == io::ErrorKind::UnexpectedEof => return Ok(None),
+        Err(e) => return Err(e),
+    }
+
+    let len = u32::from_ne_bytes(len_bytes) as usize;
+    if len == 0 || len > MAX_FRAME_LEN {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidData,
+            format!("Invalid message length: {}", len),
+        ));
+    }
+
+    let mut buffer = vec![0u8; len];
+    reader.read_exact(&mut buffer)?;
+    Ok(Some(buffer))
+}
+
+/// Writes one message in the framing `read_frame` expects.
+pub fn write_frame(writer: &mut impl Write, frame: &[u8]) -> io::Result<()> {
+    let len = u32::try_from(frame.len())
+        .map_err(|_| io::Error::new(io::ErrorKind::InvalidInput, "Message too long"))?;
+    writer.write_all(&len.to_ne_bytes())?;
+    writer.write_all(frame)?;
+    writer.flush()
+}
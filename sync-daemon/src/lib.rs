@@ -1,3 +1,30 @@
+pub mod anga;
+pub mod archive;
+pub mod bookmarks;
+pub mod caller;
+pub mod cli;
+pub mod html;
+pub mod import;
+pub mod ipc;
+pub mod links;
+pub mod lock;
+pub mod manifest;
+pub mod meta;
+pub mod naming;
+pub mod normalize;
+pub mod pdf_text;
+pub mod places;
+pub mod preview;
+pub mod readable;
+pub mod schedule;
+pub mod search;
+pub mod shortcut;
+pub mod shutdown;
+pub mod tags;
+pub mod thumbnail;
+pub mod verify;
+pub mod watch;
+
 use std::collections::HashSet;
 
 pub fn parse_server_file_listing(body: &str) -> HashSet<String> {
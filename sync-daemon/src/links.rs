@@ -0,0 +1,277 @@
+use crate::naming::write_atomically;
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, VecDeque};
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::time::{Duration, Instant};
+use url::Url;
+
+/// Where link health is kept, under `~/.kaya`. It's outside `cache/`
+/// because the result depends on the device's network, so it isn't synced.
+pub const LINK_STATE_DIR: &str = ".state/links";
+
+/// How dead links are found. Stored in `~/.kaya/.config` under `[links]`;
+/// missing keys take their default values.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct LinkCheckOptions {
+    pub enabled: bool,
+    /// Hours between checks of a link that was alive last time.
+    pub interval_hours: u64,
+    /// Hours between checks of a link that failed last time.
+    pub retry_hours: u64,
+    /// Consecutive failed checks before a link counts as dead.
+    pub dead_after: u32,
+    /// Requests per minute across all hosts.
+    pub requests_per_minute: u32,
+    /// Seconds between two requests to the same host.
+    pub host_delay_secs: u64,
+    pub timeout_secs: u64,
+}
+
+impl Default for LinkCheckOptions {
+    fn default() -> Self {
+        LinkCheckOptions {
+            enabled: true,
+            interval_hours: 7 * 24,
+            retry_hours: 24,
+            dead_after: 3,
+            requests_per_minute: 30,
+            host_delay_secs: 10,
+            timeout_secs: 20,
+        }
+    }
+}
+
+/// What one request to a link found: the final status after redirects and
+/// where they ended up, or why there was no response.
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub struct CheckResult {
+    pub status: Option<u16>,
+    pub final_url: Option<String>,
+    pub error: Option<String>,
+}
+
+/// What a status code says about a link.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Verdict {
+    Alive,
+    Failed,
+    /// The site answered but won't say, such as when it blocks robots or
+    /// asks for a login.
+    Unknown,
+}
+
+impl CheckResult {
+    pub fn verdict(&self) -> Verdict {
+        match self.status {
+            None => Verdict::Failed,
+            Some(200..=399) => Verdict::Alive,
+            Some(401 | 403 | 407 | 429) => Verdict::Unknown,
+            Some(_) => Verdict::Failed,
+        }
+    }
+}
+
+/// The last check of a bookmark's URL.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct LinkStatus {
+    pub url: String,
+    pub checked_at: DateTime<Utc>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub status: Option<u16>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+    /// Where redirects ended up, when that isn't `url`.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub redirected_to: Option<String>,
+    /// The last time the link was seen alive.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub alive_at: Option<DateTime<Utc>>,
+    /// Failed checks in a row.
+    #[serde(default)]
+    pub failures: u32,
+}
+
+impl LinkStatus {
+    /// The status after checking `url` at `now`, carrying over what
+    /// `previous` knew about the same URL.
+    pub fn record(
+        previous: Option<&LinkStatus>,
+        url: &str,
+        result: CheckResult,
+        now: DateTime<Utc>,
+    ) -> LinkStatus {
+        // A bookmark edited to point elsewhere starts over
+        let previous = previous.filter(|p| p.url == url);
+        let verdict = result.verdict();
+        let failures = previous.map_or(0, |p| p.failures);
+
+        LinkStatus {
+            url: url.to_string(),
+            checked_at: now,
+            status: result.status,
+            error: result.error,
+            redirected_to: result.final_url.filter(|u| u != url),
+            alive_at: match verdict {
+                Verdict::Alive => Some(now),
+                _ => previous.and_then(|p| p.alive_at),
+            },
+            failures: match verdict {
+                Verdict::Alive => 0,
+                Verdict::Failed => failures + 1,
+                Verdict::Unknown => failures,
+            },
+        }
+    }
+
+    pub fn is_dead(&self, opts: &LinkCheckOptions) -> bool {
+        self.failures >= opts.dead_after.max(1)
+    }
+
+    /// Whether the link should be checked again at `now`.
+    pub fn is_due(&self, opts: &LinkCheckOptions, now: DateTime<Utc>) -> bool {
+        let hours = if self.failures > 0 {
+            opts.retry_hours
+        } else {
+            opts.interval_hours
+        };
+        now - self.checked_at >= chrono::Duration::hours(hours as i64)
+    }
+}
+
+/// The file in `state_dir` holding the status of the bookmark `filename`.
+pub fn link_status_path(state_dir: &Path, filename: &str) -> PathBuf {
+    state_dir.join(format!("{}.toml", filename))
+}
+
+/// Writes the status of the bookmark `filename` to `state_dir`, creating
+/// the directory if needed.
+pub fn write_link_status(state_dir: &Path, filename: &str, status: &LinkStatus) -> io::Result<()> {
+    fs::create_dir_all(state_dir)?;
+    let text = toml::to_string(status).map_err(io::Error::other)?;
+    write_atomically(&link_status_path(state_dir, filename), text.as_bytes())
+}
+
+/// Reads the status of the bookmark `filename` from `state_dir`, if there
+/// is one.
+pub fn read_link_status(state_dir: &Path, filename: &str) -> io::Result<Option<LinkStatus>> {
+    match fs::read_to_string(link_status_path(state_dir, filename)) {
+        Ok(text) => toml::from_str(&text)
+            .map(Some)
+            .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e)),
+        Err(e) if e.kind() == io::ErrorKind::NotFound => Ok(None),
+        Err(e) => Err(e),
+    }
+}
+
+/// A bookmark whose link is dead.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct DeadLink {
+    pub filename: String,
+    #[serde(flatten)]
+    pub status: LinkStatus,
+}
+
+/// The bookmarks in `anga_dir` whose last checks found their link dead,
+/// newest first.
+pub fn dead_links(
+    anga_dir: &Path,
+    state_dir: &Path,
+    opts: &LinkCheckOptions,
+) -> io::Result<Vec<DeadLink>> {
+    if !state_dir.exists() {
+        return Ok(Vec::new());
+    }
+
+    let mut dead = Vec::new();
+    for entry in fs::read_dir(state_dir)?.filter_map(|e| e.ok()) {
+        let Some(filename) = entry
+            .file_name()
+            .to_str()
+            .and_then(|n| n.strip_suffix(".toml"))
+            .map(str::to_string)
+        else {
+            continue;
+        };
+        if !filename.ends_with(".url") || !anga_dir.join(&filename).is_file() {
+            continue;
+        }
+        match read_link_status(state_dir, &filename) {
+            Ok(Some(status)) if status.is_dead(opts) => dead.push(DeadLink { filename, status }),
+            Ok(_) => {}
+            Err(e) => log::warn!("Unreadable link status for {}: {}", filename, e),
+        }
+    }
+
+    dead.sort_by(|a, b| b.filename.cmp(&a.filename));
+    Ok(dead)
+}
+
+/// Reorders links so that consecutive ones are on different hosts where
+/// possible, taking one from each host in turn. Links on the same host keep
+/// their order.
+pub fn interleave_by_host<T>(links: Vec<(T, Url)>) -> Vec<(T, Url)> {
+    let mut hosts: Vec<String> = Vec::new();
+    let mut queues: HashMap<String, VecDeque<(T, Url)>> = HashMap::new();
+    for (item, url) in links {
+        let host = url.host_str().unwrap_or("").to_string();
+        if !queues.contains_key(&host) {
+            hosts.push(host.clone());
+        }
+        queues.entry(host).or_default().push_back((item, url));
+    }
+
+    let mut out = Vec::new();
+    while !queues.is_empty() {
+        for host in &hosts {
+            if let Some(queue) = queues.get_mut(host) {
+                if let Some(link) = queue.pop_front() {
+                    out.push(link);
+                }
+                if queue.is_empty() {
+                    queues.remove(host);
+                }
+            }
+        }
+    }
+    out
+}
+
+/// Spaces requests out to stay under a rate limit overall and to leave a
+/// gap between requests to the same host.
+#[derive(Debug)]
+pub struct Pacer {
+    gap: Duration,
+    host_gap: Duration,
+    last: Option<Instant>,
+    last_by_host: HashMap<String, Instant>,
+}
+
+impl Pacer {
+    pub fn new(opts: &LinkCheckOptions) -> Self {
+        Pacer {
+            gap: Duration::from_secs(60) / opts.requests_per_minute.max(1),
+            host_gap: Duration::from_secs(opts.host_delay_secs),
+            last: None,
+            last_by_host: HashMap::new(),
+        }
+    }
+
+    /// Books the earliest time, no sooner than `now`, that a request to
+    /// `host` may start, and returns it.
+    pub fn reserve(&mut self, host: &str, now: Instant) -> Instant {
+        let mut at = now;
+        if let Some(last) = self.last {
+            at = at.max(last + self.gap);
+        }
+        if let Some(&last) = self.last_by_host.get(host) {
+            at = at.max(last + self.host_gap);
+        }
+        self.last = Some(at);
+        self.last_by_host.insert(host.to_string(), at);
+        at
+    }
+}
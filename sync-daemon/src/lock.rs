@@ -0,0 +1,48 @@
+use fs2::FileExt;
+use std::fs::{self, File, OpenOptions};
+use std::io::{self, Write};
+use std::path::Path;
+
+/// An exclusive lock on a `~/.kaya` directory, held by whichever process is
+/// syncing it. The OS releases it when the file is closed, so a crashed
+/// holder never leaves a stale lock behind.
+#[derive(Debug)]
+pub struct SyncLock {
+    file: File,
+}
+
+impl SyncLock {
+    /// Takes the lock at `path` if no other process holds it. The holder's
+    /// process id is written into the file to help with debugging.
+    pub fn try_acquire(path: &Path) -> io::Result<Option<SyncLock>> {
+        let mut file = OpenOptions::new()
+            .read(true)
+            .write(true)
+            .create(true)
+            .truncate(false)
+            .open(path)?;
+
+        match file.try_lock_exclusive() {
+            Ok(()) => {}
+            Err(e) if e.kind() == fs2::lock_contended_error().kind() => return Ok(None),
+            Err(e) => return Err(e),
+        }
+
+        file.set_len(0)?;
+        writeln!(file, "{}", std::process::id())?;
+        file.flush()?;
+        Ok(Some(SyncLock { file }))
+    }
+}
+
+impl Drop for SyncLock {
+    fn drop(&mut self) {
+        let _ = self.file.set_len(0);
+        let _ = FileExt::unlock(&self.file);
+    }
+}
+
+/// The process id recorded by the current holder, if any.
+pub fn lock_holder(path: &Path) -> Option<u32> {
+    fs::read_to_string(path).ok()?.trim().parse().ok()
+}
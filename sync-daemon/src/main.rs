@@ -1,22 +1,36 @@
 use base64::{engine::general_purpose::STANDARD as BASE64, Engine};
-use chrono::Utc;
+use chrono::{Local, Utc};
 use rand::RngCore;
 use ring::aead::{Aad, LessSafeKey, Nonce, UnboundKey, AES_256_GCM};
 use serde::{Deserialize, Serialize};
-use std::collections::HashSet;
+use std::collections::{HashMap, HashSet};
 use std::fs;
 use std::io::{self, Read, Write};
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
 use std::sync::atomic::{AtomicBool, Ordering};
-use std::sync::Arc;
-use std::thread;
-use std::time::Duration;
+use std::sync::mpsc;
+use std::sync::{LazyLock, Mutex};
+use std::thread::{self, JoinHandle};
+use std::time::{Duration, Instant};
 use thiserror::Error;
 
 const NONCE_LEN: usize = 12;
 const KEY_LEN: usize = 32;
-
-fn setup_logging() {
+/// Firefox drops native messages over 1 MB, so `get_anga` stays well below
+/// that once base64 is added.
+const MAX_INLINE_ANGA: u64 = 512 * 1024;
+/// `bookmark_status` is sent on every tab switch, so only small images are
+/// sent along with it.
+const MAX_INLINE_FAVICON: u64 = 32 * 1024;
+const MAX_INLINE_PREVIEW_IMAGE: u64 = 256 * 1024;
+
+/// How long after starting the link checker waits, so a browser that
+/// starts the host briefly doesn't set off a round of requests.
+const LINK_CHECK_DELAY: Duration = Duration::from_secs(5 * 60);
+/// How often the link checker looks for links that are due.
+const LINK_CHECK_POLL: Duration = Duration::from_secs(60 * 60);
+
+fn setup_logging(level: log::LevelFilter) {
     let log_path = get_kaya_dir().join("log");
 
     let base = fern::Dispatch::new()
@@ -28,7 +42,9 @@ fn setup_logging() {
                 message
             ))
         })
-        .level(log::LevelFilter::Info)
+        .level(level)
+        // lopdf warns about every font it can't decode
+        .level_for("lopdf", log::LevelFilter::Error)
         .chain(io::stderr());
 
     let dispatch = if let Ok(log_file) = fern::log_file(&log_path) {
@@ -46,6 +62,41 @@ fn setup_logging() {
     }
 }
 
+/// Shared by the message loop and the sync thread, so searches see files as
+/// soon as either of them writes them.
+static SEARCH_INDEX: LazyLock<Mutex<SearchIndex>> =
+    LazyLock::new(|| Mutex::new(SearchIndex::new(&get_kaya_dir())));
+
+/// The bookmark lookup, with the normalization it was built with. It's built
+/// on first use and dropped whenever anga or meta change, so that checking
+/// the active tab doesn't read the whole library each time.
+static BOOKMARK_INDEX: Mutex<Option<(NormalizeOptions, BookmarkIndex)>> = Mutex::new(None);
+
+/// Wakes the sync loop early after local changes, and stops it on shutdown.
+static SYNC_SIGNAL: LazyLock<SyncSignal> = LazyLock::new(SyncSignal::new);
+
+/// Stops the link checker, which otherwise sleeps between requests.
+static LINK_SIGNAL: LazyLock<SyncSignal> = LazyLock::new(SyncSignal::new);
+
+/// Held while this process is the one syncing `~/.kaya`.
+static SYNC_LOCK: Mutex<Option<SyncLock>> = Mutex::new(None);
+
+/// Set once the standalone daemon owns the local socket.
+static IPC_LISTENING: AtomicBool = AtomicBool::new(false);
+
+/// Held while readable text is written, since the startup backfill, archiving
+/// and `get_readable` can all extract the same snapshot at once.
+static READABLE_LOCK: Mutex<()> = Mutex::new(());
+
+/// Held while writing thumbnails, for the same reason as `READABLE_LOCK`.
+static THUMBNAIL_LOCK: Mutex<()> = Mutex::new(());
+
+/// Held while writing the text of PDFs.
+static PDF_TEXT_LOCK: Mutex<()> = Mutex::new(());
+
+/// Threads that have to be given the chance to finish on shutdown.
+static WORKERS: Mutex<Vec<(String, JoinHandle<()>)>> = Mutex::new(Vec::new());
+
 #[derive(Error, Debug)]
 enum KayaError {
     #[error("IO error: {0}")]
@@ -60,9 +111,13 @@ enum KayaError {
     Config(String),
     #[error("Encryption error: {0}")]
     Encryption(String),
+    #[error("Bookmark error: {0}")]
+    Shortcut(#[from] ShortcutError),
+    #[error("Meta error: {0}")]
+    Meta(#[from] MetaError),
 }
 
-#[derive(Debug, Serialize, Deserialize)]
+#[derive(Debug, Serialize, Deserialize, Default)]
 struct IncomingMessage {
     id: Option<u64>,
     message: String,
@@ -74,9 +129,33 @@ struct IncomingMessage {
     server: Option<String>,
     email: Option<String>,
     password: Option<String>,
+    url: Option<String>,
+    title: Option<String>,
+    mime_type: Option<String>,
+    kind: Option<AngaKind>,
+    since: Option<String>,
+    until: Option<String>,
+    offset: Option<usize>,
+    limit: Option<usize>,
+    query: Option<String>,
+    tags: Option<Vec<String>>,
+    remove_tags: Option<Vec<String>>,
+    hidden: Option<bool>,
+    purge: Option<bool>,
+    /// Set by the host from the browser that launched it.
+    browser: Option<String>,
+    /// The page as the browser rendered it, archived with a saved bookmark,
+    /// or a bookmark file to import.
+    html: Option<String>,
+    /// Asks for (or skips) a snapshot regardless of `[archive] enabled`.
+    archive: Option<bool>,
+    /// Reports what an import would do without saving anything.
+    dry_run: Option<bool>,
+    /// Asks `bookmark_status` for the bookmark's preview as well.
+    include_preview: Option<bool>,
 }
 
-#[derive(Debug, Serialize, Deserialize)]
+#[derive(Debug, Serialize, Deserialize, Default)]
 struct OutgoingMessage {
     id: Option<u64>,
     success: bool,
@@ -88,6 +167,39 @@ struct OutgoingMessage {
     message_type: Option<String>,
     #[serde(skip_serializing_if = "Option::is_none")]
     has_password: Option<bool>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    bookmarked: Option<bool>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    filenames: Option<Vec<String>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    filename: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    items: Option<Vec<AngaEntry>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    total: Option<usize>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    anga: Option<AngaContent>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    meta: Option<Vec<MetaFile>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    results: Option<Vec<SearchHit>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    metadata: Option<MetaView>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    tags: Option<Vec<TagUsage>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    readable: Option<Readable>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    preview: Option<BookmarkPreview>,
+    /// A `data:` URL of the anga's thumbnail.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    thumbnail: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pdfs: Option<Vec<PdfMatch>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    links: Option<Vec<DeadLink>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    imported: Option<ImportSummary>,
 }
 
 #[derive(Debug, Serialize, Deserialize, Default)]
@@ -96,6 +208,11 @@ struct Config {
     email: Option<String>,
     encrypted_password: Option<String>,
     encryption_key: Option<String>,
+    normalize: Option<NormalizeOptions>,
+    sync: Option<ScheduleOptions>,
+    archive: Option<ArchiveOptions>,
+    preview: Option<PreviewOptions>,
+    links: Option<LinkCheckOptions>,
 }
 
 fn get_kaya_dir() -> PathBuf {
@@ -112,6 +229,18 @@ fn get_meta_dir() -> PathBuf {
     get_kaya_dir().join("meta")
 }
 
+fn get_cache_dir() -> PathBuf {
+    get_kaya_dir().join("cache")
+}
+
+fn get_link_state_dir() -> PathBuf {
+    get_kaya_dir().join(LINK_STATE_DIR)
+}
+
+fn get_lock_path() -> PathBuf {
+    get_kaya_dir().join(".sync.lock")
+}
+
 fn get_config_path() -> PathBuf {
     get_kaya_dir().join(".config")
 }
@@ -119,6 +248,7 @@ fn get_config_path() -> PathBuf {
 fn ensure_directories() -> io::Result<()> {
     fs::create_dir_all(get_anga_dir())?;
     fs::create_dir_all(get_meta_dir())?;
+    fs::create_dir_all(get_cache_dir())?;
     Ok(())
 }
 
@@ -235,7 +365,7 @@ fn handle_test_connection(msg: &IncomingMessage) -> Result<(), KayaError> {
 
     log::info!("Testing connection to {}", url);
 
-    let client = reqwest::blocking::Client::new();
+    let client = http_client().build()?;
     let response = client
         .get(&url)
         .basic_auth(&email, Some(&password))
@@ -281,13 +411,18 @@ fn handle_config_message(msg: &IncomingMessage) -> Result<(), KayaError> {
         email,
         encrypted_password,
         encryption_key,
+        normalize: existing.normalize,
+        sync: existing.sync,
+        archive: existing.archive,
+        preview: existing.preview,
+        links: existing.links,
     };
 
     save_config(&config)?;
     Ok(())
 }
 
-fn handle_anga_message(msg: &IncomingMessage) -> Result<(), KayaError> {
+fn handle_anga_message(msg: &IncomingMessage) -> Result<String, KayaError> {
     log::info!(
         "Received anga message: filename={:?}, type={:?}",
         msg.filename,
@@ -319,31 +454,150 @@ fn handle_anga_message(msg: &IncomingMessage) -> Result<(), KayaError> {
         Some(t) => return Err(KayaError::Config(format!("Unknown content type: {}", t))),
     };
 
+    // Bookmarks are rewritten through UrlShortcut so every .url file on disk
+    // is one the rest of the daemon can read back.
+    let content = if filename.ends_with(".url") {
+        UrlShortcut::from_bytes(&content)?.to_string().into_bytes()
+    } else {
+        content
+    };
+
     let path = get_anga_dir().join(filename);
     fs::write(&path, content)?;
 
-    Ok(())
+    Ok(filename.clone())
+}
+
+/// Meta files may legitimately arrive before their anga, but usually a
+/// missing anga means a typo or a file deleted by hand.
+fn warn_if_orphaned(meta_filename: &str, meta: &Meta) {
+    if !get_anga_dir().join(&meta.anga.filename).exists() {
+        log::warn!(
+            "Meta {} refers to missing anga {}",
+            meta_filename,
+            meta.anga.filename
+        );
+    }
 }
 
-fn handle_meta_message(msg: &IncomingMessage) -> Result<(), KayaError> {
+fn handle_meta_message(msg: &IncomingMessage) -> Result<String, KayaError> {
     log::info!("Received meta message: filename={:?}", msg.filename);
 
     ensure_directories()?;
 
-    let filename = msg
-        .filename
+    let text = msg
+        .text
         .as_ref()
-        .ok_or_else(|| KayaError::Config("Missing filename".to_string()))?;
+        .ok_or_else(|| KayaError::Config("Missing text content".to_string()))?;
+
+    let meta = Meta::parse(text)?;
+    warn_if_orphaned(msg.filename.as_deref().unwrap_or("new meta"), &meta);
+
+    // Without a filename the daemon picks one, following ADR 0003
+    let filename = match msg.filename.as_ref() {
+        Some(filename) => {
+            validate_meta_filename(filename)?;
+            fs::write(get_meta_dir().join(filename), text)?;
+            filename.clone()
+        }
+        None => {
+            create_timestamped_file(&get_meta_dir(), Utc::now(), "note", "toml", text.as_bytes())?
+        }
+    };
+
+    Ok(filename)
+}
+
+/// Writes a new anga named by the ADR 0001 rules and returns its filename.
+fn save_new_anga(slug: &str, ext: &str, content: &[u8]) -> Result<String, KayaError> {
+    ensure_directories()?;
+    let filename = create_timestamped_file(&get_anga_dir(), Utc::now(), slug, ext, content)?;
+    log::info!("Saved anga: {}", filename);
+    Ok(filename)
+}
+
+fn handle_save_url(msg: &IncomingMessage) -> Result<String, KayaError> {
+    log::info!("Received save_url message: url={:?}", msg.url);
+
+    let url = msg
+        .url
+        .as_ref()
+        .ok_or_else(|| KayaError::Config("Missing url".to_string()))?;
+    let title = msg
+        .title
+        .as_deref()
+        .map(str::trim)
+        .filter(|t| !t.is_empty());
+
+    let slug = bookmark_slug(url, title);
+
+    let mut shortcut = UrlShortcut::new(url);
+    if let Some(title) = title {
+        shortcut = shortcut.with_title(title);
+    }
+
+    save_new_anga(&slug, "url", shortcut.to_string().as_bytes())
+}
+
+fn handle_save_quote(msg: &IncomingMessage) -> Result<String, KayaError> {
+    log::info!("Received save_quote message");
 
     let text = msg
         .text
         .as_ref()
         .ok_or_else(|| KayaError::Config("Missing text content".to_string()))?;
 
-    let path = get_meta_dir().join(filename);
-    fs::write(&path, text)?;
+    save_new_anga("quote", "md", text.as_bytes())
+}
 
-    Ok(())
+fn handle_save_image(msg: &IncomingMessage) -> Result<String, KayaError> {
+    log::info!(
+        "Received save_image message: url={:?}, mime_type={:?}",
+        msg.url,
+        msg.mime_type
+    );
+
+    let b64 = msg
+        .base64
+        .as_ref()
+        .ok_or_else(|| KayaError::Config("Missing base64 content".to_string()))?;
+    let content = BASE64.decode(b64)?;
+
+    // Name the file after the last path segment of the image URL, as the
+    // extension used to.
+    let original = msg
+        .url
+        .as_ref()
+        .and_then(|u| url::Url::parse(u).ok())
+        .and_then(|u| u.path_segments()?.next_back().map(|s| s.to_string()))
+        .map(|s| urlencoding::decode(&s).map(|d| d.into_owned()).unwrap_or(s))
+        .unwrap_or_default();
+    let (stem, ext) = match original.rsplit_once('.') {
+        Some((stem, ext))
+            if !ext.is_empty()
+                && ext.len() <= 5
+                && ext.chars().all(|c| c.is_ascii_alphanumeric()) =>
+        {
+            (stem, ext.to_lowercase())
+        }
+        _ => (
+            original.as_str(),
+            extension_for_mime(msg.mime_type.as_deref()),
+        ),
+    };
+
+    save_new_anga(&slugify(stem, "image"), &ext, &content)
+}
+
+fn extension_for_mime(mime_type: Option<&str>) -> String {
+    match mime_type.unwrap_or("") {
+        "image/jpeg" => "jpg",
+        "image/gif" => "gif",
+        "image/webp" => "webp",
+        "image/svg+xml" => "svg",
+        _ => "png",
+    }
+    .to_string()
 }
 
 fn get_all_bookmarked_urls() -> Result<Vec<String>, KayaError> {
@@ -352,19 +606,20 @@ fn get_all_bookmarked_urls() -> Result<Vec<String>, KayaError> {
         return Ok(Vec::new());
     }
 
+    let hidden = hidden_anga(&get_meta_dir())?;
     let mut urls = Vec::new();
 
     for entry in fs::read_dir(anga_dir)? {
         let entry = entry?;
         let path = entry.path();
+        if hidden.contains(&*entry.file_name().to_string_lossy()) {
+            continue;
+        }
 
         if path.extension().map(|e| e == "url").unwrap_or(false) {
-            if let Ok(content) = fs::read_to_string(&path) {
-                for line in content.lines() {
-                    if line.starts_with("URL=") {
-                        urls.push(line[4..].to_string());
-                    }
-                }
+            match read_shortcut(&path) {
+                Ok(shortcut) => urls.push(shortcut.url),
+                Err(e) => log::warn!("Skipping unreadable bookmark {:?}: {}", path, e),
             }
         }
     }
@@ -372,190 +627,1280 @@ fn get_all_bookmarked_urls() -> Result<Vec<String>, KayaError> {
     Ok(urls)
 }
 
-fn read_native_message() -> Result<Option<IncomingMessage>, KayaError> {
-    let stdin = io::stdin();
-    let mut handle = stdin.lock();
+fn load_bookmark_index(opts: NormalizeOptions) -> Result<BookmarkIndex, KayaError> {
+    let hidden = hidden_anga(&get_meta_dir())?;
+    Ok(BookmarkIndex::load_excluding(
+        &get_anga_dir(),
+        opts,
+        &hidden,
+    )?)
+}
 
-    let mut len_bytes = [0u8; 4];
-    match handle.read_exact(&mut len_bytes) {
-        Ok(_) => {}
-        Err(e) if e.kind() == io::ErrorKind::UnexpectedEof => return Ok(None),
-        Err(e) => return Err(e.into()),
-    }
+/// Drops the cached bookmark lookup after anga or meta change.
+fn invalidate_bookmark_index() {
+    *BOOKMARK_INDEX.lock().unwrap_or_else(|e| e.into_inner()) = None;
+}
 
-    let len = u32::from_ne_bytes(len_bytes) as usize;
-    if len == 0 || len > 1024 * 1024 * 100 {
-        return Err(KayaError::Config(format!(
-            "Invalid message length: {}",
-            len
-        )));
-    }
+/// The bookmarks matching `url`, oldest first, leaving out hidden ones. The
+/// lookup is rebuilt when it was dropped or `[normalize]` has changed.
+fn lookup_bookmark(url: &str) -> Result<Vec<String>, KayaError> {
+    let opts = load_config()?.normalize.unwrap_or_default();
+    let mut cached = BOOKMARK_INDEX.lock().unwrap_or_else(|e| e.into_inner());
+    let index = match cached.take() {
+        Some((built_with, index)) if built_with == opts => index,
+        _ => load_bookmark_index(opts.clone())?,
+    };
+    let found = index.lookup(url).to_vec();
+    *cached = Some((opts, index));
+    Ok(found)
+}
 
-    let mut buffer = vec![0u8; len];
-    handle.read_exact(&mut buffer)?;
+/// Saves the bookmarks that aren't bookmarked yet. Hidden bookmarks still
+/// count as bookmarked, so importing the same bookmarks again doesn't bring
+/// back what was deleted since.
+fn import_new_bookmarks(
+    bookmarks: &[ImportedBookmark],
+    dry_run: bool,
+) -> Result<ImportSummary, KayaError> {
+    let opts = load_config()?.normalize.unwrap_or_default();
+    let mut index = BookmarkIndex::load(&get_anga_dir(), opts)?;
+    let summary = import_bookmarks(
+        &get_anga_dir(),
+        &get_meta_dir(),
+        &mut index,
+        bookmarks,
+        Utc::now(),
+        dry_run,
+    )?;
+    if !dry_run {
+        log::info!(
+            "Imported {} bookmarks ({} already bookmarked, {} unsupported)",
+            summary.imported,
+            summary.existing,
+            summary.unsupported
+        );
+        invalidate_bookmark_index();
+        refresh_search_index();
+    }
+    Ok(summary)
+}
 
-    let message: IncomingMessage = serde_json::from_slice(&buffer)?;
-    Ok(Some(message))
+fn handle_import_bookmarks(msg: &IncomingMessage) -> Result<ImportSummary, KayaError> {
+    log::info!("Received import_bookmarks message");
+    let html = msg
+        .html
+        .as_ref()
+        .ok_or_else(|| KayaError::Config("Missing html".to_string()))?;
+    import_new_bookmarks(
+        &parse_netscape_bookmarks(html),
+        msg.dry_run.unwrap_or(false),
+    )
 }
 
-fn write_native_message(msg: &OutgoingMessage) -> Result<(), KayaError> {
-    let json = serde_json::to_vec(msg)?;
-    let len = (json.len() as u32).to_ne_bytes();
+fn handle_bookmark_status(msg: &IncomingMessage) -> Result<Vec<String>, KayaError> {
+    let url = msg
+        .url
+        .as_ref()
+        .ok_or_else(|| KayaError::Config("Missing url".to_string()))?;
 
-    let stdout = io::stdout();
-    let mut handle = stdout.lock();
-    handle.write_all(&len)?;
-    handle.write_all(&json)?;
-    handle.flush()?;
+    lookup_bookmark(url)
+}
 
-    Ok(())
+/// A bookmark's preview as sent to the extension, with small images inline
+/// as `data:` URLs so the popup can show them without another request.
+#[derive(Debug, Serialize, Deserialize)]
+struct BookmarkPreview {
+    filename: String,
+    #[serde(flatten)]
+    preview: PagePreview,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    favicon_data: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    image_data: Option<String>,
 }
 
-use savebutton_sync_daemon::parse_server_file_listing;
+/// A cached image as a `data:` URL, if it's no larger than `max_bytes`.
+fn inline_image(dir: &Path, name: Option<&str>, max_bytes: u64) -> Option<String> {
+    let path = dir.join(name?);
+    if fs::metadata(&path).ok()?.len() > max_bytes {
+        return None;
+    }
+    let content = fs::read(&path).ok()?;
+    Some(format!(
+        "data:{};base64,{}",
+        mime_type_for(name?),
+        BASE64.encode(content)
+    ))
+}
 
-fn sync_with_server() -> Result<(), KayaError> {
-    let config = load_config()?;
+/// The preview of the first of `filenames` that has one.
+fn bookmark_preview(filenames: &[String]) -> Option<BookmarkPreview> {
+    filenames.iter().find_map(|filename| {
+        let dir = get_cache_dir().join(filename);
+        let preview = match read_preview(&dir) {
+            Ok(preview) => preview?,
+            Err(e) => {
+                log::warn!("Unreadable preview for {}: {}", filename, e);
+                return None;
+            }
+        };
+        Some(BookmarkPreview {
+            filename: filename.clone(),
+            favicon_data: inline_image(&dir, preview.favicon.as_deref(), MAX_INLINE_FAVICON),
+            image_data: inline_image(&dir, preview.image.as_deref(), MAX_INLINE_PREVIEW_IMAGE),
+            preview,
+        })
+    })
+}
 
-    let server = match config.server {
-        Some(s) => s,
-        None => return Ok(()),
+fn handle_list_anga(msg: &IncomingMessage) -> Result<AngaPage, KayaError> {
+    let bound = |value: &Option<String>, end_of_day: bool| -> Result<_, KayaError> {
+        value
+            .as_deref()
+            .map(|v| {
+                parse_date_bound(v, end_of_day)
+                    .ok_or_else(|| KayaError::Config(format!("Invalid date: {}", v)))
+            })
+            .transpose()
     };
 
-    let email = match config.email {
-        Some(e) => e,
-        None => return Ok(()),
+    let query = ListQuery {
+        kind: msg.kind,
+        since: bound(&msg.since, false)?,
+        until: bound(&msg.until, true)?,
+        offset: msg.offset.unwrap_or(0),
+        limit: msg.limit,
+        exclude: hidden_anga(&get_meta_dir())?,
     };
 
-    let password = match (&config.encrypted_password, &config.encryption_key) {
-        (Some(enc), Some(key_b64)) => {
-            let key_bytes = BASE64.decode(key_b64)?;
-            let key: [u8; KEY_LEN] = key_bytes
-                .try_into()
-                .map_err(|_| KayaError::Encryption("Invalid key length".to_string()))?;
-            decrypt_password(enc, &key)?
-        }
-        _ => return Ok(()),
-    };
+    Ok(list_anga(&get_anga_dir(), &query)?)
+}
 
-    let client = reqwest::blocking::Client::new();
+/// An anga with its meta files and folded metadata. Images too large to
+/// send whole come with their thumbnail in place of a cut-short copy.
+fn handle_get_anga(
+    msg: &IncomingMessage,
+) -> Result<(AngaContent, Vec<MetaFile>, MetaView), KayaError> {
+    let filename = msg
+        .filename
+        .as_ref()
+        .ok_or_else(|| KayaError::Config("Missing filename".to_string()))?;
 
-    let (anga_downloaded, anga_uploaded) = sync_anga(&client, &server, &email, &password)?;
-    let (meta_downloaded, meta_uploaded) = sync_meta(&client, &server, &email, &password)?;
+    let mut content = read_anga(&get_anga_dir(), filename, MAX_INLINE_ANGA)?;
+    if content.preview && has_thumbnail_type(filename) {
+        match handle_get_thumbnail(msg) {
+            // Both together could outgrow a native message
+            Ok(thumbnail) => {
+                content.thumbnail = Some(thumbnail);
+                content.base64 = None;
+            }
+            Err(e) => log::warn!("Failed to make a thumbnail of {}: {}", filename, e),
+        }
+    }
+    let meta = meta_files_for(&get_meta_dir(), filename)?;
+    let view = resolve_meta(&get_meta_dir(), filename)?;
+    Ok((content, meta, view))
+}
 
-    let total_downloaded = anga_downloaded + meta_downloaded;
-    let total_uploaded = anga_uploaded + meta_uploaded;
+/// Writes a new meta file named by the ADR 0003 rules and returns its
+/// filename.
+fn write_new_meta(meta: &Meta, slug: &str) -> Result<String, KayaError> {
+    meta.validate()?;
+    ensure_directories()?;
+    let text = meta.to_toml()?;
+    let filename =
+        create_timestamped_file(&get_meta_dir(), Utc::now(), slug, "toml", text.as_bytes())?;
+    log::info!("Saved meta: {}", filename);
+    Ok(filename)
+}
 
-    if total_downloaded > 0 || total_uploaded > 0 {
-        log::info!(
-            "Sync complete: {} downloaded, {} uploaded",
-            total_downloaded,
-            total_uploaded
-        );
+/// Records which browser an anga was saved from in a meta file of its own.
+/// The anga is already saved, so failing to do so is only logged.
+fn record_browser(filename: &str, browser: &str) {
+    let mut meta = Meta::new(filename);
+    meta.meta.browser = Some(browser.to_string());
+    if let Err(e) = write_new_meta(&meta, "browser") {
+        log::warn!("Failed to record the browser for {}: {}", filename, e);
     }
+}
 
-    Ok(())
+/// Whether saving the bookmark in `msg` should also archive the page. HTML
+/// sent with the message is a request for a snapshot in itself.
+fn wants_archive(msg: &IncomingMessage, opts: &ArchiveOptions) -> bool {
+    msg.archive.unwrap_or(opts.enabled || msg.html.is_some())
 }
 
-fn sync_anga(
+/// Downloads `url`, giving up on responses over `max_bytes`. Returns the
+/// body and its content type.
+fn fetch_limited(
     client: &reqwest::blocking::Client,
-    server: &str,
-    email: &str,
-    password: &str,
-) -> Result<(usize, usize), KayaError> {
-    let url = format!(
-        "{}/api/v1/{}/anga",
-        server.trim_end_matches('/'),
-        urlencoding::encode(email)
-    );
-
-    let response = client.get(&url).basic_auth(email, Some(password)).send()?;
-
-    if !response.status().is_success() {
-        return Err(KayaError::Http(response.error_for_status().unwrap_err()));
+    url: &url::Url,
+    max_bytes: u64,
+) -> Result<(Vec<u8>, Option<String>), KayaError> {
+    let response = client.get(url.as_str()).send()?.error_for_status()?;
+    if response.content_length().is_some_and(|len| len > max_bytes) {
+        return Err(KayaError::Config(format!("{} is too large", url)));
     }
+    let content_type = response
+        .headers()
+        .get(reqwest::header::CONTENT_TYPE)
+        .and_then(|v| v.to_str().ok())
+        .map(String::from);
+
+    let mut body = Vec::new();
+    response.take(max_bytes + 1).read_to_end(&mut body)?;
+    if body.len() as u64 > max_bytes {
+        return Err(KayaError::Config(format!("{} is too large", url)));
+    }
+    Ok((body, content_type))
+}
 
-    let server_files: HashSet<String> = parse_server_file_listing(&response.text()?);
-
-    let anga_dir = get_anga_dir();
-    let local_files: HashSet<String> = if anga_dir.exists() {
-        fs::read_dir(&anga_dir)?
-            .filter_map(|e| e.ok())
-            .filter(|e| e.path().is_file())
-            .filter_map(|e| e.file_name().into_string().ok())
-            .filter(|n| !n.starts_with('.'))
-            .collect()
-    } else {
-        HashSet::new()
-    };
-
-    let to_download: Vec<_> = server_files.difference(&local_files).collect();
-    let to_upload: Vec<_> = local_files.difference(&server_files).collect();
-
-    let downloaded = to_download.len();
-    let uploaded = to_upload.len();
+/// How every client the daemon makes is set up, whether it talks to the
+/// sync server or to the sites behind bookmarks.
+fn http_client() -> reqwest::blocking::ClientBuilder {
+    reqwest::blocking::Client::builder().user_agent(concat!(
+        "savebutton-sync-daemon/",
+        env!("CARGO_PKG_VERSION")
+    ))
+}
 
-    for filename in to_download {
-        log::info!("  downloading anga: {}", filename);
-        download_anga(client, server, email, password, filename)?;
-    }
+/// The client used to fetch saved pages and their assets.
+fn page_client() -> Result<reqwest::blocking::Client, KayaError> {
+    Ok(http_client().timeout(Duration::from_secs(30)).build()?)
+}
 
-    for filename in to_upload {
-        log::info!("  uploading anga: {}", filename);
-        upload_anga(client, server, email, password, filename)?;
+fn fetch_page(
+    client: &reqwest::blocking::Client,
+    page_url: &url::Url,
+    max_bytes: u64,
+) -> Result<String, KayaError> {
+    let (body, content_type) = fetch_limited(client, page_url, max_bytes)?;
+    if !content_type.is_some_and(|t| t.contains("html")) {
+        return Err(KayaError::Config(format!(
+            "{} is not an HTML page",
+            page_url
+        )));
     }
+    Ok(String::from_utf8_lossy(&body).into_owned())
+}
 
-    Ok((downloaded, uploaded))
+/// The error for work abandoned because the daemon is shutting down.
+fn shutting_down() -> KayaError {
+    io::Error::new(io::ErrorKind::Interrupted, "the daemon is shutting down").into()
 }
 
-fn download_anga(
+/// Snapshots a saved bookmark's page into `cache/{bookmark}/`, along with
+/// its assets when `[archive] store_assets` is on, and extracts its
+/// readable text. What the page's stylesheets load is stored too, but not
+/// what those in turn import. The watcher then uploads it like any other
+/// cache file.
+fn archive_page(
     client: &reqwest::blocking::Client,
-    server: &str,
-    email: &str,
-    password: &str,
     filename: &str,
-) -> Result<(), KayaError> {
-    // filename is already URL-encoded from the server listing
-    let url = format!(
-        "{}/api/v1/{}/anga/{}",
-        server.trim_end_matches('/'),
-        urlencoding::encode(email),
+    page_url: &url::Url,
+    html: &str,
+    opts: &ArchiveOptions,
+) -> Result<PathBuf, KayaError> {
+    let mut stored = HashMap::new();
+    let mut assets = Vec::new();
+    // Stored stylesheets, by their index in `assets`, to be rewritten
+    let mut stylesheets = Vec::new();
+    if opts.store_assets {
+        let mut failed = HashSet::new();
+        let mut pending: Vec<url::Url> = asset_references(html, page_url)
+            .into_iter()
+            .map(|a| a.url)
+            .collect();
+        let from_page = pending.len();
+        let mut next = 0;
+        while next < pending.len() {
+            if SYNC_SIGNAL.is_stopped() {
+                return Err(shutting_down());
+            }
+            if stored.len() >= opts.max_assets {
+                break;
+            }
+            let url = pending[next].clone();
+            next += 1;
+            if stored.contains_key(&url) || failed.contains(&url) {
+                continue;
+            }
+            match fetch_limited(client, &url, opts.max_bytes) {
+                Ok((content, content_type)) => {
+                    let name = asset_filename(stored.len() + 1, &url, content_type.as_deref());
+                    if name.ends_with(".css") {
+                        // Only the page's own stylesheets are followed
+                        let css = std::str::from_utf8(&content)
+                            .ok()
+                            .filter(|_| next <= from_page);
+                        if let Some(css) = css {
+                            pending.extend(css_references(css, &url).into_iter().map(|r| r.url));
+                        }
+                        stylesheets.push((assets.len(), url.clone()));
+                    }
+                    assets.push((name.clone(), content));
+                    stored.insert(url, name);
+                }
+                Err(e) => {
+                    log::debug!("Not storing asset {}: {}", url, e);
+                    failed.insert(url);
+                }
+            }
+        }
+    }
+    for (i, url) in stylesheets {
+        if let Ok(css) = std::str::from_utf8(&assets[i].1) {
+            assets[i].1 = rewrite_css(css, &url, &stored).into_bytes();
+        }
+    }
+
+    let snapshot = build_snapshot(html, page_url, &stored);
+    let path = write_snapshot(&get_cache_dir(), filename, &snapshot, &assets)?;
+    log::info!(
+        "Archived {} with {} assets for {}",
+        page_url,
+        assets.len(),
         filename
     );
+    // Extracted from the page as fetched, before links were rewritten
+    let readable = extract_readable(html);
+    let _guard = READABLE_LOCK.lock().unwrap_or_else(|e| e.into_inner());
+    write_readable(&get_cache_dir().join(filename), &readable)?;
+    Ok(path)
+}
 
-    let response = client.get(&url).basic_auth(email, Some(password)).send()?;
-
-    if response.status().is_success() {
-        let content = response.bytes()?;
-        let path = get_anga_dir().join(filename);
-        fs::write(path, content)?;
+/// Downloads an image to store as `{stem}.{ext}`, refusing anything that
+/// isn't one. Returns the filename and content.
+fn fetch_image(
+    client: &reqwest::blocking::Client,
+    url: &str,
+    stem: &str,
+    max_bytes: u64,
+) -> Result<(String, Vec<u8>), KayaError> {
+    let url = url::Url::parse(url)
+        .map_err(|e| KayaError::Config(format!("Invalid url {}: {}", url, e)))?;
+    let (content, content_type) = fetch_limited(client, &url, max_bytes)?;
+    let content_type = content_type.unwrap_or_default();
+    if !content_type.starts_with("image/") {
+        return Err(KayaError::Config(format!("{} is not an image", url)));
     }
-
-    Ok(())
+    let ext = extension_for_type(&content_type)
+        .map(String::from)
+        .or_else(|| file_extension(&url, None))
+        .unwrap_or_else(|| "img".to_string());
+    Ok((format!("{}.{}", stem, ext), content))
 }
 
-fn upload_anga(
+/// Reads a saved bookmark's title, description, canonical URL and images
+/// from its page into `cache/{bookmark}/preview.toml`, storing the favicon
+/// and preview image next to it when `[preview] store_images` is on.
+fn capture_preview(
     client: &reqwest::blocking::Client,
-    server: &str,
-    email: &str,
-    password: &str,
     filename: &str,
-) -> Result<(), KayaError> {
-    let path = get_anga_dir().join(filename);
-    let content = fs::read(&path)?;
+    page_url: &url::Url,
+    html: &str,
+    opts: &PreviewOptions,
+) -> Result<PagePreview, KayaError> {
+    let dir = get_cache_dir().join(filename);
+    let mut preview = extract_preview(html, page_url);
+
+    if opts.store_images {
+        let wanted = [
+            (preview.favicon_url.clone(), FAVICON_STEM),
+            (preview.image_url.clone(), PREVIEW_IMAGE_STEM),
+        ];
+        for (url, stem) in wanted {
+            let Some(url) = url else {
+                continue;
+            };
+            match fetch_image(client, &url, stem, opts.max_image_bytes) {
+                Ok((name, content)) => {
+                    fs::create_dir_all(&dir)?;
+                    write_atomically(&dir.join(&name), &content)?;
+                    if stem == FAVICON_STEM {
+                        preview.favicon = Some(name);
+                    } else {
+                        preview.image = Some(name);
+                    }
+                }
+                Err(e) => log::debug!("Not storing {} for {}: {}", stem, filename, e),
+            }
+        }
+    }
 
-    let url = format!(
-        "{}/api/v1/{}/anga/{}",
-        server.trim_end_matches('/'),
-        urlencoding::encode(email),
-        urlencoding::encode(filename)
-    );
+    write_preview(&dir, &preview)?;
+    log::info!("Captured preview of {} for {}", page_url, filename);
+    Ok(preview)
+}
 
-    let content_type = mime_type_for(filename);
+/// Looks at the page behind a just-saved bookmark: captures its preview
+/// when `[preview]` is enabled, and archives it when `archive` is set. The
+/// page is fetched at most once, from the HTML the browser sent or from
+/// the site. Returns the snapshot's path if one was written.
+fn capture_page(
+    filename: &str,
+    html: Option<String>,
+    archive: bool,
+) -> Result<Option<PathBuf>, KayaError> {
+    let config = load_config()?;
+    let archive_opts = config.archive.unwrap_or_default();
+    let preview_opts = config.preview.unwrap_or_default();
+    if !archive && !preview_opts.enabled {
+        return Ok(None);
+    }
 
-    let part = reqwest::blocking::multipart::Part::bytes(content)
-        .file_name(filename.to_string())
-        .mime_str(&content_type)
-        .unwrap();
+    let shortcut = read_shortcut(&get_anga_dir().join(filename))?;
+    let page_url = url::Url::parse(&shortcut.url)
+        .map_err(|e| KayaError::Config(format!("Invalid url {}: {}", shortcut.url, e)))?;
+    if !matches!(page_url.scheme(), "http" | "https") {
+        return Ok(None);
+    }
+    let client = page_client()?;
+
+    let fetch = (archive && archive_opts.fetch) || (preview_opts.enabled && preview_opts.fetch);
+    let html = match html {
+        Some(html) => html,
+        None if fetch => fetch_page(&client, &page_url, archive_opts.max_bytes)?,
+        None if archive => {
+            return Err(KayaError::Config(
+                "No page HTML sent and [archive] fetch is off".to_string(),
+            ))
+        }
+        None => return Ok(None),
+    };
+
+    if preview_opts.enabled {
+        if let Err(e) = capture_preview(&client, filename, &page_url, &html, &preview_opts) {
+            log::warn!("Failed to capture a preview for {}: {}", filename, e);
+        }
+    }
+    if archive {
+        if SYNC_SIGNAL.is_stopped() {
+            return Err(shutting_down());
+        }
+        return archive_page(&client, filename, &page_url, &html, &archive_opts).map(Some);
+    }
+    Ok(None)
+}
+
+/// Captures a just-saved bookmark's page in the background, so the browser
+/// isn't kept waiting on the page's server. Shutdown stops it between
+/// assets; a write cut short leaves only a partial file behind.
+fn spawn_capture(filename: String, html: Option<String>, archive: bool) {
+    spawn_worker("capture", move || {
+        match capture_page(&filename, html, archive) {
+            Ok(Some(_)) => refresh_search_index(),
+            Ok(None) => {}
+            Err(e) => log::warn!("Failed to capture the page for {}: {}", filename, e),
+        }
+    });
+}
+
+/// The readable text of a bookmark's snapshot, extracting it first if the
+/// snapshot is newer. Text too long for one native message is cut short.
+fn handle_get_readable(msg: &IncomingMessage) -> Result<Readable, KayaError> {
+    let filename = msg
+        .filename
+        .as_ref()
+        .ok_or_else(|| KayaError::Config("Missing filename".to_string()))?;
+    if !is_plain_filename(filename) {
+        return Err(KayaError::Config(format!("Invalid filename: {}", filename)));
+    }
+
+    let _guard = READABLE_LOCK.lock().unwrap_or_else(|e| e.into_inner());
+    let mut readable = ensure_readable(&get_cache_dir().join(filename))?
+        .ok_or_else(|| KayaError::Config(format!("No snapshot of {}", filename)))?;
+    if readable.text.len() as u64 > MAX_INLINE_ANGA {
+        let mut end = MAX_INLINE_ANGA as usize;
+        while !readable.text.is_char_boundary(end) {
+            end -= 1;
+        }
+        readable.text.truncate(end);
+        readable.text.push('…');
+    }
+    Ok(readable)
+}
+
+/// Extracts readable text from snapshots that don't have it yet, such as
+/// ones archived by an older version or copied in by hand.
+fn extract_missing_readables() {
+    let extracted = {
+        let _guard = READABLE_LOCK.lock().unwrap_or_else(|e| e.into_inner());
+        extract_missing(&get_cache_dir(), || SYNC_SIGNAL.is_stopped())
+    };
+    match extracted {
+        Ok(0) => {}
+        Ok(n) => {
+            log::info!("Extracted readable text from {} snapshot(s)", n);
+            refresh_search_index();
+        }
+        Err(e) => log::warn!("Failed to extract readable text: {}", e),
+    }
+}
+
+/// Makes a thumbnail for a new image in the background.
+fn spawn_thumbnail(filename: String) {
+    if !has_thumbnail_type(&filename) {
+        return;
+    }
+    spawn_worker("thumbnail", move || {
+        let _guard = THUMBNAIL_LOCK.lock().unwrap_or_else(|e| e.into_inner());
+        if let Err(e) = ensure_thumbnail(&get_anga_dir(), &get_cache_dir(), &filename) {
+            log::warn!("Failed to make a thumbnail of {}: {}", filename, e);
+        }
+    });
+}
+
+/// An image's thumbnail as a `data:` URL, making it first if it's
+/// missing or older than the anga.
+fn handle_get_thumbnail(msg: &IncomingMessage) -> Result<String, KayaError> {
+    let filename = msg
+        .filename
+        .as_ref()
+        .ok_or_else(|| KayaError::Config("Missing filename".to_string()))?;
+    if !is_plain_filename(filename) {
+        return Err(KayaError::Config(format!("Invalid filename: {}", filename)));
+    }
+
+    let _guard = THUMBNAIL_LOCK.lock().unwrap_or_else(|e| e.into_inner());
+    let path = ensure_thumbnail(&get_anga_dir(), &get_cache_dir(), filename)?
+        .ok_or_else(|| KayaError::Config(format!("No thumbnail for {}", filename)))?;
+    Ok(format!(
+        "data:{};base64,{}",
+        mime_type_for(THUMBNAIL_FILENAME),
+        BASE64.encode(fs::read(path)?)
+    ))
+}
+
+/// Makes thumbnails for images that don't have one yet, such as
+/// ones saved by an older version or downloaded by sync.
+fn generate_missing_thumbnails() {
+    let generated = {
+        let _guard = THUMBNAIL_LOCK.lock().unwrap_or_else(|e| e.into_inner());
+        generate_missing(&get_anga_dir(), &get_cache_dir(), || {
+            SYNC_SIGNAL.is_stopped()
+        })
+    };
+    match generated {
+        Ok(0) => {}
+        Ok(n) => log::info!("Made {} thumbnail(s)", n),
+        Err(e) => log::warn!("Failed to make thumbnails: {}", e),
+    }
+}
+
+/// Extracts the text of a new PDF in the background, so search and
+/// `find_in_pdfs` can see it.
+fn spawn_pdf_text(filename: String) {
+    if !filename.to_lowercase().ends_with(".pdf") {
+        return;
+    }
+    spawn_worker("pdf text", move || {
+        let extracted = {
+            let _guard = PDF_TEXT_LOCK.lock().unwrap_or_else(|e| e.into_inner());
+            ensure_pdf_text(&get_anga_dir(), &get_cache_dir(), &filename)
+        };
+        match extracted {
+            Ok(_) => refresh_search_index(),
+            Err(e) => log::warn!("Failed to extract text from {}: {}", filename, e),
+        }
+    });
+}
+
+/// Extracts the text of PDFs that don't have it yet.
+fn extract_missing_pdf_texts() {
+    let extracted = {
+        let _guard = PDF_TEXT_LOCK.lock().unwrap_or_else(|e| e.into_inner());
+        extract_missing_pdf_text(&get_anga_dir(), &get_cache_dir(), || {
+            SYNC_SIGNAL.is_stopped()
+        })
+    };
+    match extracted {
+        Ok(0) => {}
+        Ok(n) => {
+            log::info!("Extracted text from {} PDF(s)", n);
+            refresh_search_index();
+        }
+        Err(e) => log::warn!("Failed to extract text from PDFs: {}", e),
+    }
+}
+
+/// The PDFs, and their pages, that contain the phrase in `query`. Hidden
+/// anga are left out.
+fn handle_find_in_pdfs(msg: &IncomingMessage) -> Result<Vec<PdfMatch>, KayaError> {
+    let phrase = msg
+        .query
+        .as_deref()
+        .map(str::trim)
+        .filter(|q| !q.is_empty())
+        .ok_or_else(|| KayaError::Config("Missing query".to_string()))?;
+
+    let hidden = hidden_anga(&get_meta_dir())?;
+    let mut matches = find_in_pdfs(&get_anga_dir(), &get_cache_dir(), phrase)?;
+    matches.retain(|m| !hidden.contains(&m.filename));
+    if let Some(limit) = msg.limit {
+        matches.truncate(limit);
+    }
+    Ok(matches)
+}
+
+fn handle_tag_anga(msg: &IncomingMessage) -> Result<String, KayaError> {
+    log::info!(
+        "Received tag_anga message: filename={:?}, tags={:?}, remove_tags={:?}",
+        msg.filename,
+        msg.tags,
+        msg.remove_tags
+    );
+
+    let filename = msg
+        .filename
+        .as_ref()
+        .ok_or_else(|| KayaError::Config("Missing filename".to_string()))?;
+    if !get_anga_dir().join(filename).is_file() {
+        return Err(KayaError::Config(format!("No such anga: {}", filename)));
+    }
+
+    let mut meta = Meta::new(filename);
+    meta.meta.tags = msg.tags.clone().unwrap_or_default();
+    meta.meta.remove_tags = msg.remove_tags.clone().unwrap_or_default();
+    if meta.meta.tags.is_empty() && meta.meta.remove_tags.is_empty() {
+        return Err(KayaError::Config("Missing tags".to_string()));
+    }
+
+    write_new_meta(&meta, "tags")
+}
+
+fn handle_hide_anga(msg: &IncomingMessage) -> Result<String, KayaError> {
+    log::info!(
+        "Received hide_anga message: filename={:?}, hidden={:?}, purge={:?}",
+        msg.filename,
+        msg.hidden,
+        msg.purge
+    );
+
+    let filename = msg
+        .filename
+        .as_ref()
+        .ok_or_else(|| KayaError::Config("Missing filename".to_string()))?;
+    let hidden = msg.hidden.unwrap_or(true);
+    let purge = msg.purge.unwrap_or(false);
+    if purge && !hidden {
+        return Err(KayaError::Config(
+            "Cannot purge an anga being unhidden".to_string(),
+        ));
+    }
+
+    // A purged anga is no longer on disk but can still be unhidden or
+    // hidden again, so only insist on the file when there's no meta for it.
+    let path = get_anga_dir().join(filename);
+    if !path.is_file() && resolve_meta(&get_meta_dir(), filename)?.sources.is_empty() {
+        return Err(KayaError::Config(format!("No such anga: {}", filename)));
+    }
+
+    let mut meta = Meta::new(filename);
+    meta.meta.hidden = Some(hidden);
+    let meta_filename = write_new_meta(&meta, if hidden { "hidden" } else { "unhidden" })?;
+
+    if purge {
+        if path.is_file() {
+            fs::remove_file(&path)?;
+            log::info!("Purged local anga: {}", filename);
+        }
+        purge_from_server(filename)?;
+    }
+
+    Ok(meta_filename)
+}
+
+/// Asks the server to delete an anga. Servers without delete support answer
+/// 404, 405 or 501, which only gets logged: the tombstone still hides it.
+fn purge_from_server(filename: &str) -> Result<(), KayaError> {
+    let Some(credentials) = load_credentials()? else {
+        return Ok(());
+    };
+
+    let url = format!(
+        "{}/api/v1/{}/anga/{}",
+        credentials.server.trim_end_matches('/'),
+        urlencoding::encode(&credentials.email),
+        urlencoding::encode(filename)
+    );
+
+    let response = http_client()
+        .build()?
+        .delete(&url)
+        .basic_auth(&credentials.email, Some(&credentials.password))
+        .send()?;
+
+    match response.status() {
+        s if s.is_success() => log::info!("Purged anga from server: {}", filename),
+        reqwest::StatusCode::NOT_FOUND
+        | reqwest::StatusCode::METHOD_NOT_ALLOWED
+        | reqwest::StatusCode::NOT_IMPLEMENTED => {
+            log::warn!(
+                "Server does not support deleting {}; it stays hidden",
+                filename
+            )
+        }
+        _ => return Err(KayaError::Http(response.error_for_status().unwrap_err())),
+    }
+
+    Ok(())
+}
+
+fn handle_list_tags(msg: &IncomingMessage) -> Result<Vec<TagUsage>, KayaError> {
+    let catalogue = tag_catalogue(&get_meta_dir())?;
+    Ok(complete_tags(
+        &catalogue,
+        msg.query.as_deref().unwrap_or(""),
+        msg.limit.unwrap_or(DEFAULT_TAG_LIMIT),
+    ))
+}
+
+fn handle_get_meta(msg: &IncomingMessage) -> Result<MetaView, KayaError> {
+    let filename = msg
+        .filename
+        .as_ref()
+        .ok_or_else(|| KayaError::Config("Missing filename".to_string()))?;
+
+    Ok(resolve_meta(&get_meta_dir(), filename)?)
+}
+
+fn refresh_search_index() {
+    let mut index = SEARCH_INDEX.lock().unwrap_or_else(|e| e.into_inner());
+    if let Err(e) = index.refresh() {
+        log::error!("Failed to refresh search index: {}", e);
+    }
+}
+
+fn handle_search(msg: &IncomingMessage) -> Result<Vec<SearchHit>, KayaError> {
+    let query = SearchQuery {
+        text: msg.query.clone().unwrap_or_default(),
+        tags: msg.tags.clone().unwrap_or_default(),
+        limit: msg.limit,
+    };
+
+    let mut index = SEARCH_INDEX.lock().unwrap_or_else(|e| e.into_inner());
+    index.refresh()?;
+    Ok(index.search(&query))
+}
+
+fn read_message(reader: &mut impl Read) -> Result<Option<IncomingMessage>, KayaError> {
+    match read_frame(reader)? {
+        Some(frame) => Ok(Some(serde_json::from_slice(&frame)?)),
+        None => Ok(None),
+    }
+}
+
+fn write_message(writer: &mut impl Write, msg: &OutgoingMessage) -> Result<(), KayaError> {
+    write_frame(writer, &serde_json::to_vec(msg)?)?;
+    Ok(())
+}
+
+use savebutton_sync_daemon::anga::{
+    is_plain_filename, list_anga, meta_files_for, parse_date_bound, read_anga, AngaContent,
+    AngaEntry, AngaKind, AngaPage, ListQuery, MetaFile,
+};
+use savebutton_sync_daemon::archive::{
+    asset_filename, asset_references, build_snapshot, css_references, extension_for_type,
+    file_extension, rewrite_css, write_snapshot, ArchiveOptions,
+};
+use savebutton_sync_daemon::bookmarks::{read_shortcut, BookmarkIndex};
+use savebutton_sync_daemon::caller::{detect_caller, parent_process_name, Caller};
+use savebutton_sync_daemon::cli::{parse_args, Command, USAGE};
+use savebutton_sync_daemon::import::{
+    import_bookmarks, parse_netscape_bookmarks, ImportSummary, ImportedBookmark,
+};
+use savebutton_sync_daemon::ipc::{self, read_frame, write_frame};
+use savebutton_sync_daemon::links::{
+    dead_links, interleave_by_host, read_link_status, write_link_status, CheckResult, DeadLink,
+    LinkCheckOptions, LinkStatus, Pacer, LINK_STATE_DIR,
+};
+use savebutton_sync_daemon::lock::{lock_holder, SyncLock};
+use savebutton_sync_daemon::manifest::{
+    browser_targets, check_registration, read_manifest, remove_registration, BrowserFamily,
+    HostManifest, Registration,
+};
+use savebutton_sync_daemon::meta::{
+    hidden_anga, resolve_meta, validate_meta_filename, Meta, MetaError, MetaView,
+};
+use savebutton_sync_daemon::naming::{
+    bookmark_slug, create_timestamped_file, remove_partial_files, slugify, write_atomically,
+};
+use savebutton_sync_daemon::normalize::NormalizeOptions;
+use savebutton_sync_daemon::parse_server_file_listing;
+use savebutton_sync_daemon::pdf_text::{
+    ensure_pdf_text, extract_missing_pdf_text, find_in_pdfs, PdfMatch,
+};
+use savebutton_sync_daemon::places::read_places;
+use savebutton_sync_daemon::preview::{
+    extract_preview, read_preview, write_preview, PagePreview, PreviewOptions, FAVICON_STEM,
+    PREVIEW_IMAGE_STEM,
+};
+use savebutton_sync_daemon::readable::{
+    ensure_readable, extract_missing, extract_readable, write_readable, Readable,
+};
+use savebutton_sync_daemon::schedule::{
+    sync_allowed, ScheduleOptions, Scheduler, SyncOutcome, SyncSignal, Wake,
+};
+use savebutton_sync_daemon::search::{SearchHit, SearchIndex, SearchQuery};
+use savebutton_sync_daemon::shortcut::{ShortcutError, UrlShortcut};
+use savebutton_sync_daemon::shutdown::{join_within, DEFAULT_GRACE_PERIOD};
+use savebutton_sync_daemon::tags::{complete_tags, tag_catalogue, TagUsage, DEFAULT_TAG_LIMIT};
+use savebutton_sync_daemon::thumbnail::{
+    ensure_thumbnail, generate_missing, has_thumbnail_type, THUMBNAIL_FILENAME,
+};
+use savebutton_sync_daemon::verify::{verify_kaya_dir, Severity};
+use savebutton_sync_daemon::watch::{Change, Debouncer, DEFAULT_QUIET_PERIOD};
+
+struct Credentials {
+    server: String,
+    email: String,
+    password: String,
+}
+
+/// Reads the server, email and decrypted password from the config. Returns
+/// `None` until all three are configured.
+fn load_credentials() -> Result<Option<Credentials>, KayaError> {
+    let config = load_config()?;
+
+    let (server, email) = match (config.server, config.email) {
+        (Some(server), Some(email)) => (server, email),
+        _ => return Ok(None),
+    };
+
+    let password = match (&config.encrypted_password, &config.encryption_key) {
+        (Some(enc), Some(key_b64)) => {
+            let key_bytes = BASE64.decode(key_b64)?;
+            let key: [u8; KEY_LEN] = key_bytes
+                .try_into()
+                .map_err(|_| KayaError::Encryption("Invalid key length".to_string()))?;
+            decrypt_password(enc, &key)?
+        }
+        _ => return Ok(None),
+    };
+
+    Ok(Some(Credentials {
+        server,
+        email,
+        password,
+    }))
+}
+
+/// Runs one sync pass and returns the number of files transferred.
+fn sync_with_server() -> Result<usize, KayaError> {
+    let Credentials {
+        server,
+        email,
+        password,
+    } = match load_credentials()? {
+        Some(credentials) => credentials,
+        None => return Ok(0),
+    };
+
+    let client = http_client().build()?;
+
+    let (anga_downloaded, anga_uploaded) = sync_anga(&client, &server, &email, &password)?;
+    let (meta_downloaded, meta_uploaded) = sync_meta(&client, &server, &email, &password)?;
+    // The cache is a convenience; a server without it shouldn't stop sync
+    let cache_uploaded = sync_cache(&client, &server, &email, &password).unwrap_or_else(|e| {
+        log::warn!("Cache sync failed: {}", e);
+        0
+    });
+
+    if anga_downloaded > 0 {
+        spawn_worker("thumbnails", generate_missing_thumbnails);
+        spawn_worker("pdf text", extract_missing_pdf_texts);
+    }
+
+    let total_downloaded = anga_downloaded + meta_downloaded;
+    let total_uploaded = anga_uploaded + meta_uploaded + cache_uploaded;
+
+    if total_downloaded > 0 || total_uploaded > 0 {
+        log::info!(
+            "Sync complete: {} downloaded, {} uploaded",
+            total_downloaded,
+            total_uploaded
+        );
+    }
+
+    Ok(total_downloaded + total_uploaded)
+}
+
+/// Whether the current connection is known to be metered. Only NetworkManager
+/// reports this; everywhere else the connection is assumed to be unmetered.
+fn connection_is_metered() -> bool {
+    std::process::Command::new("nmcli")
+        .args(["-t", "-f", "METERED", "general", "status"])
+        .output()
+        .ok()
+        .filter(|out| out.status.success())
+        .map(|out| {
+            String::from_utf8_lossy(&out.stdout)
+                .trim()
+                .starts_with("yes")
+        })
+        .unwrap_or(false)
+}
+
+/// Makes sure this process holds the sync lock, taking it if it's free.
+/// Only the holder talks to the server, so a browser-launched host and a
+/// standalone daemon never sync the same directory at once; the other one
+/// keeps answering messages and picks the lock up once it's released.
+fn hold_sync_lock() -> bool {
+    let mut held = SYNC_LOCK.lock().unwrap_or_else(|e| e.into_inner());
+    if held.is_some() {
+        return true;
+    }
+
+    match SyncLock::try_acquire(&get_lock_path()) {
+        Ok(Some(lock)) => {
+            log::info!("Took the sync lock");
+            *held = Some(lock);
+            true
+        }
+        Ok(None) => {
+            log::debug!(
+                "Another process (pid {:?}) is syncing; not syncing from this one",
+                lock_holder(&get_lock_path())
+            );
+            false
+        }
+        Err(e) => {
+            log::error!("Failed to take the sync lock: {}", e);
+            false
+        }
+    }
+}
+
+fn run_sync_pass(opts: &ScheduleOptions) -> Option<SyncOutcome> {
+    if !hold_sync_lock() {
+        return None;
+    }
+
+    let metered = opts.unmetered_only && connection_is_metered();
+    if !sync_allowed(opts, Local::now().time(), metered) {
+        log::debug!("Skipping sync: quiet hours or metered connection");
+        return None;
+    }
+
+    let outcome = match sync_with_server() {
+        Ok(0) => SyncOutcome::Idle,
+        Ok(_) => SyncOutcome::Changed,
+        Err(KayaError::Http(e)) => {
+            log::error!("Sync error: {}", e);
+            SyncOutcome::Unreachable
+        }
+        Err(e) => {
+            log::error!("Sync error: {}", e);
+            SyncOutcome::Idle
+        }
+    };
+    if outcome == SyncOutcome::Changed {
+        invalidate_bookmark_index();
+    }
+    refresh_search_index();
+    Some(outcome)
+}
+
+/// Syncs on an interval that backs off while idle or offline, and runs early
+/// when `SYNC_SIGNAL` is woken by a local change.
+fn run_sync_loop() {
+    let mut scheduler = Scheduler::new(&load_config().unwrap_or_default().sync.unwrap_or_default());
+
+    loop {
+        // Re-read each time so config changes apply without a restart
+        let opts = load_config().unwrap_or_default().sync.unwrap_or_default();
+
+        let wait = match run_sync_pass(&opts) {
+            Some(outcome) => scheduler.next_interval(&opts, outcome),
+            None => scheduler.current(),
+        };
+
+        match SYNC_SIGNAL.wait(wait) {
+            Wake::Stopped => break,
+            Wake::Timeout => {}
+            Wake::Woken => {
+                scheduler.local_change(&opts);
+                // Let a burst of changes settle into one pass
+                if SYNC_SIGNAL.wait(Duration::from_secs(opts.min_interval_secs)) == Wake::Stopped {
+                    break;
+                }
+            }
+        }
+    }
+}
+
+/// Requests `url` and reports how it answered. Tries HEAD first, then GET,
+/// since plenty of servers refuse or mishandle HEAD. The body is never read.
+fn check_link(client: &reqwest::blocking::Client, url: &url::Url) -> CheckResult {
+    let response = match client.head(url.clone()).send() {
+        Ok(r) if r.status().is_success() || r.status().is_redirection() => Ok(r),
+        _ => client.get(url.clone()).send(),
+    };
+    match response {
+        Ok(r) => CheckResult {
+            status: Some(r.status().as_u16()),
+            final_url: Some(r.url().to_string()),
+            error: None,
+        },
+        Err(e) => CheckResult {
+            status: None,
+            final_url: None,
+            error: Some(e.to_string()),
+        },
+    }
+}
+
+/// A bookmark due for a link check, with its URL as saved.
+struct DueLink {
+    filename: String,
+    url: String,
+}
+
+/// The bookmarks due for a link check, with their parsed URLs, least
+/// recently checked first.
+fn due_links(opts: &LinkCheckOptions) -> Result<Vec<(DueLink, url::Url)>, KayaError> {
+    let anga_dir = get_anga_dir();
+    if !anga_dir.exists() {
+        return Ok(Vec::new());
+    }
+
+    let now = Utc::now();
+    let mut due = Vec::new();
+    for entry in fs::read_dir(&anga_dir)?.filter_map(|e| e.ok()) {
+        let Some(filename) = entry.file_name().to_str().map(str::to_string) else {
+            continue;
+        };
+        if filename.starts_with('.') || !filename.ends_with(".url") {
+            continue;
+        }
+        let Ok(shortcut) = read_shortcut(&entry.path()) else {
+            continue;
+        };
+        let Some(url) = url::Url::parse(&shortcut.url)
+            .ok()
+            .filter(|u| matches!(u.scheme(), "http" | "https"))
+        else {
+            continue;
+        };
+        let checked_at = match read_link_status(&get_link_state_dir(), &filename) {
+            Ok(Some(status)) if status.url == shortcut.url => {
+                if !status.is_due(opts, now) {
+                    continue;
+                }
+                Some(status.checked_at)
+            }
+            _ => None,
+        };
+        let link = DueLink {
+            filename,
+            url: shortcut.url,
+        };
+        due.push((checked_at, link, url));
+    }
+
+    due.sort_by_key(|(checked_at, _, _)| *checked_at);
+    Ok(due.into_iter().map(|(_, item, url)| (item, url)).collect())
+}
+
+/// Checks every link that's due, spacing requests out as `[links]` says.
+/// Returns how many were checked, stopping early when the daemon shuts
+/// down.
+fn check_due_links(opts: &LinkCheckOptions) -> Result<usize, KayaError> {
+    let client = http_client()
+        .timeout(Duration::from_secs(opts.timeout_secs))
+        .build()?;
+    let mut pacer = Pacer::new(opts);
+    let mut checked = 0;
+
+    for (
+        DueLink {
+            filename,
+            url: saved_url,
+        },
+        url,
+    ) in interleave_by_host(due_links(opts)?)
+    {
+        let at = pacer.reserve(url.host_str().unwrap_or(""), Instant::now());
+        while let Some(wait) = at.checked_duration_since(Instant::now()) {
+            if wait.is_zero() {
+                break;
+            }
+            if LINK_SIGNAL.wait(wait) == Wake::Stopped {
+                return Ok(checked);
+            }
+        }
+
+        let state_dir = get_link_state_dir();
+        let previous = read_link_status(&state_dir, &filename).ok().flatten();
+        let status = LinkStatus::record(
+            previous.as_ref(),
+            &saved_url,
+            check_link(&client, &url),
+            Utc::now(),
+        );
+        if status.is_dead(opts) && !previous.is_some_and(|p| p.is_dead(opts)) {
+            log::warn!("Dead link in {}: {}", filename, saved_url);
+        }
+        write_link_status(&state_dir, &filename, &status)?;
+        checked += 1;
+    }
+
+    Ok(checked)
+}
+
+/// Checks saved links in the background. Like sync, it only runs in the
+/// process holding the sync lock, and not in quiet hours or on a metered
+/// connection.
+fn run_link_loop() {
+    let mut wait = LINK_CHECK_DELAY;
+    loop {
+        if LINK_SIGNAL.wait(wait) == Wake::Stopped {
+            break;
+        }
+        wait = LINK_CHECK_POLL;
+
+        let config = load_config().unwrap_or_default();
+        let opts = config.links.unwrap_or_default();
+        let schedule = config.sync.unwrap_or_default();
+        let metered = schedule.unmetered_only && connection_is_metered();
+        if !opts.enabled
+            || !hold_sync_lock()
+            || !sync_allowed(&schedule, Local::now().time(), metered)
+        {
+            continue;
+        }
+
+        match check_due_links(&opts) {
+            Ok(0) => {}
+            Ok(n) => log::info!("Checked {} link(s)", n),
+            Err(e) => log::warn!("Link check failed: {}", e),
+        }
+    }
+}
+
+/// Bookmarks whose links are dead, newest first. Hidden anga are left out.
+fn handle_dead_links(msg: &IncomingMessage) -> Result<Vec<DeadLink>, KayaError> {
+    let opts = load_config()?.links.unwrap_or_default();
+    let hidden = hidden_anga(&get_meta_dir())?;
+    let mut dead = dead_links(&get_anga_dir(), &get_link_state_dir(), &opts)?;
+    dead.retain(|d| !hidden.contains(&d.filename));
+    if let Some(limit) = msg.limit {
+        dead.truncate(limit);
+    }
+    Ok(dead)
+}
+
+fn sync_anga(
+    client: &reqwest::blocking::Client,
+    server: &str,
+    email: &str,
+    password: &str,
+) -> Result<(usize, usize), KayaError> {
+    let url = format!(
+        "{}/api/v1/{}/anga",
+        server.trim_end_matches('/'),
+        urlencoding::encode(email)
+    );
+
+    let response = client.get(&url).basic_auth(email, Some(password)).send()?;
+
+    if !response.status().is_success() {
+        return Err(KayaError::Http(response.error_for_status().unwrap_err()));
+    }
+
+    let server_files: HashSet<String> = parse_server_file_listing(&response.text()?);
+
+    let anga_dir = get_anga_dir();
+    let local_files: HashSet<String> = if anga_dir.exists() {
+        fs::read_dir(&anga_dir)?
+            .filter_map(|e| e.ok())
+            .filter(|e| e.path().is_file())
+            .filter_map(|e| e.file_name().into_string().ok())
+            .filter(|n| !n.starts_with('.'))
+            .collect()
+    } else {
+        HashSet::new()
+    };
+
+    // Hidden anga stay gone locally once purged, rather than coming back
+    let hidden = hidden_anga(&get_meta_dir())?;
+    let to_download: Vec<_> = server_files
+        .difference(&local_files)
+        .filter(|f| !hidden.contains(urlencoding::decode(f).as_deref().unwrap_or(f)))
+        .collect();
+    let to_upload: Vec<_> = local_files.difference(&server_files).collect();
+
+    let downloaded = to_download.len();
+    let uploaded = to_upload.len();
+
+    for filename in to_download {
+        if SYNC_SIGNAL.is_stopped() {
+            return Ok((downloaded, uploaded));
+        }
+        log::info!("  downloading anga: {}", filename);
+        download_anga(client, server, email, password, filename)?;
+    }
+
+    for filename in to_upload {
+        if SYNC_SIGNAL.is_stopped() {
+            return Ok((downloaded, uploaded));
+        }
+        log::info!("  uploading anga: {}", filename);
+        upload_anga(client, server, email, password, filename)?;
+    }
+
+    Ok((downloaded, uploaded))
+}
+
+fn download_anga(
+    client: &reqwest::blocking::Client,
+    server: &str,
+    email: &str,
+    password: &str,
+    filename: &str,
+) -> Result<(), KayaError> {
+    // filename is already URL-encoded from the server listing
+    let url = format!(
+        "{}/api/v1/{}/anga/{}",
+        server.trim_end_matches('/'),
+        urlencoding::encode(email),
+        filename
+    );
+
+    let response = client.get(&url).basic_auth(email, Some(password)).send()?;
+
+    if response.status().is_success() {
+        let content = response.bytes()?;
+        write_atomically(&get_anga_dir().join(filename), &content)?;
+    }
+
+    Ok(())
+}
+
+fn upload_anga(
+    client: &reqwest::blocking::Client,
+    server: &str,
+    email: &str,
+    password: &str,
+    filename: &str,
+) -> Result<(), KayaError> {
+    let path = get_anga_dir().join(filename);
+    let content = fs::read(&path)?;
+
+    let url = format!(
+        "{}/api/v1/{}/anga/{}",
+        server.trim_end_matches('/'),
+        urlencoding::encode(email),
+        urlencoding::encode(filename)
+    );
+
+    let content_type = mime_type_for(filename);
+
+    let part = reqwest::blocking::multipart::Part::bytes(content)
+        .file_name(filename.to_string())
+        .mime_str(&content_type)
+        .unwrap();
 
     let form = reqwest::blocking::multipart::Form::new().part("file", part);
 
@@ -613,11 +1958,17 @@ fn sync_meta(
     let uploaded = to_upload.len();
 
     for filename in to_download {
+        if SYNC_SIGNAL.is_stopped() {
+            return Ok((downloaded, uploaded));
+        }
         log::info!("  downloading meta: {}", filename);
         download_meta(client, server, email, password, filename)?;
     }
 
     for filename in to_upload {
+        if SYNC_SIGNAL.is_stopped() {
+            return Ok((downloaded, uploaded));
+        }
         log::info!("  uploading meta: {}", filename);
         upload_meta(client, server, email, password, filename)?;
     }
@@ -644,8 +1995,13 @@ fn download_meta(
 
     if response.status().is_success() {
         let content = response.bytes()?;
-        let path = get_meta_dir().join(filename);
-        fs::write(path, content)?;
+        // Invalid meta is still kept: the server is the source of truth and
+        // dropping it would only re-download it on the next pass.
+        match Meta::parse(&String::from_utf8_lossy(&content)) {
+            Ok(meta) => warn_if_orphaned(filename, &meta),
+            Err(e) => log::warn!("Downloaded invalid meta {}: {}", filename, e),
+        }
+        write_atomically(&get_meta_dir().join(filename), &content)?;
     }
 
     Ok(())
@@ -670,7 +2026,108 @@ fn upload_meta(
 
     let part = reqwest::blocking::multipart::Part::bytes(content)
         .file_name(filename.to_string())
-        .mime_str("application/toml")
+        .mime_str("application/toml")
+        .unwrap();
+
+    let form = reqwest::blocking::multipart::Form::new().part("file", part);
+
+    let response = client
+        .post(&url)
+        .basic_auth(email, Some(password))
+        .multipart(form)
+        .send()?;
+
+    if response.status() == reqwest::StatusCode::CONFLICT {
+        // File already exists, that's fine
+    } else if !response.status().is_success() {
+        log::error!("Failed to upload meta {}: {}", filename, response.status());
+    }
+
+    Ok(())
+}
+
+/// Uploads cache directories the server doesn't have yet, such as page
+/// snapshots taken while offline. Files added to a directory the server
+/// already has are left to the watcher. Nothing is downloaded: the cache can
+/// always be rebuilt from the anga.
+fn sync_cache(
+    client: &reqwest::blocking::Client,
+    server: &str,
+    email: &str,
+    password: &str,
+) -> Result<usize, KayaError> {
+    let cache_dir = get_cache_dir();
+    if !cache_dir.exists() {
+        return Ok(0);
+    }
+
+    let url = format!(
+        "{}/api/v1/{}/cache",
+        server.trim_end_matches('/'),
+        urlencoding::encode(email)
+    );
+
+    let response = client.get(&url).basic_auth(email, Some(password)).send()?;
+
+    if !response.status().is_success() {
+        return Err(KayaError::Http(response.error_for_status().unwrap_err()));
+    }
+
+    let server_dirs: HashSet<String> = parse_server_file_listing(&response.text()?)
+        .into_iter()
+        .map(|d| d.trim_end_matches('/').to_string())
+        .collect();
+
+    let mut uploaded = 0;
+    for entry in fs::read_dir(&cache_dir)?.filter_map(|e| e.ok()) {
+        let Ok(anga) = entry.file_name().into_string() else {
+            continue;
+        };
+        if anga.starts_with('.') || !entry.path().is_dir() || server_dirs.contains(&anga) {
+            continue;
+        }
+
+        for file in fs::read_dir(entry.path())?.filter_map(|e| e.ok()) {
+            if SYNC_SIGNAL.is_stopped() {
+                return Ok(uploaded);
+            }
+            let Ok(filename) = file.file_name().into_string() else {
+                continue;
+            };
+            if filename.starts_with('.') || !file.path().is_file() {
+                continue;
+            }
+            log::info!("  uploading cache: {}/{}", anga, filename);
+            upload_cache(client, server, email, password, &anga, &filename)?;
+            uploaded += 1;
+        }
+    }
+
+    Ok(uploaded)
+}
+
+fn upload_cache(
+    client: &reqwest::blocking::Client,
+    server: &str,
+    email: &str,
+    password: &str,
+    anga: &str,
+    filename: &str,
+) -> Result<(), KayaError> {
+    let path = get_cache_dir().join(anga).join(filename);
+    let content = fs::read(&path)?;
+
+    let url = format!(
+        "{}/api/v1/{}/cache/{}/{}",
+        server.trim_end_matches('/'),
+        urlencoding::encode(email),
+        urlencoding::encode(anga),
+        urlencoding::encode(filename)
+    );
+
+    let part = reqwest::blocking::multipart::Part::bytes(content)
+        .file_name(filename.to_string())
+        .mime_str(&mime_type_for(filename))
         .unwrap();
 
     let form = reqwest::blocking::multipart::Form::new().part("file", part);
@@ -684,12 +2141,129 @@ fn upload_meta(
     if response.status() == reqwest::StatusCode::CONFLICT {
         // File already exists, that's fine
     } else if !response.status().is_success() {
-        log::error!("Failed to upload meta {}: {}", filename, response.status());
+        log::error!(
+            "Failed to upload cache {}/{}: {}",
+            anga,
+            filename,
+            response.status()
+        );
+    }
+
+    Ok(())
+}
+
+/// Uploads files that changed on disk without waiting for the next poll.
+/// Anything already on the server is answered with a conflict and skipped.
+fn upload_changes(changes: &[Change]) -> Result<(), KayaError> {
+    // The lock holder's own watcher or poll will pick these up
+    if !hold_sync_lock() {
+        return Ok(());
+    }
+
+    let Some(Credentials {
+        server,
+        email,
+        password,
+    }) = load_credentials()?
+    else {
+        return Ok(());
+    };
+
+    let client = http_client().build()?;
+    let kaya_dir = get_kaya_dir();
+
+    for change in changes {
+        if SYNC_SIGNAL.is_stopped() {
+            break;
+        }
+        // Removed or renamed away before it settled
+        if !change.path(&kaya_dir).is_file() {
+            continue;
+        }
+
+        let result = match change {
+            Change::Anga(filename) => {
+                log::info!("  uploading changed anga: {}", filename);
+                upload_anga(&client, &server, &email, &password, filename)
+            }
+            Change::Meta(filename) => {
+                log::info!("  uploading changed meta: {}", filename);
+                upload_meta(&client, &server, &email, &password, filename)
+            }
+            Change::Cache { anga, filename } => {
+                log::info!("  uploading changed cache: {}/{}", anga, filename);
+                upload_cache(&client, &server, &email, &password, anga, filename)
+            }
+        };
+        if let Err(e) = result {
+            log::error!("Failed to upload {:?}: {}", change, e);
+        }
     }
 
     Ok(())
 }
 
+/// Watches `anga/`, `meta/` and `cache/` and uploads files as they settle.
+/// The returned watcher has to be kept alive for events to keep arriving.
+/// The polling sync loop stays in place as a fallback for anything missed.
+fn spawn_watcher() -> notify::Result<notify::RecommendedWatcher> {
+    use notify::{EventKind, RecursiveMode, Watcher};
+
+    let (tx, rx) = mpsc::channel();
+    let mut watcher = notify::recommended_watcher(tx)?;
+
+    let kaya_dir = get_kaya_dir();
+    watcher.watch(&get_anga_dir(), RecursiveMode::NonRecursive)?;
+    watcher.watch(&get_meta_dir(), RecursiveMode::NonRecursive)?;
+    watcher.watch(&get_cache_dir(), RecursiveMode::Recursive)?;
+
+    spawn_worker("watcher", move || {
+        let mut debouncer = Debouncer::new(DEFAULT_QUIET_PERIOD);
+        loop {
+            if SYNC_SIGNAL.is_stopped() {
+                if !debouncer.is_empty() {
+                    log::info!("Leaving unsettled changes for the next sync");
+                }
+                break;
+            }
+
+            // Wake at least once a second to notice shutdown
+            let timeout = debouncer
+                .next_deadline()
+                .map(|at| at.saturating_duration_since(Instant::now()))
+                .unwrap_or(Duration::MAX)
+                .min(Duration::from_secs(1));
+
+            match rx.recv_timeout(timeout) {
+                Ok(Ok(event)) => {
+                    if matches!(event.kind, EventKind::Create(_) | EventKind::Modify(_)) {
+                        for path in &event.paths {
+                            if let Some(change) = Change::classify(&kaya_dir, path) {
+                                debouncer.record(change, Instant::now());
+                            }
+                        }
+                    }
+                }
+                Ok(Err(e)) => log::warn!("Watch error: {}", e),
+                Err(mpsc::RecvTimeoutError::Timeout) => {}
+                Err(mpsc::RecvTimeoutError::Disconnected) => break,
+            }
+
+            let ready = debouncer.take_ready(Instant::now());
+            if !ready.is_empty() {
+                if let Err(e) = upload_changes(&ready) {
+                    log::error!("Sync error: {}", e);
+                }
+                invalidate_bookmark_index();
+                refresh_search_index();
+                SYNC_SIGNAL.wake();
+            }
+        }
+    });
+
+    Ok(watcher)
+}
+
 fn mime_type_for(filename: &str) -> String {
     let ext = filename.rsplit('.').next().unwrap_or("").to_lowercase();
     match ext.as_str() {
@@ -703,113 +2277,971 @@ fn mime_type_for(filename: &str) -> String {
         "gif" => "image/gif",
         "webp" => "image/webp",
         "svg" => "image/svg+xml",
+        "ico" => "image/x-icon",
+        "avif" => "image/avif",
         "html" | "htm" => "text/html",
         _ => "application/octet-stream",
     }
     .to_string()
 }
 
-fn main() {
-    setup_logging();
+fn error_response(id: Option<u64>, e: KayaError) -> OutgoingMessage {
+    OutgoingMessage {
+        id,
+        success: false,
+        error: Some(e.to_string()),
+        ..Default::default()
+    }
+}
 
-    if let Err(e) = ensure_directories() {
-        log::error!("Failed to create directories: {}", e);
-        std::process::exit(1);
+fn handle_message(msg: &IncomingMessage) -> OutgoingMessage {
+    let id = msg.id;
+
+    match msg.message.as_str() {
+        "config_status" => match handle_config_status(msg) {
+            Ok(has_password) => OutgoingMessage {
+                id,
+                success: true,
+                has_password: Some(has_password),
+                ..Default::default()
+            },
+            Err(e) => error_response(id, e),
+        },
+        "bookmark_status" => {
+            let include_preview = msg.include_preview.unwrap_or(false);
+            match handle_bookmark_status(msg) {
+                Ok(filenames) => OutgoingMessage {
+                    id,
+                    success: true,
+                    bookmarked: Some(!filenames.is_empty()),
+                    preview: include_preview
+                        .then(|| bookmark_preview(&filenames))
+                        .flatten(),
+                    filenames: Some(filenames),
+                    ..Default::default()
+                },
+                Err(e) => error_response(id, e),
+            }
+        }
+        "list_anga" => match handle_list_anga(msg) {
+            Ok(page) => OutgoingMessage {
+                id,
+                success: true,
+                items: Some(page.items),
+                total: Some(page.total),
+                ..Default::default()
+            },
+            Err(e) => error_response(id, e),
+        },
+        "get_anga" => match handle_get_anga(msg) {
+            Ok((anga, meta, view)) => OutgoingMessage {
+                id,
+                success: true,
+                anga: Some(anga),
+                meta: Some(meta),
+                metadata: Some(view),
+                ..Default::default()
+            },
+            Err(e) => error_response(id, e),
+        },
+        "get_meta" => match handle_get_meta(msg) {
+            Ok(view) => OutgoingMessage {
+                id,
+                success: true,
+                metadata: Some(view),
+                ..Default::default()
+            },
+            Err(e) => error_response(id, e),
+        },
+        "search" => match handle_search(msg) {
+            Ok(results) => OutgoingMessage {
+                id,
+                success: true,
+                results: Some(results),
+                ..Default::default()
+            },
+            Err(e) => error_response(id, e),
+        },
+        "get_readable" => match handle_get_readable(msg) {
+            Ok(readable) => OutgoingMessage {
+                id,
+                success: true,
+                readable: Some(readable),
+                ..Default::default()
+            },
+            Err(e) => error_response(id, e),
+        },
+        "import_bookmarks" => match handle_import_bookmarks(msg) {
+            Ok(summary) => OutgoingMessage {
+                id,
+                success: true,
+                imported: Some(summary),
+                ..Default::default()
+            },
+            Err(e) => error_response(id, e),
+        },
+        "dead_links" => match handle_dead_links(msg) {
+            Ok(links) => OutgoingMessage {
+                id,
+                success: true,
+                links: Some(links),
+                ..Default::default()
+            },
+            Err(e) => error_response(id, e),
+        },
+        "find_in_pdfs" => match handle_find_in_pdfs(msg) {
+            Ok(pdfs) => OutgoingMessage {
+                id,
+                success: true,
+                pdfs: Some(pdfs),
+                ..Default::default()
+            },
+            Err(e) => error_response(id, e),
+        },
+        "get_thumbnail" => match handle_get_thumbnail(msg) {
+            Ok(thumbnail) => OutgoingMessage {
+                id,
+                success: true,
+                thumbnail: Some(thumbnail),
+                ..Default::default()
+            },
+            Err(e) => error_response(id, e),
+        },
+        "list_tags" => match handle_list_tags(msg) {
+            Ok(tags) => OutgoingMessage {
+                id,
+                success: true,
+                tags: Some(tags),
+                ..Default::default()
+            },
+            Err(e) => error_response(id, e),
+        },
+        "anga" | "meta" | "tag_anga" | "hide_anga" | "save_url" | "save_quote" | "save_image" => {
+            let result = match msg.message.as_str() {
+                "anga" => handle_anga_message(msg),
+                "meta" => handle_meta_message(msg),
+                "tag_anga" => handle_tag_anga(msg),
+                "hide_anga" => handle_hide_anga(msg),
+                "save_url" => handle_save_url(msg),
+                "save_quote" => handle_save_quote(msg),
+                _ => handle_save_image(msg),
+            };
+
+            if let (Ok(filename), Some(browser)) = (&result, &msg.browser) {
+                let creates_anga =
+                    !matches!(msg.message.as_str(), "meta" | "tag_anga" | "hide_anga");
+                if creates_anga {
+                    record_browser(filename, browser);
+                }
+            }
+
+            if let Ok(filename) = &result {
+                let saves_bookmark = matches!(msg.message.as_str(), "anga" | "save_url")
+                    && filename.ends_with(".url");
+                let opts = load_config()
+                    .ok()
+                    .and_then(|c| c.archive)
+                    .unwrap_or_default();
+                if saves_bookmark {
+                    spawn_capture(
+                        filename.clone(),
+                        msg.html.clone(),
+                        wants_archive(msg, &opts),
+                    );
+                } else if matches!(msg.message.as_str(), "anga" | "save_image") {
+                    spawn_thumbnail(filename.clone());
+                    spawn_pdf_text(filename.clone());
+                }
+            }
+
+            if result.is_ok() {
+                invalidate_bookmark_index();
+                refresh_search_index();
+                SYNC_SIGNAL.wake();
+            }
+
+            match result {
+                Ok(filename) => OutgoingMessage {
+                    id,
+                    success: true,
+                    filename: Some(filename),
+                    urls: get_all_bookmarked_urls().ok(),
+                    message_type: Some("bookmarks".to_string()),
+                    ..Default::default()
+                },
+                Err(e) => error_response(id, e),
+            }
+        }
+        _ => {
+            let result = match msg.message.as_str() {
+                "config" => handle_config_message(msg),
+                "test_connection" => handle_test_connection(msg),
+                "sync" => {
+                    SYNC_SIGNAL.wake();
+                    Ok(())
+                }
+                other => Err(KayaError::Config(format!(
+                    "Unknown message type: {}",
+                    other
+                ))),
+            };
+
+            match result {
+                Ok(_) => OutgoingMessage {
+                    id,
+                    success: true,
+                    urls: get_all_bookmarked_urls().ok(),
+                    message_type: Some("bookmarks".to_string()),
+                    ..Default::default()
+                },
+                Err(e) => error_response(id, e),
+            }
+        }
+    }
+}
+
+/// Starts a thread that shutdown waits for. Nothing new starts once
+/// shutdown has begun, and threads that have finished are joined here so
+/// the list only holds running ones.
+fn spawn_worker(name: &str, f: impl FnOnce() + Send + 'static) {
+    if SYNC_SIGNAL.is_stopped() {
+        return;
     }
+    let thread = thread::spawn(f);
+    let mut workers = WORKERS.lock().unwrap_or_else(|e| e.into_inner());
+    let (finished, running): (Vec<_>, Vec<_>) = std::mem::take(&mut *workers)
+        .into_iter()
+        .partition(|(_, t)| t.is_finished());
+    for (name, thread) in finished {
+        if thread.join().is_err() {
+            log::error!("{} thread panicked", name);
+        }
+    }
+    *workers = running;
+    workers.push((name.to_string(), thread));
+}
 
-    log::info!("Kaya sync daemon started");
+/// Answers messages from one socket client until it disconnects.
+fn serve_client(mut stream: ipc::Stream) {
+    loop {
+        let response = match read_message(&mut stream) {
+            Ok(Some(msg)) => handle_message(&msg),
+            Ok(None) => break,
+            Err(KayaError::Json(e)) => error_response(None, e.into()),
+            Err(e) => {
+                log::warn!("Dropping socket client: {}", e);
+                break;
+            }
+        };
+        if let Err(e) = write_message(&mut stream, &response) {
+            log::warn!("Dropping socket client: {}", e);
+            break;
+        }
+    }
+}
 
-    let running = Arc::new(AtomicBool::new(true));
-    let running_clone = running.clone();
+/// Listens on the local socket so browser hosts, other browsers and command
+/// line tools can share this daemon. Each client gets its own thread; they
+/// aren't waited for on shutdown since an idle client never finishes.
+fn spawn_ipc_listener() -> io::Result<()> {
+    let Some(listener) = ipc::listen(&get_kaya_dir())? else {
+        return Err(io::Error::new(
+            io::ErrorKind::AddrInUse,
+            "another daemon is already listening",
+        ));
+    };
 
     thread::spawn(move || {
-        while running_clone.load(Ordering::Relaxed) {
-            if let Err(e) = sync_with_server() {
-                log::error!("Sync error: {}", e);
+        use interprocess::local_socket::traits::ListenerExt;
+        for stream in listener.incoming() {
+            match stream {
+                Ok(stream) => {
+                    thread::spawn(move || serve_client(stream));
+                }
+                Err(e) => log::warn!("Failed to accept socket client: {}", e),
             }
-            thread::sleep(Duration::from_secs(60));
         }
     });
 
+    Ok(())
+}
+
+/// Adds the calling browser to a request, unless it names one already, so
+/// the daemon can record where anga came from.
+fn tag_request(request: Vec<u8>, browser: Option<&str>) -> Vec<u8> {
+    let Some(browser) = browser else {
+        return request;
+    };
+    match serde_json::from_slice::<serde_json::Value>(&request) {
+        Ok(serde_json::Value::Object(mut map)) if !map.contains_key("browser") => {
+            map.insert("browser".to_string(), browser.into());
+            serde_json::to_vec(&map).unwrap_or(request)
+        }
+        _ => request,
+    }
+}
+
+/// Relays messages between the browser and the standalone daemon, which does
+/// the actual work. If the daemon goes away the host exits, and the
+/// extension starts a fresh one that works on its own.
+fn run_proxy(mut daemon: ipc::Stream, browser: Option<&str>) -> ! {
+    log::info!("Kaya sync daemon forwarding to the standalone daemon");
+
     loop {
-        match read_native_message() {
-            Ok(Some(msg)) => {
-                let id = msg.id;
-
-                let response = if msg.message == "config_status" {
-                    match handle_config_status(&msg) {
-                        Ok(has_password) => OutgoingMessage {
-                            id,
-                            success: true,
-                            error: None,
-                            urls: None,
-                            message_type: None,
-                            has_password: Some(has_password),
-                        },
-                        Err(e) => OutgoingMessage {
-                            id,
-                            success: false,
-                            error: Some(e.to_string()),
-                            urls: None,
-                            message_type: None,
-                            has_password: None,
-                        },
-                    }
+        let request = match read_frame(&mut io::stdin().lock()) {
+            Ok(Some(request)) => tag_request(request, browser),
+            Ok(None) => std::process::exit(0),
+            Err(e) => {
+                log::error!("Error reading message: {}", e);
+                let _ = write_message(&mut io::stdout().lock(), &error_response(None, e.into()));
+                continue;
+            }
+        };
+
+        let response = write_frame(&mut daemon, &request).and_then(|_| {
+            read_frame(&mut daemon)?.ok_or_else(|| io::ErrorKind::UnexpectedEof.into())
+        });
+        match response {
+            Ok(response) => {
+                if let Err(e) = write_frame(&mut io::stdout().lock(), &response) {
+                    log::error!("Failed to write response: {}", e);
+                }
+            }
+            Err(e) => {
+                log::error!("Lost the standalone daemon: {}", e);
+                let id = serde_json::from_slice::<IncomingMessage>(&request)
+                    .ok()
+                    .and_then(|msg| msg.id);
+                let _ = write_message(&mut io::stdout().lock(), &error_response(id, e.into()));
+                std::process::exit(1);
+            }
+        }
+    }
+}
+
+/// Stops the sync loop, watcher and background cache work, gives in-flight
+/// transfers `DEFAULT_GRACE_PERIOD` to finish, then exits. Downloads and
+/// cache files are written to a partial file and renamed into place, so one
+/// that gets abandoned leaves nothing behind but a partial file, which is
+/// removed on the next start.
+fn shutdown(reason: &str) -> ! {
+    log::info!("Kaya sync daemon shutting down: {}", reason);
+    SYNC_SIGNAL.stop();
+    LINK_SIGNAL.stop();
+
+    let workers = std::mem::take(&mut *WORKERS.lock().unwrap_or_else(|e| e.into_inner()));
+    let abandoned = join_within(workers, DEFAULT_GRACE_PERIOD);
+    if !abandoned.is_empty() {
+        log::warn!(
+            "Abandoning in-flight work after {}s: {}",
+            DEFAULT_GRACE_PERIOD.as_secs(),
+            abandoned.join(", ")
+        );
+    }
+
+    SYNC_LOCK.lock().unwrap_or_else(|e| e.into_inner()).take();
+    if IPC_LISTENING.load(Ordering::SeqCst) {
+        ipc::remove_socket(&get_kaya_dir());
+    }
+    log::logger().flush();
+    std::process::exit(0);
+}
+
+/// Reads all of stdin, for commands whose text wasn't given as an argument.
+fn read_stdin() -> Result<String, KayaError> {
+    let mut text = String::new();
+    io::stdin().read_to_string(&mut text)?;
+    Ok(text)
+}
+
+/// Syncs from this process, or asks the running daemon to if it holds the
+/// sync lock.
+fn run_sync_command() -> Result<(), KayaError> {
+    if load_credentials()?.is_none() {
+        return Err(KayaError::Config(
+            "Not configured: set server, email and password with `config set`".to_string(),
+        ));
+    }
+
+    if hold_sync_lock() {
+        let transferred = sync_with_server()?;
+        println!("Transferred {} file(s)", transferred);
+        return Ok(());
+    }
+
+    let mut daemon = ipc::connect(&get_kaya_dir()).map_err(|_| {
+        KayaError::Config(format!(
+            "Another process (pid {:?}) is syncing; try again once it's done",
+            lock_holder(&get_lock_path())
+        ))
+    })?;
+    let request = IncomingMessage {
+        message: "sync".to_string(),
+        ..Default::default()
+    };
+    write_frame(&mut daemon, &serde_json::to_vec(&request)?)?;
+    read_frame(&mut daemon)?;
+    println!("Asked the running daemon to sync");
+    Ok(())
+}
+
+fn print_status() -> Result<(), KayaError> {
+    let config = load_config()?;
+    let kaya_dir = get_kaya_dir();
+    let count = |dir: PathBuf| {
+        fs::read_dir(dir)
+            .map(|entries| {
+                entries
+                    .filter_map(|e| e.ok())
+                    .filter(|e| !e.file_name().to_string_lossy().starts_with('.'))
+                    .count()
+            })
+            .unwrap_or(0)
+    };
+    let syncing = match SyncLock::try_acquire(&get_lock_path())? {
+        Some(_) => "no".to_string(),
+        None => match lock_holder(&get_lock_path()) {
+            Some(pid) => format!("yes (pid {})", pid),
+            None => "yes".to_string(),
+        },
+    };
+
+    println!(
+        "Server:   {}",
+        config.server.as_deref().unwrap_or("(not set)")
+    );
+    println!(
+        "Email:    {}",
+        config.email.as_deref().unwrap_or("(not set)")
+    );
+    println!(
+        "Password: {}",
+        if config.encrypted_password.is_some() {
+            "set"
+        } else {
+            "not set"
+        }
+    );
+    println!("Data:     {}", kaya_dir.display());
+    println!("Anga:     {}", count(get_anga_dir()));
+    println!("Meta:     {}", count(get_meta_dir()));
+    println!(
+        "Daemon:   {}",
+        if ipc::connect(&kaya_dir).is_ok() {
+            "running"
+        } else {
+            "not running"
+        }
+    );
+    println!("Syncing:  {}", syncing);
+    Ok(())
+}
+
+fn run_verify() -> Result<(), KayaError> {
+    let problems = verify_kaya_dir(&get_kaya_dir())?;
+    for problem in &problems {
+        let severity = match problem.severity {
+            Severity::Error => "error",
+            Severity::Warning => "warning",
+        };
+        println!("{}: {}: {}", severity, problem.path, problem.message);
+    }
+
+    let errors = problems
+        .iter()
+        .filter(|p| p.severity == Severity::Error)
+        .count();
+    if errors > 0 {
+        return Err(KayaError::Config(format!(
+            "{} file(s) failed verification",
+            errors
+        )));
+    }
+    if problems.is_empty() {
+        println!("No problems found");
+    }
+    Ok(())
+}
+
+fn home_dir() -> Result<PathBuf, KayaError> {
+    dirs::home_dir().ok_or_else(|| KayaError::Config("Could not find home directory".to_string()))
+}
+
+/// Whether the Windows registry key `key` under `HKEY_CURRENT_USER` names
+/// `manifest`.
+fn registry_points_at(key: &str, manifest: &Path) -> bool {
+    std::process::Command::new("reg")
+        .args(["query", &format!(r"HKCU\{}", key), "/ve"])
+        .output()
+        .ok()
+        .filter(|out| out.status.success())
+        .map(|out| String::from_utf8_lossy(&out.stdout).contains(&*manifest.to_string_lossy()))
+        .unwrap_or(false)
+}
+
+fn set_registry_key(key: &str, manifest: &Path) -> Result<(), KayaError> {
+    let key = format!(r"HKCU\{}", key);
+    let status = std::process::Command::new("reg")
+        .args(["add", &key, "/ve", "/t", "REG_SZ", "/f", "/d"])
+        .arg(manifest)
+        .status()?;
+    if !status.success() {
+        return Err(KayaError::Config(format!(
+            "Failed to set registry key {}",
+            key
+        )));
+    }
+    Ok(())
+}
+
+fn delete_registry_key(key: &str) -> bool {
+    std::process::Command::new("reg")
+        .args(["delete", &format!(r"HKCU\{}", key), "/f"])
+        .output()
+        .map(|out| out.status.success())
+        .unwrap_or(false)
+}
+
+/// Registers this binary with every browser found for the current user, or
+/// with `check` only reports on what's registered. Chromium-family browsers
+/// need the extension's origin, from `chromium_origins` or an earlier
+/// registration. Fails if any registration was left missing or stale.
+fn install_host(check: bool, chromium_origins: &[String]) -> Result<(), KayaError> {
+    let exe = std::env::current_exe()?;
+    let mut problems = 0;
+
+    for target in browser_targets(&home_dir()?) {
+        if !target.is_present() {
+            continue;
+        }
+        if let Some(reason) = target.unsupported {
+            println!("{}: skipped, {}", target.name, reason);
+            continue;
+        }
+
+        let expected = match target.family {
+            BrowserFamily::Firefox => HostManifest::firefox(&exe),
+            BrowserFamily::Chromium => {
+                let origins = if chromium_origins.is_empty() {
+                    read_manifest(&target.manifest)
+                        .map(|m| m.allowed_origins)
+                        .unwrap_or_default()
                 } else {
-                    let result = match msg.message.as_str() {
-                        "config" => handle_config_message(&msg),
-                        "test_connection" => handle_test_connection(&msg),
-                        "anga" => handle_anga_message(&msg),
-                        "meta" => handle_meta_message(&msg),
-                        other => Err(KayaError::Config(format!(
-                            "Unknown message type: {}",
-                            other
-                        ))),
-                    };
-
-                    match result {
-                        Ok(_) => {
-                            let urls = get_all_bookmarked_urls().ok();
-                            OutgoingMessage {
-                                id,
-                                success: true,
-                                error: None,
-                                urls,
-                                message_type: Some("bookmarks".to_string()),
-                                has_password: None,
-                            }
-                        }
-                        Err(e) => OutgoingMessage {
-                            id,
-                            success: false,
-                            error: Some(e.to_string()),
-                            urls: None,
-                            message_type: None,
-                            has_password: None,
-                        },
+                    chromium_origins.to_vec()
+                };
+                if origins.is_empty() {
+                    println!("{}: skipped, needs --chromium-origin", target.name);
+                    continue;
+                }
+                HostManifest::chromium(&exe, &origins)
+            }
+        };
+
+        let registration = check_registration(&target.manifest, &expected);
+        let registry_ok = target
+            .registry_key
+            .as_ref()
+            .is_none_or(|key| registry_points_at(key, &target.manifest));
+
+        let status = match registration {
+            Registration::Current if registry_ok => "up to date".to_string(),
+            Registration::Foreign(reason) => {
+                problems += 1;
+                format!("left {} alone, {}", target.manifest.display(), reason)
+            }
+            registration if check => {
+                problems += 1;
+                match registration {
+                    Registration::Stale(found) => {
+                        format!("out of date, points at {}", found.path.display())
+                    }
+                    Registration::Missing => "not registered".to_string(),
+                    _ => "registry key missing".to_string(),
+                }
+            }
+            registration => {
+                if let Some(dir) = target.manifest.parent() {
+                    fs::create_dir_all(dir)?;
+                }
+                fs::write(&target.manifest, expected.to_json())?;
+                if let Some(key) = &target.registry_key {
+                    set_registry_key(key, &target.manifest)?;
+                }
+                match registration {
+                    Registration::Stale(found) => {
+                        format!("updated, was {}", found.path.display())
                     }
+                    _ => format!("registered at {}", target.manifest.display()),
+                }
+            }
+        };
+        println!("{}: {}", target.name, status);
+    }
+
+    if problems > 0 {
+        return Err(KayaError::Config(format!(
+            "{} registration(s) need attention",
+            problems
+        )));
+    }
+    Ok(())
+}
+
+/// Removes every registration this binary could have made. Manifests for
+/// other hosts are left alone.
+fn uninstall_host() -> Result<(), KayaError> {
+    let mut removed_any = false;
+
+    for target in browser_targets(&home_dir()?) {
+        let removed = remove_registration(&target.manifest)?;
+        let unregistered = target
+            .registry_key
+            .as_ref()
+            .is_some_and(|key| delete_registry_key(key));
+        if removed || unregistered {
+            println!("{}: removed", target.name);
+            removed_any = true;
+        }
+    }
+
+    if !removed_any {
+        println!("Not registered with any browser");
+    }
+    Ok(())
+}
+
+/// Prints what an import did, or would do with `--dry-run`.
+fn print_import_summary(summary: &ImportSummary, dry_run: bool) {
+    println!(
+        "{} {} bookmark(s), skipped {} already bookmarked and {} that aren't links to pages",
+        if dry_run { "Would import" } else { "Imported" },
+        summary.imported,
+        summary.existing,
+        summary.unsupported
+    );
+}
+
+/// Carries out a command-line command through the same handlers native
+/// messages use, printing the result to stdout.
+fn run_command(command: Command) -> Result<(), KayaError> {
+    ensure_directories()?;
+
+    match command {
+        Command::Sync => run_sync_command(),
+        Command::Status => print_status(),
+        Command::ConfigShow => {
+            let config = load_config()?;
+            println!("server = {}", config.server.unwrap_or_default());
+            println!("email = {}", config.email.unwrap_or_default());
+            println!(
+                "password = {}",
+                if config.encrypted_password.is_some() {
+                    "(set)"
+                } else {
+                    "(not set)"
+                }
+            );
+            Ok(())
+        }
+        Command::ConfigSet { key, value } => {
+            // Reading from stdin keeps passwords out of shell history
+            let value = match value {
+                Some(value) => value,
+                None => read_stdin()?.trim_end_matches(['\r', '\n']).to_string(),
+            };
+            let mut msg = IncomingMessage {
+                message: "config".to_string(),
+                ..Default::default()
+            };
+            match key.as_str() {
+                "server" => msg.server = Some(value),
+                "email" => msg.email = Some(value),
+                _ => msg.password = Some(value),
+            }
+            handle_config_message(&msg)
+        }
+        Command::AddUrl {
+            url,
+            title,
+            archive,
+        } => {
+            let msg = IncomingMessage {
+                message: "save_url".to_string(),
+                url: Some(url),
+                title,
+                archive: archive.then_some(true),
+                ..Default::default()
+            };
+            let existing = handle_bookmark_status(&msg)?;
+            if !existing.is_empty() {
+                eprintln!("Already bookmarked in {}", existing.join(", "));
+            }
+            let filename = handle_save_url(&msg)?;
+            println!("{}", filename);
+            // In the foreground, since this process is about to exit
+            let archive = wants_archive(&msg, &load_config()?.archive.unwrap_or_default());
+            match capture_page(&filename, None, archive) {
+                Ok(Some(path)) => println!("{}", path.display()),
+                Ok(None) => {}
+                Err(e) => eprintln!("Could not capture the page: {}", e),
+            }
+            Ok(())
+        }
+        Command::AddNote { text } => {
+            let text = match text {
+                Some(text) => text,
+                None => read_stdin()?,
+            };
+            if text.trim().is_empty() {
+                return Err(KayaError::Config("Missing text content".to_string()));
+            }
+            println!("{}", save_new_anga("note", "md", text.as_bytes())?);
+            Ok(())
+        }
+        Command::AddFile { path } => {
+            let content = fs::read(&path)?;
+            let stem = path
+                .file_stem()
+                .map(|s| s.to_string_lossy().into_owned())
+                .unwrap_or_default();
+            let ext = path
+                .extension()
+                .map(|e| e.to_string_lossy().to_lowercase())
+                .unwrap_or_default();
+            let filename = save_new_anga(&slugify(&stem, "file"), &ext, &content)?;
+            println!("{}", filename);
+            if let Err(e) = ensure_thumbnail(&get_anga_dir(), &get_cache_dir(), &filename) {
+                eprintln!("Could not make a thumbnail: {}", e);
+            }
+            if let Err(e) = ensure_pdf_text(&get_anga_dir(), &get_cache_dir(), &filename) {
+                eprintln!("Could not extract the PDF's text: {}", e);
+            }
+            Ok(())
+        }
+        Command::ImportBookmarks { path, dry_run } => {
+            let html = String::from_utf8_lossy(&fs::read(&path)?).into_owned();
+            let summary = import_new_bookmarks(&parse_netscape_bookmarks(&html), dry_run)?;
+            print_import_summary(&summary, dry_run);
+            Ok(())
+        }
+        Command::ImportPlaces {
+            path,
+            history_min_visits,
+            dry_run,
+        } => {
+            let bookmarks = read_places(&path, history_min_visits)?;
+            let summary = import_new_bookmarks(&bookmarks, dry_run)?;
+            print_import_summary(&summary, dry_run);
+            Ok(())
+        }
+        Command::List {
+            kind,
+            since,
+            until,
+            limit,
+        } => {
+            let page = handle_list_anga(&IncomingMessage {
+                message: "list_anga".to_string(),
+                kind,
+                since,
+                until,
+                limit,
+                ..Default::default()
+            })?;
+            for item in page.items {
+                println!("{}", item.filename);
+            }
+            Ok(())
+        }
+        Command::Search { query, tags, limit } => {
+            let hits = handle_search(&IncomingMessage {
+                message: "search".to_string(),
+                query: Some(query),
+                tags: Some(tags),
+                limit,
+                ..Default::default()
+            })?;
+            for hit in hits {
+                println!("{}", hit.filename);
+            }
+            Ok(())
+        }
+        Command::FindInPdfs { phrase, limit } => {
+            let matches = handle_find_in_pdfs(&IncomingMessage {
+                message: "find_in_pdfs".to_string(),
+                query: Some(phrase),
+                limit,
+                ..Default::default()
+            })?;
+            for m in matches {
+                let pages: Vec<String> = m.pages.iter().map(u32::to_string).collect();
+                println!("{}\t{}", m.filename, pages.join(","));
+            }
+            Ok(())
+        }
+        Command::DeadLinks { check } => {
+            if check {
+                let opts = load_config()?.links.unwrap_or_default();
+                eprintln!("Checked {} link(s)", check_due_links(&opts)?);
+            }
+            let dead = handle_dead_links(&IncomingMessage {
+                message: "dead_links".to_string(),
+                ..Default::default()
+            })?;
+            for link in dead {
+                let status = link.status;
+                let answer = match (status.status, status.error) {
+                    (Some(code), _) => code.to_string(),
+                    (None, error) => error.unwrap_or_default(),
                 };
+                let alive = status
+                    .alive_at
+                    .map(|t| format!("last alive {}", t.format("%Y-%m-%d")))
+                    .unwrap_or_else(|| "never seen alive".to_string());
+                println!("{}\t{}\t{}\t{}", link.filename, status.url, answer, alive);
+            }
+            Ok(())
+        }
+        Command::Verify => run_verify(),
+        Command::InstallHost {
+            check,
+            chromium_origins,
+        } => install_host(check, &chromium_origins),
+        Command::UninstallHost => uninstall_host(),
+        Command::NativeHost | Command::Daemon | Command::Help => {
+            unreachable!("handled in main")
+        }
+    }
+}
+
+/// Runs as a browser's native messaging host, or with `daemon_mode` as the
+/// standalone daemon, until the browser disconnects or a signal arrives.
+/// Anga saved for `caller` are tagged with its browser.
+fn run_host(daemon_mode: bool, caller: Option<Caller>) -> ! {
+    setup_logging(log::LevelFilter::Info);
+    let browser = caller.as_ref().map(|c| c.browser.clone());
+    if let Some(caller) = &caller {
+        log::info!(
+            "Launched by {} for {} ({:?} family)",
+            caller.browser,
+            caller.extension,
+            caller.family
+        );
+    }
+
+    if let Err(e) = ensure_directories() {
+        log::error!("Failed to create directories: {}", e);
+        std::process::exit(1);
+    }
+
+    // A daemon is already running: leave the files to it and relay messages
+    if !daemon_mode {
+        if let Ok(daemon) = ipc::connect(&get_kaya_dir()) {
+            run_proxy(daemon, browser.as_deref());
+        }
+    }
 
-                if let Err(e) = write_native_message(&response) {
+    let cache_dirs = fs::read_dir(get_cache_dir())
+        .map(|entries| {
+            entries
+                .filter_map(|e| e.ok())
+                .map(|e| e.path())
+                .filter(|p| p.is_dir())
+                .collect::<Vec<_>>()
+        })
+        .unwrap_or_default();
+    for dir in [get_anga_dir(), get_meta_dir()]
+        .into_iter()
+        .chain(cache_dirs)
+    {
+        match remove_partial_files(&dir) {
+            Ok(0) => {}
+            Ok(n) => log::info!("Removed {} unfinished write(s) from {:?}", n, dir),
+            Err(e) => log::warn!("Failed to clean up {:?}: {}", dir, e),
+        }
+    }
+
+    if daemon_mode {
+        log::info!("Kaya sync daemon started in standalone mode");
+    } else {
+        log::info!("Kaya sync daemon started");
+    }
+
+    if let Err(e) = ctrlc::set_handler(|| shutdown("received termination signal")) {
+        log::warn!("Failed to install signal handler: {}", e);
+    }
+
+    let _watcher = match spawn_watcher() {
+        Ok(watcher) => Some(watcher),
+        Err(e) => {
+            log::warn!("File watching unavailable, relying on polling: {}", e);
+            None
+        }
+    };
+
+    spawn_worker("sync", run_sync_loop);
+    spawn_worker("links", run_link_loop);
+    spawn_worker("readable text", extract_missing_readables);
+    spawn_worker("thumbnails", generate_missing_thumbnails);
+    spawn_worker("pdf text", extract_missing_pdf_texts);
+
+    if daemon_mode {
+        match spawn_ipc_listener() {
+            Ok(()) => IPC_LISTENING.store(true, Ordering::SeqCst),
+            Err(e) => log::warn!("Not listening for local clients: {}", e),
+        }
+
+        // No browser to talk to: run until a termination signal arrives
+        loop {
+            thread::park();
+        }
+    }
+
+    loop {
+        match read_message(&mut io::stdin().lock()) {
+            Ok(Some(mut msg)) => {
+                if msg.browser.is_none() {
+                    msg.browser = browser.clone();
+                }
+                let response = handle_message(&msg);
+                if let Err(e) = write_message(&mut io::stdout().lock(), &response) {
                     log::error!("Failed to write response: {}", e);
                 }
             }
-            Ok(None) => {
-                running.store(false, Ordering::Relaxed);
-                log::info!("Kaya sync daemon shutting down");
-                break;
-            }
+            Ok(None) => shutdown("browser closed the connection"),
             Err(e) => {
                 log::error!("Error reading message: {}", e);
-                let response = OutgoingMessage {
-                    id: None,
-                    success: false,
-                    error: Some(e.to_string()),
-                    urls: None,
-                    message_type: None,
-                    has_password: None,
-                };
-                let _ = write_native_message(&response);
+                let _ = write_message(&mut io::stdout().lock(), &error_response(None, e));
+            }
+        }
+    }
+}
+
+fn main() {
+    let args: Vec<String> = std::env::args().skip(1).collect();
+    let command = match parse_args(&args) {
+        Ok(command) => command,
+        Err(e) => {
+            eprintln!("{}\n\n{}", e, USAGE);
+            std::process::exit(2);
+        }
+    };
+
+    match command {
+        Command::NativeHost => run_host(
+            false,
+            detect_caller(&args, parent_process_name().as_deref()),
+        ),
+        Command::Daemon => run_host(true, None),
+        Command::Help => print!("{}", USAGE),
+        command => {
+            // Created up front so the log file can be opened; keep
+            // informational logging out of the command's own output
+            let _ = ensure_directories();
+            setup_logging(log::LevelFilter::Warn);
+            if let Err(e) = run_command(command) {
+                eprintln!("Error: {}", e);
+                std::process::exit(1);
             }
         }
     }
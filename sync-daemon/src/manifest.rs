@@ -0,0 +1,265 @@
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+
+/// The name the extension passes to `connectNative`.
+pub const NATIVE_HOST_NAME: &str = "org.savebutton.nativehost";
+
+pub const FIREFOX_EXTENSION_ID: &str = "org.savebutton@savebutton.org";
+
+const DESCRIPTION: &str =
+    "Save Button Sync Daemon - Native messaging host for the Save Button Firefox extension";
+
+/// A native messaging host manifest, as read by the browser. Firefox-family
+/// browsers allow callers by extension id, Chromium-family ones by origin.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct HostManifest {
+    pub name: String,
+    pub description: String,
+    pub path: PathBuf,
+    #[serde(rename = "type")]
+    pub kind: String,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub allowed_extensions: Vec<String>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub allowed_origins: Vec<String>,
+}
+
+impl HostManifest {
+    /// The Firefox manifest for the host binary at `path`.
+    pub fn firefox(path: &Path) -> Self {
+        HostManifest {
+            name: NATIVE_HOST_NAME.to_string(),
+            description: DESCRIPTION.to_string(),
+            path: path.to_path_buf(),
+            kind: "stdio".to_string(),
+            allowed_extensions: vec![FIREFOX_EXTENSION_ID.to_string()],
+            allowed_origins: Vec::new(),
+        }
+    }
+
+    /// The Chromium manifest for the host binary at `path`, allowing the
+    /// given `chrome-extension://{id}/` origins.
+    pub fn chromium(path: &Path, origins: &[String]) -> Self {
+        HostManifest {
+            description: DESCRIPTION.replace("Firefox", "browser"),
+            allowed_extensions: Vec::new(),
+            allowed_origins: origins.to_vec(),
+            ..HostManifest::firefox(path)
+        }
+    }
+
+    pub fn to_json(&self) -> String {
+        serde_json::to_string_pretty(self).expect("manifest serializes") + "\n"
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum BrowserFamily {
+    Firefox,
+    Chromium,
+}
+
+/// One place a browser looks for native messaging hosts.
+#[derive(Debug, Clone, PartialEq)]
+pub struct BrowserTarget {
+    pub name: &'static str,
+    pub family: BrowserFamily,
+    /// Where our manifest goes.
+    pub manifest: PathBuf,
+    /// Only installed for when this exists, meaning the browser has been
+    /// run. `None` for targets that are always installed for.
+    pub detect: Option<PathBuf>,
+    /// On Windows, the `HKEY_CURRENT_USER` key pointing at the manifest.
+    pub registry_key: Option<String>,
+    /// Why nothing is installed for this browser even when it's present.
+    pub unsupported: Option<&'static str>,
+}
+
+impl BrowserTarget {
+    pub fn is_present(&self) -> bool {
+        self.detect.as_ref().is_none_or(|dir| dir.exists())
+    }
+}
+
+fn manifest_file(dir: PathBuf) -> PathBuf {
+    dir.join(format!("{}.json", NATIVE_HOST_NAME))
+}
+
+fn target(
+    name: &'static str,
+    family: BrowserFamily,
+    manifest_dir: PathBuf,
+    detect: Option<PathBuf>,
+) -> BrowserTarget {
+    BrowserTarget {
+        name,
+        family,
+        manifest: manifest_file(manifest_dir),
+        detect,
+        registry_key: None,
+        unsupported: None,
+    }
+}
+
+/// Every browser the host can register with for the user whose home is
+/// `home`, on the platform this was built for. Firefox ESR reads the same
+/// manifests as Firefox.
+pub fn browser_targets(home: &Path) -> Vec<BrowserTarget> {
+    use BrowserFamily::{Chromium, Firefox};
+
+    if cfg!(windows) {
+        // Manifests can live anywhere; the registry says where
+        let dir = home.join(r"AppData\Roaming\Save Button");
+        let registry = |name, family, subdir: &str, vendor: &str| BrowserTarget {
+            name,
+            family,
+            manifest: manifest_file(dir.join(subdir)),
+            detect: None,
+            registry_key: Some(format!(
+                r"Software\{}\NativeMessagingHosts\{}",
+                vendor, NATIVE_HOST_NAME
+            )),
+            unsupported: None,
+        };
+        return vec![
+            registry("Firefox", Firefox, "firefox", "Mozilla"),
+            registry("LibreWolf", Firefox, "librewolf", "LibreWolf"),
+            // Brave and Vivaldi read Chrome's key
+            registry("Chrome", Chromium, "chrome", r"Google\Chrome"),
+            registry("Chromium", Chromium, "chromium", "Chromium"),
+            registry("Edge", Chromium, "edge", r"Microsoft\Edge"),
+        ];
+    }
+
+    if cfg!(target_os = "macos") {
+        let support = home.join("Library/Application Support");
+        let app = |name, family, dir: &str| {
+            target(
+                name,
+                family,
+                support.join(dir).join("NativeMessagingHosts"),
+                Some(support.join(dir)),
+            )
+        };
+        return vec![
+            target(
+                "Firefox",
+                Firefox,
+                support.join("Mozilla/NativeMessagingHosts"),
+                None,
+            ),
+            app("LibreWolf", Firefox, "LibreWolf"),
+            app("Chrome", Chromium, "Google/Chrome"),
+            app("Chromium", Chromium, "Chromium"),
+            app("Brave", Chromium, "BraveSoftware/Brave-Browser"),
+            app("Edge", Chromium, "Microsoft Edge"),
+            app("Vivaldi", Chromium, "Vivaldi"),
+        ];
+    }
+
+    let config = home.join(".config");
+    let chromium = |name, dir: &str| {
+        target(
+            name,
+            Chromium,
+            config.join(dir).join("NativeMessagingHosts"),
+            Some(config.join(dir)),
+        )
+    };
+    // Sandboxed builds can't start a binary outside their sandbox, so a
+    // manifest in their data directory would name a path they can't run.
+    // They're listed so `install-host` can say why they're skipped, and so
+    // `uninstall-host` removes manifests older versions put there.
+    let sandboxed = |name, app_dir: PathBuf, profile_dir: &str| BrowserTarget {
+        unsupported: Some(
+            "the sandbox can't run the host binary; \
+             builds using the WebExtensions portal read the Firefox registration",
+        ),
+        ..target(
+            name,
+            Firefox,
+            app_dir.join(profile_dir).join("native-messaging-hosts"),
+            Some(app_dir),
+        )
+    };
+    vec![
+        target(
+            "Firefox",
+            Firefox,
+            home.join(".mozilla/native-messaging-hosts"),
+            None,
+        ),
+        target(
+            "LibreWolf",
+            Firefox,
+            home.join(".librewolf/native-messaging-hosts"),
+            Some(home.join(".librewolf")),
+        ),
+        sandboxed(
+            "Firefox (Flatpak)",
+            home.join(".var/app/org.mozilla.firefox"),
+            ".mozilla",
+        ),
+        sandboxed(
+            "LibreWolf (Flatpak)",
+            home.join(".var/app/io.gitlab.librewolf-community"),
+            ".librewolf",
+        ),
+        sandboxed(
+            "Firefox (Snap)",
+            home.join("snap/firefox/common"),
+            ".mozilla",
+        ),
+        chromium("Chrome", "google-chrome"),
+        chromium("Chromium", "chromium"),
+        chromium("Brave", "BraveSoftware/Brave-Browser"),
+        chromium("Edge", "microsoft-edge"),
+        chromium("Vivaldi", "vivaldi"),
+    ]
+}
+
+/// How an existing manifest compares to the one we'd write.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Registration {
+    Missing,
+    Current,
+    /// Ours, but out of date: usually a binary that has since moved.
+    Stale(HostManifest),
+    /// Something we didn't write, which is left alone.
+    Foreign(String),
+}
+
+/// Reads the manifest at `path` if it's one of ours.
+pub fn read_manifest(path: &Path) -> Option<HostManifest> {
+    let text = fs::read_to_string(path).ok()?;
+    serde_json::from_str::<HostManifest>(&text)
+        .ok()
+        .filter(|m| m.name == NATIVE_HOST_NAME)
+}
+
+/// Reads the manifest at `path` and compares it with `expected`.
+pub fn check_registration(path: &Path, expected: &HostManifest) -> Registration {
+    let text = match fs::read_to_string(path) {
+        Ok(text) => text,
+        Err(e) if e.kind() == io::ErrorKind::NotFound => return Registration::Missing,
+        Err(e) => return Registration::Foreign(e.to_string()),
+    };
+    match serde_json::from_str::<HostManifest>(&text) {
+        Ok(found) if found == *expected => Registration::Current,
+        Ok(found) if found.name == NATIVE_HOST_NAME => Registration::Stale(found),
+        Ok(found) => Registration::Foreign(format!("names another host: {}", found.name)),
+        Err(e) => Registration::Foreign(format!("not a host manifest: {}", e)),
+    }
+}
+
+/// Removes our manifest at `path`, leaving anything else in place. Returns
+/// whether a file was removed.
+pub fn remove_registration(path: &Path) -> io::Result<bool> {
+    if read_manifest(path).is_none() {
+        return Ok(false);
+    }
+    fs::remove_file(path)?;
+    Ok(true)
+}
@@ -0,0 +1,269 @@
+use crate::anga::{is_plain_filename, parse_filename_timestamp};
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashSet};
+use std::fs;
+use std::io;
+use std::path::Path;
+use thiserror::Error;
+
+#[derive(Error, Debug, PartialEq, Eq)]
+pub enum MetaError {
+    #[error("invalid TOML: {0}")]
+    Parse(String),
+    #[error("{0}")]
+    Invalid(String),
+}
+
+/// An ADR 0003 metadata file:
+///
+/// ```toml
+/// [anga]
+/// filename = "2026-01-28T205208-bookmark.url"
+///
+/// [meta]
+/// tags = ["podcast", "democracy", "cooperatives"]
+/// note = '''A note.'''
+/// ```
+///
+/// The format is expected to grow, so keys and tables this type doesn't know
+/// about are kept and written back out.
+#[derive(Debug, Clone, PartialEq, Default, Serialize, Deserialize)]
+pub struct Meta {
+    pub anga: AngaRef,
+    #[serde(default)]
+    pub meta: MetaFields,
+    #[serde(flatten)]
+    pub extra: toml::Table,
+}
+
+#[derive(Debug, Clone, PartialEq, Default, Serialize, Deserialize)]
+pub struct AngaRef {
+    pub filename: String,
+    #[serde(flatten)]
+    pub extra: toml::Table,
+}
+
+#[derive(Debug, Clone, PartialEq, Default, Serialize, Deserialize)]
+pub struct MetaFields {
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub tags: Vec<String>,
+    /// Tags to take away from those added by earlier meta files.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub remove_tags: Vec<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub note: Option<String>,
+    /// A tombstone: `true` hides the anga everywhere, `false` brings it back.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub hidden: Option<bool>,
+    /// The browser the anga was saved from, such as `firefox` or `chrome`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub browser: Option<String>,
+    /// A word typed in the address bar to open the bookmark, as Firefox
+    /// keywords are.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub keyword: Option<String>,
+    #[serde(flatten)]
+    pub extra: toml::Table,
+}
+
+impl Meta {
+    pub fn new(anga_filename: &str) -> Self {
+        Meta {
+            anga: AngaRef {
+                filename: anga_filename.to_string(),
+                ..Default::default()
+            },
+            ..Default::default()
+        }
+    }
+
+    /// Parses and validates the text of a meta file.
+    pub fn parse(text: &str) -> Result<Self, MetaError> {
+        let meta: Meta = toml::from_str(text).map_err(|e| MetaError::Parse(e.message().into()))?;
+        meta.validate()?;
+        Ok(meta)
+    }
+
+    pub fn validate(&self) -> Result<(), MetaError> {
+        if !is_plain_filename(&self.anga.filename) {
+            return Err(MetaError::Invalid(format!(
+                "invalid anga filename: {:?}",
+                self.anga.filename
+            )));
+        }
+        for tag in self.meta.tags.iter().chain(&self.meta.remove_tags) {
+            if tag.trim().is_empty() || tag.contains(['\n', '\r']) {
+                return Err(MetaError::Invalid(format!("invalid tag: {:?}", tag)));
+            }
+        }
+        Ok(())
+    }
+
+    pub fn to_toml(&self) -> Result<String, MetaError> {
+        toml::to_string(self).map_err(|e| MetaError::Parse(e.to_string()))
+    }
+}
+
+/// Checks that a meta filename is a plain `.toml` name.
+pub fn validate_meta_filename(filename: &str) -> Result<(), MetaError> {
+    if is_plain_filename(filename) && filename.ends_with(".toml") {
+        Ok(())
+    } else {
+        Err(MetaError::Invalid(format!(
+            "invalid meta filename: {:?}",
+            filename
+        )))
+    }
+}
+
+/// Reads every `.toml` file in `meta_dir`, oldest first. Files that fail to
+/// parse are returned with their error rather than dropped, so callers can
+/// report them.
+pub fn read_meta_dir(meta_dir: &Path) -> io::Result<Vec<(String, Result<Meta, MetaError>)>> {
+    if !meta_dir.exists() {
+        return Ok(Vec::new());
+    }
+
+    let mut filenames: Vec<String> = fs::read_dir(meta_dir)?
+        .filter_map(|e| e.ok())
+        .filter_map(|e| e.file_name().into_string().ok())
+        .filter(|n| !n.starts_with('.') && n.ends_with(".toml"))
+        .collect();
+    filenames.sort();
+
+    Ok(filenames
+        .into_iter()
+        .map(|filename| {
+            let meta = fs::read_to_string(meta_dir.join(&filename))
+                .map_err(|e| MetaError::Parse(e.to_string()))
+                .and_then(|text| Meta::parse(&text));
+            (filename, meta)
+        })
+        .collect())
+}
+
+/// The current tags and note of one anga, folded from all of its meta files.
+#[derive(Debug, Clone, PartialEq, Default, Serialize, Deserialize)]
+pub struct MetaView {
+    pub anga: String,
+    pub tags: Vec<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub note: Option<String>,
+    pub hidden: bool,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub browser: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub keyword: Option<String>,
+    /// The meta files that were folded, oldest first.
+    pub sources: Vec<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub updated: Option<DateTime<Utc>>,
+}
+
+/// What tags are compared by: trimmed and lowercased, so `Ärger` and
+/// `ärger` are the same tag.
+pub fn tag_key(tag: &str) -> String {
+    tag.trim().to_lowercase()
+}
+
+/// Folds meta files for one anga, in the order given, into its current view:
+///
+/// - `tags` adds tags and `remove_tags` takes them away. Tags compare
+///   case-insensitively and keep the spelling they were first added with.
+/// - The latest `note` replaces earlier ones; `note = ""` clears it.
+/// - The latest `hidden` decides whether the anga is hidden.
+/// - The latest `browser` is the one the anga was saved from.
+/// - The latest `keyword` replaces earlier ones; `keyword = ""` clears it.
+pub fn fold_meta<'a>(anga: &str, metas: impl IntoIterator<Item = (&'a str, &'a Meta)>) -> MetaView {
+    let mut view = MetaView {
+        anga: anga.to_string(),
+        ..Default::default()
+    };
+
+    for (filename, meta) in metas {
+        for tag in &meta.meta.tags {
+            let tag = tag.trim();
+            if !view.tags.iter().any(|t| tag_key(t) == tag_key(tag)) {
+                view.tags.push(tag.to_string());
+            }
+        }
+        for tag in &meta.meta.remove_tags {
+            let removed = tag_key(tag);
+            view.tags.retain(|t| tag_key(t) != removed);
+        }
+        if let Some(note) = &meta.meta.note {
+            view.note = Some(note.clone()).filter(|n| !n.is_empty());
+        }
+        if let Some(hidden) = meta.meta.hidden {
+            view.hidden = hidden;
+        }
+        if let Some(browser) = &meta.meta.browser {
+            view.browser = Some(browser.clone());
+        }
+        if let Some(keyword) = &meta.meta.keyword {
+            view.keyword = Some(keyword.clone()).filter(|k| !k.is_empty());
+        }
+        view.sources.push(filename.to_string());
+        view.updated = parse_filename_timestamp(filename).or(view.updated);
+    }
+
+    view
+}
+
+/// Orders meta files by the timestamp in their filenames, falling back to the
+/// filename itself.
+pub fn cmp_meta_filenames(a: &str, b: &str) -> std::cmp::Ordering {
+    parse_filename_timestamp(a)
+        .cmp(&parse_filename_timestamp(b))
+        .then_with(|| a.cmp(b))
+}
+
+/// Groups valid meta files by the anga they refer to, each group in fold
+/// order. Invalid meta files are skipped.
+pub fn group_meta_by_anga(meta_dir: &Path) -> io::Result<BTreeMap<String, Vec<(String, Meta)>>> {
+    let mut grouped: BTreeMap<String, Vec<(String, Meta)>> = BTreeMap::new();
+    for (filename, meta) in read_meta_dir(meta_dir)? {
+        if let Ok(meta) = meta {
+            grouped
+                .entry(meta.anga.filename.clone())
+                .or_default()
+                .push((filename, meta));
+        }
+    }
+    for metas in grouped.values_mut() {
+        metas.sort_by(|a, b| cmp_meta_filenames(&a.0, &b.0));
+    }
+    Ok(grouped)
+}
+
+/// Resolves every anga that has meta.
+pub fn resolve_all_meta(meta_dir: &Path) -> io::Result<BTreeMap<String, MetaView>> {
+    Ok(group_meta_by_anga(meta_dir)?
+        .into_iter()
+        .map(|(anga, metas)| {
+            let view = fold_meta(&anga, metas.iter().map(|(f, m)| (f.as_str(), m)));
+            (anga, view)
+        })
+        .collect())
+}
+
+/// The anga currently hidden by tombstones.
+pub fn hidden_anga(meta_dir: &Path) -> io::Result<HashSet<String>> {
+    Ok(resolve_all_meta(meta_dir)?
+        .into_values()
+        .filter(|view| view.hidden)
+        .map(|view| view.anga)
+        .collect())
+}
+
+/// Resolves one anga. An anga without meta gets an empty view.
+pub fn resolve_meta(meta_dir: &Path, anga: &str) -> io::Result<MetaView> {
+    let mut metas: Vec<(String, Meta)> = read_meta_dir(meta_dir)?
+        .into_iter()
+        .filter_map(|(filename, meta)| meta.ok().map(|m| (filename, m)))
+        .filter(|(_, meta)| meta.anga.filename == anga)
+        .collect();
+    metas.sort_by(|a, b| cmp_meta_filenames(&a.0, &b.0));
+    Ok(fold_meta(anga, metas.iter().map(|(f, m)| (f.as_str(), m))))
+}
@@ -0,0 +1,135 @@
+use chrono::{DateTime, Duration, Utc};
+use std::fs::{self, File, OpenOptions};
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
+
+/// Longest slug produced by `slugify`, so titles don't produce unwieldy names.
+const MAX_SLUG_LEN: usize = 60;
+
+/// The `YYYY-mm-ddTHHMMSS` prefix from ADR 0001, in UTC.
+pub fn timestamp_prefix(at: DateTime<Utc>) -> String {
+    at.format("%Y-%m-%dT%H%M%S").to_string()
+}
+
+/// The `YYYY-mm-ddTHHMMSS_SSSSSSSSS` prefix ADR 0001 uses when two files
+/// would otherwise share a name.
+pub fn nanosecond_prefix(at: DateTime<Utc>) -> String {
+    at.format("%Y-%m-%dT%H%M%S_%9f").to_string()
+}
+
+/// Turns a title into the URL-safe part of a filename: lowercase ASCII
+/// letters and digits separated by single dashes. Returns `fallback` when
+/// nothing usable is left.
+pub fn slugify(title: &str, fallback: &str) -> String {
+    let mut slug = String::new();
+    for c in title.chars() {
+        if c.is_ascii_alphanumeric() {
+            slug.push(c.to_ascii_lowercase());
+        } else if !slug.is_empty() && !slug.ends_with('-') {
+            slug.push('-');
+        }
+    }
+
+    if slug.len() > MAX_SLUG_LEN {
+        // Prefer cutting at a word boundary
+        let cut = slug[..MAX_SLUG_LEN].rfind('-').unwrap_or(MAX_SLUG_LEN);
+        slug.truncate(cut);
+    }
+
+    let slug = slug.trim_matches('-');
+    if slug.is_empty() {
+        fallback.to_string()
+    } else {
+        slug.to_string()
+    }
+}
+
+/// The slug for a bookmark: its title, or its host when it has no usable
+/// title.
+pub fn bookmark_slug(url: &str, title: Option<&str>) -> String {
+    let host_slug = url::Url::parse(url)
+        .ok()
+        .and_then(|u| u.host_str().map(|h| slugify(h, "bookmark")))
+        .unwrap_or_else(|| "bookmark".to_string());
+    slugify(title.unwrap_or(""), &host_slug)
+}
+
+/// Creates `{timestamp}-{slug}.{ext}` in `dir` and writes `content` to it,
+/// returning the filename. If that name is taken, the nanosecond form is used
+/// instead, moving forward a nanosecond at a time until a free name is found.
+/// Files are created with `create_new`, so two writers can never claim the
+/// same name.
+pub fn create_timestamped_file(
+    dir: &Path,
+    at: DateTime<Utc>,
+    slug: &str,
+    ext: &str,
+    content: &[u8],
+) -> io::Result<String> {
+    let suffix = if ext.is_empty() {
+        format!("-{}", slug)
+    } else {
+        format!("-{}.{}", slug, ext)
+    };
+
+    let mut filename = format!("{}{}", timestamp_prefix(at), suffix);
+    let mut at = at;
+    loop {
+        match OpenOptions::new()
+            .write(true)
+            .create_new(true)
+            .open(dir.join(&filename))
+        {
+            Ok(mut file) => {
+                file.write_all(content)?;
+                return Ok(filename);
+            }
+            Err(e) if e.kind() == io::ErrorKind::AlreadyExists => {
+                filename = format!("{}{}", nanosecond_prefix(at), suffix);
+                at += Duration::nanoseconds(1);
+            }
+            Err(e) => return Err(e),
+        }
+    }
+}
+
+/// Where `write_atomically` stages a file before moving it into place. The
+/// leading dot keeps it out of listings, sync and the watcher.
+pub fn partial_path(path: &Path) -> Option<PathBuf> {
+    let name = path.file_name()?.to_str()?;
+    Some(path.with_file_name(format!(".{}.part", name)))
+}
+
+/// Writes `content` to a hidden partial file and renames it over `path`, so
+/// an interrupted write never leaves a truncated file under the real name.
+pub fn write_atomically(path: &Path, content: &[u8]) -> io::Result<()> {
+    let partial = partial_path(path).ok_or_else(|| {
+        io::Error::new(
+            io::ErrorKind::InvalidInput,
+            format!("invalid path: {:?}", path),
+        )
+    })?;
+    let mut file = File::create(&partial)?;
+    file.write_all(content)?;
+    file.sync_all()?;
+    fs::rename(&partial, path)
+}
+
+/// Deletes partial files left behind by writes that never finished, and
+/// returns how many were removed.
+pub fn remove_partial_files(dir: &Path) -> io::Result<usize> {
+    if !dir.exists() {
+        return Ok(0);
+    }
+
+    let mut removed = 0;
+    for entry in fs::read_dir(dir)?.filter_map(|e| e.ok()) {
+        let name = entry.file_name();
+        let name = name.to_string_lossy();
+        if name.starts_with('.') && name.ends_with(".part") && entry.path().is_file() {
+            fs::remove_file(entry.path())?;
+            removed += 1;
+        }
+    }
+    Ok(removed)
+}
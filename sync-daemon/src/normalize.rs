@@ -0,0 +1,127 @@
+use serde::{Deserialize, Serialize};
+use url::Url;
+
+/// Query parameters that only identify where a click came from. Any parameter
+/// starting with `utm_` is also treated as tracking.
+const TRACKING_PARAMS: &[&str] = &[
+    "fbclid",
+    "gclid",
+    "dclid",
+    "gbraid",
+    "wbraid",
+    "msclkid",
+    "mc_cid",
+    "mc_eid",
+    "igshid",
+    "yclid",
+    "twclid",
+    "_ga",
+    "_gl",
+    "_hsenc",
+    "_hsmi",
+    "mkt_tok",
+    "oly_anon_id",
+    "oly_enc_id",
+    "vero_id",
+];
+
+/// Controls which differences between two URLs are ignored when deciding
+/// whether they point at the same page. Stored in `~/.kaya/.config` under
+/// `[normalize]`; missing keys take their default (enabled) values.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct NormalizeOptions {
+    /// Treat `http://` and `https://` as the same page.
+    pub ignore_scheme: bool,
+    /// Treat `/a/` and `/a` as the same page.
+    pub strip_trailing_slash: bool,
+    /// Drop `utm_*`, `fbclid` and similar click-tracking parameters.
+    pub strip_tracking_params: bool,
+    /// Drop `#fragment`s.
+    pub strip_fragment: bool,
+    /// Additional query parameters to drop, matched case-insensitively.
+    pub extra_tracking_params: Vec<String>,
+}
+
+impl Default for NormalizeOptions {
+    fn default() -> Self {
+        NormalizeOptions {
+            ignore_scheme: true,
+            strip_trailing_slash: true,
+            strip_tracking_params: true,
+            strip_fragment: true,
+            extra_tracking_params: Vec::new(),
+        }
+    }
+}
+
+impl NormalizeOptions {
+    fn is_tracking_param(&self, name: &str) -> bool {
+        let name = name.to_lowercase();
+        name.starts_with("utm_")
+            || TRACKING_PARAMS.contains(&name.as_str())
+            || self
+                .extra_tracking_params
+                .iter()
+                .any(|p| p.eq_ignore_ascii_case(&name))
+    }
+}
+
+/// Reduces a URL to the form used for bookmark matching. Hostnames are
+/// lowercased and converted to punycode, default ports are removed, and the
+/// rest depends on `opts`. Strings that do not parse as URLs are only trimmed.
+pub fn normalize_url(raw: &str, opts: &NormalizeOptions) -> String {
+    let raw = raw.trim();
+    let mut url = match Url::parse(raw) {
+        Ok(u) => u,
+        Err(_) => return raw.to_string(),
+    };
+
+    if opts.ignore_scheme && url.scheme() == "http" {
+        // http -> https cannot fail, and the default port is dropped by `url`
+        let _ = url.set_scheme("https");
+    }
+
+    if opts.strip_fragment {
+        url.set_fragment(None);
+    }
+
+    if opts.strip_tracking_params {
+        if let Some(query) = url.query() {
+            // Filter the raw pairs so the remaining parameters keep their
+            // original encoding.
+            let kept: Vec<&str> = query
+                .split('&')
+                .filter(|pair| !pair.is_empty())
+                .filter(|pair| {
+                    let name = pair.split('=').next().unwrap_or("");
+                    !opts.is_tracking_param(name)
+                })
+                .collect();
+            if kept.is_empty() {
+                url.set_query(None);
+            } else {
+                url.set_query(Some(&kept.join("&")));
+            }
+        }
+    }
+
+    if opts.strip_trailing_slash && !url.cannot_be_a_base() {
+        let path = url.path();
+        if path.len() > 1 && path.ends_with('/') {
+            let trimmed = path.trim_end_matches('/').to_string();
+            url.set_path(&trimmed);
+        }
+    }
+
+    let mut normalized = url.to_string();
+    // `url` always keeps the root path, so `https://x.com/` needs trimming here
+    if opts.strip_trailing_slash
+        && url.path() == "/"
+        && url.query().is_none()
+        && url.fragment().is_none()
+    {
+        normalized.pop();
+    }
+    normalized
+}
@@ -0,0 +1,175 @@
+use crate::html::collapse_whitespace;
+use crate::naming::write_atomically;
+use lopdf::Document;
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::io;
+use std::path::Path;
+
+/// A PDF's text, in its cache directory. Pages are separated by form feeds,
+/// as `pdftotext` does.
+pub const PDF_TEXT_FILENAME: &str = "pdf-text.txt";
+/// PDFs larger than this aren't read.
+pub const MAX_PDF_BYTES: u64 = 64 * 1024 * 1024;
+
+const PAGE_BREAK: char = '\u{c}';
+
+fn is_pdf(filename: &str) -> bool {
+    filename.to_lowercase().ends_with(".pdf")
+}
+
+/// The text of each page of a PDF. A page whose text can't be decoded,
+/// such as a scan, comes back empty rather than failing the document.
+pub fn extract_pdf_pages(content: &[u8]) -> io::Result<Vec<String>> {
+    let doc = Document::load_mem(content)
+        .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e.to_string()))?;
+    Ok(doc
+        .get_pages()
+        .keys()
+        .map(|&page| {
+            let text = doc.extract_text(&[page]).unwrap_or_default();
+            text.lines()
+                .map(str::trim_end)
+                .filter(|l| !l.is_empty())
+                .collect::<Vec<_>>()
+                .join("\n")
+                // A stray form feed would shift every later page number
+                .replace(PAGE_BREAK, " ")
+        })
+        .collect())
+}
+
+/// Writes a PDF's pages to `dir`, creating the directory if needed.
+pub fn write_pdf_text(dir: &Path, pages: &[String]) -> io::Result<()> {
+    fs::create_dir_all(dir)?;
+    let text = pages.join(&PAGE_BREAK.to_string());
+    write_atomically(&dir.join(PDF_TEXT_FILENAME), text.as_bytes())
+}
+
+/// Reads the pages stored in `dir`, if there are any.
+pub fn read_pdf_text(dir: &Path) -> io::Result<Option<Vec<String>>> {
+    match fs::read_to_string(dir.join(PDF_TEXT_FILENAME)) {
+        Ok(text) => Ok(Some(text.split(PAGE_BREAK).map(String::from).collect())),
+        Err(e) if e.kind() == io::ErrorKind::NotFound => Ok(None),
+        Err(e) => Err(e),
+    }
+}
+
+/// Whether the PDF `filename` in `anga_dir` has no text under `cache_dir`
+/// yet, or only text older than the PDF.
+fn is_stale(anga_dir: &Path, cache_dir: &Path, filename: &str) -> bool {
+    let text = cache_dir.join(filename).join(PDF_TEXT_FILENAME);
+    match (
+        fs::metadata(anga_dir.join(filename)).and_then(|m| m.modified()),
+        fs::metadata(text).and_then(|m| m.modified()),
+    ) {
+        (Ok(pdf), Ok(text)) => text < pdf,
+        (Ok(_), Err(_)) => true,
+        _ => false,
+    }
+}
+
+/// Extracts the text of the PDF `filename` in `anga_dir` unless the text
+/// under `cache_dir` is up to date. Returns the pages, or `None` when the
+/// anga isn't a PDF or is too large to read.
+pub fn ensure_pdf_text(
+    anga_dir: &Path,
+    cache_dir: &Path,
+    filename: &str,
+) -> io::Result<Option<Vec<String>>> {
+    if !is_pdf(filename) {
+        return Ok(None);
+    }
+    let dir = cache_dir.join(filename);
+    if !is_stale(anga_dir, cache_dir, filename) {
+        return read_pdf_text(&dir);
+    }
+    let path = anga_dir.join(filename);
+    if fs::metadata(&path)?.len() > MAX_PDF_BYTES {
+        return Ok(None);
+    }
+
+    let pages = extract_pdf_pages(&fs::read(&path)?)?;
+    write_pdf_text(&dir, &pages)?;
+    Ok(Some(pages))
+}
+
+/// Extracts the text of every PDF in `anga_dir` that doesn't have
+/// up-to-date text yet, and returns how many were written. A PDF that fails
+/// is skipped. Stops between PDFs once `stopped` says so.
+pub fn extract_missing_pdf_text(
+    anga_dir: &Path,
+    cache_dir: &Path,
+    stopped: impl Fn() -> bool,
+) -> io::Result<usize> {
+    if !anga_dir.exists() {
+        return Ok(0);
+    }
+
+    let mut written = 0;
+    for entry in fs::read_dir(anga_dir)?.filter_map(|e| e.ok()) {
+        if stopped() {
+            break;
+        }
+        let Some(filename) = entry.file_name().to_str().map(str::to_string) else {
+            continue;
+        };
+        if filename.starts_with('.') || !is_pdf(&filename) {
+            continue;
+        }
+        if is_stale(anga_dir, cache_dir, &filename)
+            && matches!(ensure_pdf_text(anga_dir, cache_dir, &filename), Ok(Some(_)))
+        {
+            written += 1;
+        }
+    }
+    Ok(written)
+}
+
+/// A PDF containing a phrase, and the pages it's on, counting from 1.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct PdfMatch {
+    pub filename: String,
+    pub pages: Vec<u32>,
+}
+
+fn normalize(text: &str) -> String {
+    collapse_whitespace(text).to_lowercase()
+}
+
+/// The PDFs in `anga_dir` whose extracted text contains `phrase`, newest
+/// first. Matching ignores case and differences in whitespace, including
+/// line breaks, but not page breaks. PDFs whose text hasn't been extracted
+/// yet aren't searched.
+pub fn find_in_pdfs(anga_dir: &Path, cache_dir: &Path, phrase: &str) -> io::Result<Vec<PdfMatch>> {
+    let phrase = normalize(phrase);
+    if phrase.is_empty() || !cache_dir.exists() {
+        return Ok(Vec::new());
+    }
+
+    let mut matches = Vec::new();
+    for entry in fs::read_dir(cache_dir)?.filter_map(|e| e.ok()) {
+        let Some(filename) = entry.file_name().to_str().map(str::to_string) else {
+            continue;
+        };
+        // Cached text outlives its PDF until the cache is cleaned up
+        if !is_pdf(&filename) || !anga_dir.join(&filename).is_file() {
+            continue;
+        }
+        let Some(pages) = read_pdf_text(&entry.path())? else {
+            continue;
+        };
+        let pages: Vec<u32> = pages
+            .iter()
+            .zip(1..)
+            .filter(|(text, _)| normalize(text).contains(&phrase))
+            .map(|(_, number)| number)
+            .collect();
+        if !pages.is_empty() {
+            matches.push(PdfMatch { filename, pages });
+        }
+    }
+
+    matches.sort_by(|a, b| b.filename.cmp(&a.filename));
+    Ok(matches)
+}
@@ -0,0 +1,273 @@
+use crate::import::{push_tag, ImportedBookmark};
+use chrono::DateTime;
+use rusqlite::Connection;
+use std::collections::HashMap;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+/// The history and bookmarks database in a Firefox profile directory.
+pub const PLACES_FILENAME: &str = "places.sqlite";
+/// The tag given to pages imported from history rather than bookmarks.
+pub const HISTORY_TAG: &str = "history";
+
+/// The folders every profile has: the root, the menu, the toolbar, "Other
+/// Bookmarks" and mobile bookmarks. They don't become tags.
+const ROOT_GUIDS: &[&str] = &[
+    "root________",
+    "menu________",
+    "toolbar_____",
+    "unfiled_____",
+    "mobile______",
+];
+/// The folder holding one folder per tag, each listing the tagged places.
+const TAGS_GUID: &str = "tags________";
+/// Folders nested deeper than this are taken to be a loop.
+const MAX_DEPTH: usize = 64;
+
+const TYPE_BOOKMARK: i64 = 1;
+const TYPE_FOLDER: i64 = 2;
+
+/// Tells apart the copies made by concurrent reads in one process.
+static COPIES: AtomicUsize = AtomicUsize::new(0);
+
+fn invalid(e: impl ToString) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, e.to_string())
+}
+
+/// The database `path` names: `places.sqlite` inside it when it's a
+/// profile directory, or `path` itself.
+pub fn places_path(path: &Path) -> PathBuf {
+    if path.is_dir() {
+        path.join(PLACES_FILENAME)
+    } else {
+        path.to_path_buf()
+    }
+}
+
+/// Reads the bookmarks in a Firefox profile's `places.sqlite`, oldest
+/// first. With `history_min_visits`, they're followed by the pages in
+/// history visited at least that often, tagged `history`. `path` can be
+/// the database or the profile directory.
+///
+/// A running Firefox keeps the database locked and its latest changes in
+/// `places.sqlite-wal`, so both are copied to a temporary directory and the
+/// copy is read.
+pub fn read_places(
+    path: &Path,
+    history_min_visits: Option<u32>,
+) -> io::Result<Vec<ImportedBookmark>> {
+    let path = places_path(path);
+    if !path.is_file() {
+        return Err(io::Error::new(
+            io::ErrorKind::NotFound,
+            format!("no Firefox places database at {}", path.display()),
+        ));
+    }
+
+    let copy_dir = std::env::temp_dir().join(format!(
+        "savebutton-places-{}-{}",
+        std::process::id(),
+        COPIES.fetch_add(1, Ordering::Relaxed)
+    ));
+    fs::create_dir_all(&copy_dir)?;
+    let result = read_copy(&path, &copy_dir, history_min_visits);
+    if let Err(e) = fs::remove_dir_all(&copy_dir) {
+        log::warn!("Failed to remove {}: {}", copy_dir.display(), e);
+    }
+    result
+}
+
+fn read_copy(
+    path: &Path,
+    copy_dir: &Path,
+    history_min_visits: Option<u32>,
+) -> io::Result<Vec<ImportedBookmark>> {
+    let copy = copy_dir.join(PLACES_FILENAME);
+    fs::copy(path, &copy)?;
+    let mut wal = path.as_os_str().to_owned();
+    wal.push("-wal");
+    let wal = PathBuf::from(wal);
+    if wal.is_file() {
+        fs::copy(&wal, copy_dir.join(format!("{}-wal", PLACES_FILENAME)))?;
+    }
+
+    let conn = Connection::open(&copy).map_err(invalid)?;
+    let has_bookmarks: bool = conn
+        .query_row(
+            "SELECT EXISTS (SELECT 1 FROM sqlite_master WHERE type = 'table' AND name = 'moz_bookmarks')",
+            [],
+            |row| row.get(0),
+        )
+        .map_err(invalid)?;
+    if !has_bookmarks {
+        return Err(invalid(format!(
+            "{} isn't a Firefox places database",
+            path.display()
+        )));
+    }
+    read_bookmarks(&conn, history_min_visits).map_err(invalid)
+}
+
+struct Folder {
+    parent: i64,
+    title: String,
+    guid: String,
+}
+
+/// A bookmark as it's being gathered. The same URL can be filed in several
+/// folders and tagged separately, so tags are put together at the end.
+struct Gathered {
+    bookmark: ImportedBookmark,
+    folders: Vec<String>,
+}
+
+fn read_bookmarks(
+    conn: &Connection,
+    history_min_visits: Option<u32>,
+) -> rusqlite::Result<Vec<ImportedBookmark>> {
+    let mut folders: HashMap<i64, Folder> = HashMap::new();
+    let mut stmt = conn.prepare(
+        "SELECT id, parent, IFNULL(title, ''), IFNULL(guid, '') FROM moz_bookmarks WHERE type = ?1",
+    )?;
+    let rows = stmt.query_map([TYPE_FOLDER], |row| {
+        Ok((
+            row.get::<_, i64>(0)?,
+            Folder {
+                parent: row.get(1)?,
+                title: row.get(2)?,
+                guid: row.get(3)?,
+            },
+        ))
+    })?;
+    for row in rows {
+        let (id, folder) = row?;
+        folders.insert(id, folder);
+    }
+    let tags_root = folders
+        .iter()
+        .find(|(_, f)| f.guid == TAGS_GUID)
+        .map(|(id, _)| *id);
+
+    let mut gathered: Vec<Gathered> = Vec::new();
+    let mut by_url: HashMap<String, usize> = HashMap::new();
+    let mut tags_by_url: HashMap<String, Vec<String>> = HashMap::new();
+
+    let mut stmt = conn.prepare(
+        "SELECT b.parent, IFNULL(b.title, ''), IFNULL(b.dateAdded, 0), p.url, IFNULL(p.title, ''), k.keyword
+         FROM moz_bookmarks b
+         JOIN moz_places p ON p.id = b.fk
+         LEFT JOIN moz_keywords k ON k.place_id = p.id
+         WHERE b.type = ?1
+         ORDER BY b.dateAdded, b.id",
+    )?;
+    let rows = stmt.query_map([TYPE_BOOKMARK], |row| {
+        Ok((
+            row.get::<_, i64>(0)?,
+            row.get::<_, String>(1)?,
+            row.get::<_, i64>(2)?,
+            row.get::<_, String>(3)?,
+            row.get::<_, String>(4)?,
+            row.get::<_, Option<String>>(5)?,
+        ))
+    })?;
+    for row in rows {
+        let (parent, title, added, url, page_title, keyword) = row?;
+
+        // An entry in a tag folder tags the place rather than bookmarking it
+        let tag_folder = folders.get(&parent).filter(|f| Some(f.parent) == tags_root);
+        if let Some(tag_folder) = tag_folder {
+            push_tag(tags_by_url.entry(url).or_default(), &tag_folder.title);
+            continue;
+        }
+
+        let mut path = Vec::new();
+        let mut id = parent;
+        for _ in 0..MAX_DEPTH {
+            let Some(folder) = folders.get(&id) else {
+                break;
+            };
+            if !ROOT_GUIDS.contains(&folder.guid.as_str()) {
+                path.push(folder.title.clone());
+            }
+            id = folder.parent;
+        }
+        path.reverse();
+
+        let keyword = keyword.filter(|k| !k.is_empty());
+        if let Some(&i) = by_url.get(&url) {
+            let earlier = &mut gathered[i];
+            for folder in path {
+                push_tag(&mut earlier.folders, &folder);
+            }
+            earlier.bookmark.keyword = earlier.bookmark.keyword.take().or(keyword);
+            continue;
+        }
+
+        let title = Some(title)
+            .filter(|t| !t.trim().is_empty())
+            .or(Some(page_title).filter(|t| !t.trim().is_empty()));
+        by_url.insert(url.clone(), gathered.len());
+        gathered.push(Gathered {
+            bookmark: ImportedBookmark {
+                url,
+                title,
+                added: Some(added)
+                    .filter(|a| *a > 0)
+                    .and_then(DateTime::from_timestamp_micros),
+                keyword,
+                ..Default::default()
+            },
+            folders: path,
+        });
+    }
+
+    let mut bookmarks = Vec::new();
+    for Gathered {
+        mut bookmark,
+        folders,
+    } in gathered
+    {
+        let own = tags_by_url.remove(&bookmark.url).unwrap_or_default();
+        for tag in own.iter().chain(&folders) {
+            push_tag(&mut bookmark.tags, tag);
+        }
+        bookmarks.push(bookmark);
+    }
+
+    if let Some(min_visits) = history_min_visits {
+        let mut stmt = conn.prepare(
+            "SELECT p.url, IFNULL(p.title, ''), MIN(v.visit_date)
+             FROM moz_places p
+             JOIN moz_historyvisits v ON v.place_id = p.id
+             WHERE p.visit_count >= ?1 AND p.hidden = 0
+             GROUP BY p.id
+             ORDER BY MIN(v.visit_date), p.id",
+        )?;
+        let rows = stmt.query_map([min_visits.max(1)], |row| {
+            Ok((
+                row.get::<_, String>(0)?,
+                row.get::<_, String>(1)?,
+                row.get::<_, Option<i64>>(2)?,
+            ))
+        })?;
+        for row in rows {
+            let (url, title, first_visit) = row?;
+            if by_url.contains_key(&url) {
+                continue;
+            }
+            bookmarks.push(ImportedBookmark {
+                url,
+                title: Some(title).filter(|t| !t.trim().is_empty()),
+                added: first_visit
+                    .filter(|a| *a > 0)
+                    .and_then(DateTime::from_timestamp_micros),
+                tags: vec![HISTORY_TAG.to_string()],
+                ..Default::default()
+            });
+        }
+    }
+
+    Ok(bookmarks)
+}
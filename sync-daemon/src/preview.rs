@@ -0,0 +1,188 @@
+use crate::html::{collapse_whitespace, decode_entities, tokenize, Token};
+use crate::naming::write_atomically;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::fs;
+use std::io;
+use std::path::Path;
+use url::Url;
+
+/// A bookmark's preview, next to its other files in the cache.
+pub const PREVIEW_FILENAME: &str = "preview.toml";
+/// Stem of the stored favicon, e.g. `favicon.ico`.
+pub const FAVICON_STEM: &str = "favicon";
+/// Stem of the stored preview image, e.g. `preview-image.jpg`.
+pub const PREVIEW_IMAGE_STEM: &str = "preview-image";
+
+/// Whether and how previews are captured when a bookmark is saved. Stored
+/// in `~/.kaya/.config` under `[preview]`; missing keys take their default
+/// values.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct PreviewOptions {
+    pub enabled: bool,
+    /// Fetch the page when the extension didn't send its HTML.
+    pub fetch: bool,
+    /// Download the favicon and preview image into the cache.
+    pub store_images: bool,
+    /// Images larger than this are left on the site.
+    pub max_image_bytes: u64,
+}
+
+impl Default for PreviewOptions {
+    fn default() -> Self {
+        PreviewOptions {
+            enabled: true,
+            fetch: true,
+            store_images: true,
+            max_image_bytes: 1024 * 1024,
+        }
+    }
+}
+
+/// What a page says about itself in its `<head>`: the `<title>`,
+/// OpenGraph and Twitter card fields and the canonical URL.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct PagePreview {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub title: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub description: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub site_name: Option<String>,
+    /// `og:type`, such as `article` or `video.other`.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub kind: Option<String>,
+    /// `twitter:card`, such as `summary_large_image`.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub card: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub canonical_url: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub image_url: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub favicon_url: Option<String>,
+    /// The stored copy of `image_url`, in the same cache directory.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub image: Option<String>,
+    /// The stored copy of `favicon_url`, in the same cache directory.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub favicon: Option<String>,
+}
+
+/// How much a `<link rel>` is preferred as the favicon; higher wins.
+fn icon_rank(rel: &str) -> Option<u8> {
+    let rels: Vec<&str> = rel.split_whitespace().collect();
+    if rels.contains(&"icon") {
+        Some(2)
+    } else if rels.contains(&"apple-touch-icon") || rels.contains(&"apple-touch-icon-precomposed") {
+        Some(1)
+    } else {
+        None
+    }
+}
+
+/// Reads a page's title, description, site name, type, card, canonical URL,
+/// preview image and favicon. OpenGraph fields win over Twitter ones, which
+/// win over plain HTML. URLs are resolved against `page_url`; without a
+/// declared icon the favicon is `/favicon.ico`.
+pub fn extract_preview(html: &str, page_url: &Url) -> PagePreview {
+    let tokens = tokenize(html);
+    let mut meta: HashMap<String, String> = HashMap::new();
+    let mut title = None;
+    let mut canonical = None;
+    let mut icon: Option<(u8, String)> = None;
+
+    for (i, token) in tokens.iter().enumerate() {
+        let Token::Start { name, .. } = token else {
+            continue;
+        };
+        match name.as_str() {
+            "meta" => {
+                let key = token
+                    .attribute("property")
+                    .or(token.attribute("name"))
+                    .map(str::to_lowercase);
+                if let (Some(key), Some(content)) = (key, token.attribute("content")) {
+                    let content = collapse_whitespace(content);
+                    if !content.is_empty() {
+                        meta.entry(key).or_insert(content);
+                    }
+                }
+            }
+            "title" if title.is_none() => {
+                if let Some(Token::Text(text)) = tokens.get(i + 1) {
+                    title =
+                        Some(collapse_whitespace(&decode_entities(text))).filter(|t| !t.is_empty());
+                }
+            }
+            "link" => {
+                let rel = token.attribute("rel").unwrap_or("").to_lowercase();
+                let Some(href) = token.attribute("href").filter(|h| !h.trim().is_empty()) else {
+                    continue;
+                };
+                if rel.split_whitespace().any(|r| r == "canonical") && canonical.is_none() {
+                    canonical = Some(href.trim().to_string());
+                }
+                if let Some(rank) = icon_rank(&rel) {
+                    if icon.as_ref().is_none_or(|(best, _)| rank > *best) {
+                        icon = Some((rank, href.trim().to_string()));
+                    }
+                }
+            }
+            // Everything that matters is in the head
+            "body" => break,
+            _ => {}
+        }
+    }
+
+    let field = |keys: &[&str]| keys.iter().find_map(|k| meta.get(*k)).cloned();
+    let resolve = |href: String| {
+        page_url
+            .join(&href)
+            .ok()
+            .filter(|u| matches!(u.scheme(), "http" | "https"))
+            .map(String::from)
+    };
+
+    PagePreview {
+        title: field(&["og:title", "twitter:title"]).or(title),
+        description: field(&["og:description", "twitter:description", "description"]),
+        site_name: field(&["og:site_name", "application-name"]),
+        kind: field(&["og:type"]),
+        card: field(&["twitter:card"]),
+        canonical_url: canonical.or_else(|| field(&["og:url"])).and_then(resolve),
+        image_url: field(&[
+            "og:image:secure_url",
+            "og:image",
+            "og:image:url",
+            "twitter:image",
+            "twitter:image:src",
+        ])
+        .and_then(resolve),
+        favicon_url: icon
+            .map(|(_, href)| href)
+            .or_else(|| Some("/favicon.ico".to_string()))
+            .and_then(resolve),
+        image: None,
+        favicon: None,
+    }
+}
+
+/// Writes `preview` to `dir`, creating the directory if needed.
+pub fn write_preview(dir: &Path, preview: &PagePreview) -> io::Result<()> {
+    fs::create_dir_all(dir)?;
+    let text = toml::to_string(preview).map_err(io::Error::other)?;
+    write_atomically(&dir.join(PREVIEW_FILENAME), text.as_bytes())
+}
+
+/// Reads the preview stored in `dir`, if there is one.
+pub fn read_preview(dir: &Path) -> io::Result<Option<PagePreview>> {
+    match fs::read_to_string(dir.join(PREVIEW_FILENAME)) {
+        Ok(text) => toml::from_str(&text)
+            .map(Some)
+            .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e)),
+        Err(e) if e.kind() == io::ErrorKind::NotFound => Ok(None),
+        Err(e) => Err(e),
+    }
+}
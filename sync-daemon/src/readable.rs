@@ -0,0 +1,445 @@
+use crate::archive::SNAPSHOT_FILENAME;
+use crate::html::{collapse_whitespace, decode_entities, tokenize, Token, VOID_ELEMENTS};
+use crate::naming::write_atomically;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::fs;
+use std::io;
+use std::path::Path;
+
+/// The extracted text of a snapshot, next to it in the cache.
+pub const READABLE_TEXT_FILENAME: &str = "readable.txt";
+/// Title, byline and excerpt of a snapshot, next to it in the cache.
+pub const READABLE_INFO_FILENAME: &str = "readable.toml";
+
+/// The article in a page, without navigation, ads and other chrome.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Readable {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub title: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub byline: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub excerpt: Option<String>,
+    /// Paragraphs separated by blank lines. Stored in its own file.
+    #[serde(default, skip_serializing_if = "String::is_empty")]
+    pub text: String,
+}
+
+/// Elements that never hold the article.
+const SKIPPED_ELEMENTS: &[&str] = &[
+    "script", "style", "noscript", "template", "svg", "canvas", "iframe", "nav", "header",
+    "footer", "aside", "form", "button", "select", "textarea", "dialog", "title", "head",
+];
+
+/// Elements that start a new paragraph of text.
+const BLOCK_ELEMENTS: &[&str] = &[
+    "p",
+    "div",
+    "section",
+    "article",
+    "main",
+    "h1",
+    "h2",
+    "h3",
+    "h4",
+    "h5",
+    "h6",
+    "ul",
+    "ol",
+    "li",
+    "blockquote",
+    "pre",
+    "br",
+    "hr",
+    "table",
+    "tr",
+    "td",
+    "th",
+    "figure",
+    "figcaption",
+    "dl",
+    "dt",
+    "dd",
+];
+
+/// Blocks that read as prose, which are what scores a container.
+const PROSE_ELEMENTS: &[&str] = &["p", "pre", "blockquote", "td"];
+
+/// Class and id words that mark page chrome rather than the article.
+const UNLIKELY: &[&str] = &[
+    "ad-",
+    "banner",
+    "breadcrumb",
+    "comment",
+    "cookie",
+    "disqus",
+    "footer",
+    "gdpr",
+    "masthead",
+    "menu",
+    "modal",
+    "newsletter",
+    "pagination",
+    "popup",
+    "promo",
+    "related",
+    "share",
+    "sidebar",
+    "social",
+    "sponsor",
+    "subscribe",
+    "toolbar",
+];
+
+/// Words that overrule `UNLIKELY`, as in `main-menu-and-content`.
+const MAYBE: &[&str] = &["article", "body", "content", "main", "post", "story"];
+
+/// A run of text between block boundaries.
+struct Block {
+    text: String,
+    /// Ids of the elements it sits in, outermost first.
+    ancestors: Vec<usize>,
+    prose: bool,
+}
+
+struct Open {
+    name: String,
+    id: usize,
+    skipped: bool,
+}
+
+fn is_unlikely(token: &Token) -> bool {
+    let words = format!(
+        "{} {} {}",
+        token.attribute("class").unwrap_or(""),
+        token.attribute("id").unwrap_or(""),
+        token.attribute("role").unwrap_or("")
+    )
+    .to_lowercase();
+    let hidden = token.attribute("hidden").is_some()
+        || token.attribute("aria-hidden") == Some("true")
+        || matches!(
+            token.attribute("role"),
+            Some("navigation" | "complementary")
+        );
+    hidden
+        || (UNLIKELY.iter().any(|w| words.contains(w)) && !MAYBE.iter().any(|w| words.contains(w)))
+}
+
+/// Pulls the article out of a page: the title, author, a short excerpt and
+/// the main text. Like Readability, it drops the parts of the page that are
+/// never the article, scores each container by the prose directly inside
+/// it, and keeps the best one. Pages with an `<article>` or `<main>` that
+/// holds most of the prose use that instead.
+pub fn extract_readable(html: &str) -> Readable {
+    let tokens = tokenize(html);
+    let mut blocks: Vec<Block> = Vec::new();
+    let mut stack: Vec<Open> = Vec::new();
+    let mut current = String::new();
+    let mut next_id = 0;
+    let mut landmarks: Vec<usize> = Vec::new();
+    let mut title = None;
+    let mut heading = None;
+    let mut meta: HashMap<String, String> = HashMap::new();
+    let mut byline_id = None;
+    let mut byline_text = String::new();
+    let mut byline = None;
+
+    let flush = |current: &mut String, stack: &[Open], blocks: &mut Vec<Block>| {
+        let text = collapse_whitespace(&decode_entities(current));
+        current.clear();
+        if text.is_empty() {
+            return;
+        }
+        blocks.push(Block {
+            text,
+            ancestors: stack.iter().map(|o| o.id).collect(),
+            prose: stack
+                .iter()
+                .rev()
+                .find(|o| BLOCK_ELEMENTS.contains(&o.name.as_str()))
+                .is_some_and(|o| PROSE_ELEMENTS.contains(&o.name.as_str())),
+        });
+    };
+
+    for (i, token) in tokens.iter().enumerate() {
+        let skipping = stack.last().is_some_and(|o| o.skipped);
+        match token {
+            Token::Start { name, .. } => {
+                if name == "meta" {
+                    let key = token
+                        .attribute("property")
+                        .or(token.attribute("name"))
+                        .map(str::to_lowercase);
+                    if let (Some(key), Some(content)) = (key, token.attribute("content")) {
+                        meta.entry(key)
+                            .or_insert_with(|| collapse_whitespace(content));
+                    }
+                }
+                if name == "title" && title.is_none() {
+                    if let Some(Token::Text(text)) = tokens.get(i + 1) {
+                        title = Some(collapse_whitespace(&decode_entities(text)));
+                    }
+                }
+                if BLOCK_ELEMENTS.contains(&name.as_str()) {
+                    flush(&mut current, &stack, &mut blocks);
+                }
+                if VOID_ELEMENTS.contains(&name.as_str()) {
+                    continue;
+                }
+
+                let id = next_id;
+                next_id += 1;
+                // An article's own header holds its headline and byline
+                let article_header = name == "header" && stack.iter().any(|o| o.name == "article");
+                let skipped = skipping
+                    || (SKIPPED_ELEMENTS.contains(&name.as_str()) && !article_header)
+                    || is_unlikely(token);
+                if !skipped {
+                    if name == "article"
+                        || name == "main"
+                        || token.attribute("role") == Some("main")
+                    {
+                        landmarks.push(id);
+                    }
+                    let marks_author = ["class", "itemprop", "rel"].iter().any(|a| {
+                        token.attribute(a).is_some_and(|v| {
+                            let v = v.to_lowercase();
+                            v.contains("byline") || v.contains("author")
+                        })
+                    });
+                    if marks_author && byline_id.is_none() && byline.is_none() {
+                        byline_text.clear();
+                        byline_id = Some(id);
+                    }
+                }
+                stack.push(Open {
+                    name: name.clone(),
+                    id,
+                    skipped,
+                });
+            }
+            Token::End(name) => {
+                let Some(pos) = stack.iter().rposition(|o| &o.name == name) else {
+                    continue;
+                };
+                if BLOCK_ELEMENTS.contains(&name.as_str())
+                    || stack[pos + 1..]
+                        .iter()
+                        .any(|o| BLOCK_ELEMENTS.contains(&o.name.as_str()))
+                {
+                    flush(&mut current, &stack, &mut blocks);
+                }
+                for closed in stack.drain(pos..) {
+                    if Some(closed.id) == byline_id {
+                        // An empty match, like an avatar, lets a later one count
+                        byline_id = None;
+                        let text = collapse_whitespace(&decode_entities(&byline_text));
+                        byline_text.clear();
+                        if !text.is_empty() {
+                            byline = Some(text);
+                        }
+                    }
+                }
+            }
+            Token::Text(text) => {
+                if skipping {
+                    continue;
+                }
+                current.push_str(text);
+                if byline_id.is_some() {
+                    byline_text.push_str(text);
+                }
+                if heading.is_none() && stack.iter().any(|o| o.name == "h1") {
+                    let text = collapse_whitespace(&decode_entities(text));
+                    if !text.is_empty() {
+                        heading = Some(text);
+                    }
+                }
+            }
+        }
+    }
+    flush(&mut current, &stack, &mut blocks);
+
+    let content = choose_content(&blocks, &landmarks);
+    let text = content
+        .iter()
+        .map(|b| b.text.as_str())
+        .collect::<Vec<_>>()
+        .join("\n\n");
+
+    let meta_value = |keys: &[&str]| {
+        keys.iter()
+            .find_map(|k| meta.get(*k))
+            .filter(|v| !v.is_empty())
+            .cloned()
+    };
+    let title = meta_value(&["og:title", "twitter:title"])
+        .or(title.filter(|t| !t.is_empty()))
+        .or(heading);
+    let byline = meta_value(&["author", "article:author"])
+        .filter(|a| !a.starts_with("http"))
+        .or(byline)
+        .filter(|b| b.chars().count() <= 100);
+    let excerpt =
+        meta_value(&["description", "og:description", "twitter:description"]).or_else(|| {
+            content
+                .iter()
+                .find(|b| b.prose)
+                .map(|b| truncate(&b.text, 200))
+        });
+
+    Readable {
+        title,
+        byline,
+        excerpt,
+        text,
+    }
+}
+
+/// The blocks that make up the article.
+fn choose_content<'a>(blocks: &'a [Block], landmarks: &[usize]) -> Vec<&'a Block> {
+    let prose_len: usize = blocks
+        .iter()
+        .filter(|b| b.prose)
+        .map(|b| b.text.len())
+        .sum();
+    let within = |id: usize| -> Vec<&Block> {
+        blocks
+            .iter()
+            .filter(|b| b.ancestors.contains(&id))
+            .collect()
+    };
+
+    // An <article> or <main> holding most of the prose is the article
+    let landmark = landmarks
+        .iter()
+        .map(|&id| {
+            let len: usize = within(id)
+                .iter()
+                .filter(|b| b.prose)
+                .map(|b| b.text.len())
+                .sum();
+            (id, len)
+        })
+        .max_by_key(|(_, len)| *len);
+    if let Some((id, len)) = landmark {
+        if len > 0 && len * 2 >= prose_len {
+            return within(id);
+        }
+    }
+
+    // Otherwise score containers by the prose in them, as Readability does:
+    // a paragraph counts fully for its parent and half for its grandparent
+    let mut scores: HashMap<usize, f64> = HashMap::new();
+    for block in blocks.iter().filter(|b| b.prose && b.text.len() >= 25) {
+        let score = 1.0
+            + block.text.matches([',', '，']).count() as f64
+            + (block.text.len() / 100).min(3) as f64;
+        let mut parents = block.ancestors.iter().rev().skip(1);
+        if let Some(&parent) = parents.next() {
+            *scores.entry(parent).or_default() += score;
+        }
+        if let Some(&grandparent) = parents.next() {
+            *scores.entry(grandparent).or_default() += score / 2.0;
+        }
+    }
+
+    match scores
+        .into_iter()
+        .max_by(|a, b| a.1.total_cmp(&b.1).then(b.0.cmp(&a.0)))
+    {
+        Some((id, _)) => within(id),
+        None => blocks.iter().collect(),
+    }
+}
+
+fn truncate(text: &str, max_chars: usize) -> String {
+    if text.chars().count() <= max_chars {
+        return text.to_string();
+    }
+    let cut: String = text.chars().take(max_chars).collect();
+    let cut = cut.rsplit_once(' ').map(|(head, _)| head).unwrap_or(&cut);
+    format!("{}…", cut.trim_end_matches([',', '.', ';', ':']))
+}
+
+/// Writes `readable` next to the snapshot in `dir`.
+pub fn write_readable(dir: &Path, readable: &Readable) -> io::Result<()> {
+    let info = Readable {
+        text: String::new(),
+        ..readable.clone()
+    };
+    let info = toml::to_string(&info).map_err(io::Error::other)?;
+    write_atomically(&dir.join(READABLE_TEXT_FILENAME), readable.text.as_bytes())?;
+    write_atomically(&dir.join(READABLE_INFO_FILENAME), info.as_bytes())
+}
+
+/// Reads the readable text stored in `dir`, if there is any.
+pub fn read_readable(dir: &Path) -> io::Result<Option<Readable>> {
+    let text = match fs::read_to_string(dir.join(READABLE_TEXT_FILENAME)) {
+        Ok(text) => text,
+        Err(e) if e.kind() == io::ErrorKind::NotFound => return Ok(None),
+        Err(e) => return Err(e),
+    };
+    let info = match fs::read_to_string(dir.join(READABLE_INFO_FILENAME)) {
+        Ok(info) => {
+            toml::from_str(&info).map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))?
+        }
+        Err(e) if e.kind() == io::ErrorKind::NotFound => Readable::default(),
+        Err(e) => return Err(e),
+    };
+    Ok(Some(Readable { text, ..info }))
+}
+
+/// Extracts the snapshot in `dir` unless its readable text is already up
+/// to date. Returns the readable text, or `None` when there's no snapshot.
+pub fn ensure_readable(dir: &Path) -> io::Result<Option<Readable>> {
+    let snapshot = dir.join(SNAPSHOT_FILENAME);
+    let snapshot_modified = match fs::metadata(&snapshot) {
+        Ok(m) => m.modified()?,
+        Err(e) if e.kind() == io::ErrorKind::NotFound => return read_readable(dir),
+        Err(e) => return Err(e),
+    };
+    let text_modified = fs::metadata(dir.join(READABLE_TEXT_FILENAME)).and_then(|m| m.modified());
+    if text_modified.is_ok_and(|t| t >= snapshot_modified) {
+        if let Some(readable) = read_readable(dir)? {
+            return Ok(Some(readable));
+        }
+    }
+
+    let html = String::from_utf8_lossy(&fs::read(&snapshot)?).into_owned();
+    let readable = extract_readable(&html);
+    write_readable(dir, &readable)?;
+    Ok(Some(readable))
+}
+
+/// Extracts every snapshot under `cache_dir` that doesn't have up-to-date
+/// readable text yet, and returns how many were written. A snapshot that
+/// fails is skipped. Stops between snapshots once `stopped` says so.
+pub fn extract_missing(cache_dir: &Path, stopped: impl Fn() -> bool) -> io::Result<usize> {
+    if !cache_dir.exists() {
+        return Ok(0);
+    }
+
+    let mut written = 0;
+    for entry in fs::read_dir(cache_dir)?.filter_map(|e| e.ok()) {
+        if stopped() {
+            break;
+        }
+        let dir = entry.path();
+        let text = dir.join(READABLE_TEXT_FILENAME);
+        let stale = match (
+            fs::metadata(dir.join(SNAPSHOT_FILENAME)).and_then(|m| m.modified()),
+            fs::metadata(&text).and_then(|m| m.modified()),
+        ) {
+            (Ok(snapshot), Ok(text)) => text < snapshot,
+            (Ok(_), Err(_)) => true,
+            _ => false,
+        };
+        if stale && ensure_readable(&dir).is_ok() {
+            written += 1;
+        }
+    }
+    Ok(written)
+}
@@ -0,0 +1,187 @@
+use chrono::NaiveTime;
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+use std::sync::{Condvar, Mutex};
+use std::time::Duration;
+
+/// When the daemon syncs. Stored in `~/.kaya/.config` under `[sync]`;
+/// missing keys take their default values.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct ScheduleOptions {
+    /// Seconds between syncs while things are changing.
+    pub interval_secs: u64,
+    /// Shortest gap between two syncs, however often the daemon is woken.
+    pub min_interval_secs: u64,
+    /// Longest the interval grows to while idle or offline.
+    pub max_interval_secs: u64,
+    /// Local times between which the daemon doesn't sync at all.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub quiet_hours: Option<QuietHours>,
+    /// Skip syncing while the network connection is metered.
+    pub unmetered_only: bool,
+}
+
+impl Default for ScheduleOptions {
+    fn default() -> Self {
+        ScheduleOptions {
+            interval_secs: 60,
+            min_interval_secs: 5,
+            max_interval_secs: 15 * 60,
+            quiet_hours: None,
+            unmetered_only: false,
+        }
+    }
+}
+
+/// A daily window given as `start = "22:00"`, `end = "07:00"`. Windows that
+/// cross midnight are allowed.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct QuietHours {
+    #[serde(with = "hhmm")]
+    pub start: NaiveTime,
+    #[serde(with = "hhmm")]
+    pub end: NaiveTime,
+}
+
+impl QuietHours {
+    pub fn contains(&self, time: NaiveTime) -> bool {
+        if self.start <= self.end {
+            time >= self.start && time < self.end
+        } else {
+            time >= self.start || time < self.end
+        }
+    }
+}
+
+mod hhmm {
+    use super::*;
+
+    pub fn serialize<S: Serializer>(time: &NaiveTime, s: S) -> Result<S::Ok, S::Error> {
+        s.serialize_str(&time.format("%H:%M").to_string())
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(d: D) -> Result<NaiveTime, D::Error> {
+        let value = String::deserialize(d)?;
+        NaiveTime::parse_from_str(&value, "%H:%M").map_err(serde::de::Error::custom)
+    }
+}
+
+/// How a sync pass went, which decides how long to wait for the next one.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SyncOutcome {
+    /// Files were transferred.
+    Changed,
+    /// Nothing to do.
+    Idle,
+    /// The server couldn't be reached.
+    Unreachable,
+}
+
+/// Tracks the current sync interval. It starts at `interval_secs`, doubles
+/// while idle or offline up to `max_interval_secs`, and drops back as soon
+/// as something changes locally or remotely.
+#[derive(Debug, Clone)]
+pub struct Scheduler {
+    current: Duration,
+}
+
+impl Scheduler {
+    pub fn new(opts: &ScheduleOptions) -> Self {
+        Scheduler {
+            current: Duration::from_secs(opts.interval_secs),
+        }
+    }
+
+    /// Records a finished pass and returns how long to wait before the next.
+    pub fn next_interval(&mut self, opts: &ScheduleOptions, outcome: SyncOutcome) -> Duration {
+        let base = Duration::from_secs(opts.interval_secs.max(opts.min_interval_secs));
+        let max = Duration::from_secs(opts.max_interval_secs).max(base);
+        self.current = match outcome {
+            SyncOutcome::Changed => base,
+            SyncOutcome::Idle | SyncOutcome::Unreachable => (self.current * 2).clamp(base, max),
+        };
+        self.current
+    }
+
+    /// Something changed locally: go back to the base interval.
+    pub fn local_change(&mut self, opts: &ScheduleOptions) {
+        self.current = Duration::from_secs(opts.interval_secs.max(opts.min_interval_secs));
+    }
+
+    pub fn current(&self) -> Duration {
+        self.current
+    }
+}
+
+/// Whether a pass may run now, given the local time and whether the
+/// connection is known to be metered.
+pub fn sync_allowed(opts: &ScheduleOptions, local_time: NaiveTime, metered: bool) -> bool {
+    if opts.quiet_hours.is_some_and(|q| q.contains(local_time)) {
+        return false;
+    }
+    !(opts.unmetered_only && metered)
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Wake {
+    /// The wait ran its full length.
+    Timeout,
+    /// `wake()` was called.
+    Woken,
+    /// `stop()` was called.
+    Stopped,
+}
+
+/// Lets other threads cut the sync loop's wait short, or stop it.
+#[derive(Debug, Default)]
+pub struct SyncSignal {
+    state: Mutex<SignalState>,
+    cond: Condvar,
+}
+
+#[derive(Debug, Default)]
+struct SignalState {
+    woken: bool,
+    stopped: bool,
+}
+
+impl SyncSignal {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn wake(&self) {
+        let mut state = self.state.lock().unwrap_or_else(|e| e.into_inner());
+        state.woken = true;
+        self.cond.notify_all();
+    }
+
+    pub fn stop(&self) {
+        let mut state = self.state.lock().unwrap_or_else(|e| e.into_inner());
+        state.stopped = true;
+        self.cond.notify_all();
+    }
+
+    pub fn is_stopped(&self) -> bool {
+        self.state.lock().unwrap_or_else(|e| e.into_inner()).stopped
+    }
+
+    /// Waits up to `timeout`. A wake that arrived while nobody was waiting
+    /// is returned straight away rather than lost.
+    pub fn wait(&self, timeout: Duration) -> Wake {
+        let state = self.state.lock().unwrap_or_else(|e| e.into_inner());
+        let (mut state, _) = self
+            .cond
+            .wait_timeout_while(state, timeout, |s| !s.woken && !s.stopped)
+            .unwrap_or_else(|e| e.into_inner());
+
+        if state.stopped {
+            Wake::Stopped
+        } else if state.woken {
+            state.woken = false;
+            Wake::Woken
+        } else {
+            Wake::Timeout
+        }
+    }
+}
@@ -0,0 +1,485 @@
+use crate::bookmarks::read_shortcut;
+use crate::html::{self, Token};
+use crate::meta::{cmp_meta_filenames, fold_meta, tag_key, Meta};
+use crate::readable::READABLE_TEXT_FILENAME;
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, BTreeSet, HashMap, HashSet};
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::time::SystemTime;
+
+/// BM25 parameters.
+const K1: f32 = 1.2;
+const B: f32 = 0.75;
+
+pub const DEFAULT_RESULT_LIMIT: usize = 50;
+
+/// Where a term came from. Matches in tags and titles count for more than
+/// matches deep in a cached page.
+#[derive(Debug, Clone, Copy)]
+enum Field {
+    Filename,
+    Title,
+    Url,
+    Tag,
+    Note,
+    Body,
+    Cache,
+}
+
+impl Field {
+    fn weight(self) -> f32 {
+        match self {
+            Field::Tag => 3.0,
+            Field::Title => 2.0,
+            Field::Url | Field::Note => 1.5,
+            Field::Filename | Field::Body => 1.0,
+            Field::Cache => 0.5,
+        }
+    }
+}
+
+/// The terms one file on disk contributes to one anga. Meta files are kept
+/// whole instead, because tags and notes only count once folded together.
+#[derive(Debug)]
+struct Source {
+    modified: Option<SystemTime>,
+    anga: Option<String>,
+    terms: HashMap<String, f32>,
+    meta: Option<Meta>,
+}
+
+impl Source {
+    fn new(modified: Option<SystemTime>, anga: Option<String>) -> Self {
+        Source {
+            modified,
+            anga,
+            terms: HashMap::new(),
+            meta: None,
+        }
+    }
+
+    fn add(&mut self, text: &str, field: Field) {
+        add_terms(&mut self.terms, text, field);
+    }
+}
+
+fn add_terms(terms: &mut HashMap<String, f32>, text: &str, field: Field) {
+    for token in tokenize(text) {
+        *terms.entry(token).or_default() += field.weight();
+    }
+}
+
+#[derive(Debug, Default)]
+struct Doc {
+    terms: HashMap<String, f32>,
+    tags: BTreeSet<String>,
+    len: f32,
+    hidden: bool,
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct SearchQuery {
+    /// Free text. Every word must match. The last word, and any word ending
+    /// in `*`, also matches as a prefix.
+    pub text: String,
+    /// Tags every result must carry, compared case-insensitively.
+    pub tags: Vec<String>,
+    pub limit: Option<usize>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct SearchHit {
+    pub filename: String,
+    pub score: f32,
+}
+
+/// A full-text index over `~/.kaya`: anga contents, bookmark titles and URLs,
+/// meta tags and notes, and text in `cache/{anga}/`. It lives in memory and
+/// `refresh` brings it up to date by re-reading only files whose modification
+/// time changed.
+#[derive(Debug)]
+pub struct SearchIndex {
+    kaya_dir: PathBuf,
+    sources: HashMap<PathBuf, Source>,
+    by_anga: HashMap<String, HashSet<PathBuf>>,
+    docs: HashMap<String, Doc>,
+    postings: BTreeMap<String, HashSet<String>>,
+}
+
+impl SearchIndex {
+    pub fn new(kaya_dir: &Path) -> Self {
+        SearchIndex {
+            kaya_dir: kaya_dir.to_path_buf(),
+            sources: HashMap::new(),
+            by_anga: HashMap::new(),
+            docs: HashMap::new(),
+            postings: BTreeMap::new(),
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        self.docs.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.docs.is_empty()
+    }
+
+    /// Re-reads new and modified files and forgets deleted ones. Returns the
+    /// number of anga whose entry changed.
+    pub fn refresh(&mut self) -> io::Result<usize> {
+        let mut seen = HashSet::new();
+        let mut affected = HashSet::new();
+
+        for path in self.source_files()? {
+            let modified = fs::metadata(&path).and_then(|m| m.modified()).ok();
+            seen.insert(path.clone());
+
+            if let Some(existing) = self.sources.get(&path) {
+                if modified.is_some() && existing.modified == modified {
+                    continue;
+                }
+            }
+
+            self.remove_source(&path, &mut affected);
+            let source = self.extract(&path, modified);
+            if let Some(anga) = &source.anga {
+                affected.insert(anga.clone());
+                self.by_anga
+                    .entry(anga.clone())
+                    .or_default()
+                    .insert(path.clone());
+            }
+            self.sources.insert(path, source);
+        }
+
+        let removed: Vec<PathBuf> = self
+            .sources
+            .keys()
+            .filter(|p| !seen.contains(*p))
+            .cloned()
+            .collect();
+        for path in removed {
+            self.remove_source(&path, &mut affected);
+        }
+
+        for anga in &affected {
+            self.rebuild_doc(anga);
+        }
+        Ok(affected.len())
+    }
+
+    pub fn search(&self, query: &SearchQuery) -> Vec<SearchHit> {
+        let wanted_tags: Vec<String> = query.tags.iter().map(|t| tag_key(t)).collect();
+        let wanted = |doc: &Doc| !doc.hidden && wanted_tags.iter().all(|t| doc.tags.contains(t));
+
+        let words: Vec<&str> = query.text.split_whitespace().collect();
+        let mut required: Vec<(String, bool)> = Vec::new();
+        for (i, word) in words.iter().enumerate() {
+            let explicit_prefix = word.ends_with('*');
+            let tokens = tokenize(word);
+            let last = tokens.len().saturating_sub(1);
+            for (j, token) in tokens.into_iter().enumerate() {
+                let prefix = j == last && (explicit_prefix || i == words.len() - 1);
+                required.push((token, prefix));
+            }
+        }
+
+        let mut hits: Vec<SearchHit> = if required.is_empty() {
+            if wanted_tags.is_empty() {
+                return Vec::new();
+            }
+            self.docs
+                .iter()
+                .filter(|(_, doc)| wanted(doc))
+                .map(|(filename, _)| SearchHit {
+                    filename: filename.clone(),
+                    score: 0.0,
+                })
+                .collect()
+        } else {
+            let mut scores: Option<HashMap<&str, f32>> = None;
+            for (term, prefix) in &required {
+                let term_scores = self.score_term(term, *prefix);
+                scores = Some(match scores {
+                    None => term_scores,
+                    Some(acc) => acc
+                        .into_iter()
+                        .filter_map(|(f, s)| term_scores.get(f).map(|t| (f, s + t)))
+                        .collect(),
+                });
+            }
+            scores
+                .unwrap_or_default()
+                .into_iter()
+                .filter(|(f, _)| self.docs.get(*f).map(wanted).unwrap_or(false))
+                .map(|(filename, score)| SearchHit {
+                    filename: filename.to_string(),
+                    score,
+                })
+                .collect()
+        };
+
+        // Best first; equal scores newest first
+        hits.sort_by(|a, b| {
+            b.score
+                .total_cmp(&a.score)
+                .then_with(|| b.filename.cmp(&a.filename))
+        });
+        hits.truncate(query.limit.unwrap_or(DEFAULT_RESULT_LIMIT));
+        hits
+    }
+
+    fn score_term(&self, term: &str, prefix: bool) -> HashMap<&str, f32> {
+        let n = self.docs.len() as f32;
+        let avg_len = if self.docs.is_empty() {
+            1.0
+        } else {
+            self.docs.values().map(|d| d.len).sum::<f32>() / n
+        };
+
+        let expansions: Vec<(&String, &HashSet<String>)> = if prefix {
+            self.postings
+                .range(term.to_string()..)
+                .take_while(|(k, _)| k.starts_with(term))
+                .collect()
+        } else {
+            self.postings.get_key_value(term).into_iter().collect()
+        };
+
+        let mut scores: HashMap<&str, f32> = HashMap::new();
+        for (expansion, filenames) in expansions {
+            let df = filenames.len() as f32;
+            let idf = ((n - df + 0.5) / (df + 0.5) + 1.0).ln();
+            for filename in filenames {
+                let doc = &self.docs[filename];
+                let tf = doc.terms.get(expansion).copied().unwrap_or(0.0);
+                let score = idf * tf * (K1 + 1.0) / (tf + K1 * (1.0 - B + B * doc.len / avg_len));
+                let best = scores.entry(filename.as_str()).or_default();
+                *best = best.max(score);
+            }
+        }
+        scores
+    }
+
+    fn source_files(&self) -> io::Result<Vec<PathBuf>> {
+        let mut files = Vec::new();
+        files.extend(list_files(&self.kaya_dir.join("anga"), |_| true)?);
+        files.extend(list_files(&self.kaya_dir.join("meta"), |n| {
+            n.ends_with(".toml")
+        })?);
+
+        let cache_dir = self.kaya_dir.join("cache");
+        if cache_dir.exists() {
+            for entry in fs::read_dir(&cache_dir)?.filter_map(|e| e.ok()) {
+                let dir = entry.path();
+                if dir.is_dir() {
+                    // Readable text is the page without its chrome, so it
+                    // stands in for the raw HTML once it has been extracted
+                    let readable = dir.join(READABLE_TEXT_FILENAME).is_file();
+                    files.extend(list_files(&dir, |n| {
+                        is_cached_text(n) && !(readable && is_html(n))
+                    })?);
+                }
+            }
+        }
+        Ok(files)
+    }
+
+    fn extract(&self, path: &Path, modified: Option<SystemTime>) -> Source {
+        let name = file_name(path);
+        let parent = path.parent().unwrap_or(Path::new(""));
+
+        if parent == self.kaya_dir.join("anga") {
+            let mut source = Source::new(modified, Some(name.clone()));
+            source.add(&filename_words(&name), Field::Filename);
+            if name.ends_with(".url") {
+                if let Ok(shortcut) = read_shortcut(path) {
+                    source.add(shortcut.title.as_deref().unwrap_or(""), Field::Title);
+                    source.add(&shortcut.url, Field::Url);
+                }
+            } else if name.ends_with(".md") || name.ends_with(".txt") {
+                source.add(&fs::read_to_string(path).unwrap_or_default(), Field::Body);
+            }
+            source
+        } else if parent == self.kaya_dir.join("meta") {
+            let meta = fs::read_to_string(path)
+                .ok()
+                .and_then(|t| Meta::parse(&t).ok());
+            let mut source = Source::new(modified, meta.as_ref().map(|m| m.anga.filename.clone()));
+            source.meta = meta;
+            source
+        } else {
+            // cache/{anga}/{file}
+            let anga = parent
+                .file_name()
+                .and_then(|n| n.to_str())
+                .map(String::from);
+            let mut source = Source::new(modified, anga);
+            let text = fs::read_to_string(path).unwrap_or_default();
+            if name.ends_with(".txt") {
+                source.add(&text, Field::Cache);
+            } else {
+                source.add(&html_to_text(&text), Field::Cache);
+            }
+            source
+        }
+    }
+
+    fn remove_source(&mut self, path: &Path, affected: &mut HashSet<String>) {
+        if let Some(old) = self.sources.remove(path) {
+            if let Some(anga) = old.anga {
+                if let Some(paths) = self.by_anga.get_mut(&anga) {
+                    paths.remove(path);
+                }
+                affected.insert(anga);
+            }
+        }
+    }
+
+    fn rebuild_doc(&mut self, anga: &str) {
+        if let Some(old) = self.docs.remove(anga) {
+            for term in old.terms.keys() {
+                if let Some(filenames) = self.postings.get_mut(term) {
+                    filenames.remove(anga);
+                    if filenames.is_empty() {
+                        self.postings.remove(term);
+                    }
+                }
+            }
+        }
+
+        // Meta and cache files only count while the anga itself exists
+        let anga_path = self.kaya_dir.join("anga").join(anga);
+        let paths = match self.by_anga.get(anga) {
+            Some(paths) if paths.contains(&anga_path) => paths,
+            _ => return,
+        };
+
+        let mut doc = Doc::default();
+        let mut metas: Vec<(&str, &Meta)> = Vec::new();
+        for path in paths {
+            let source = &self.sources[path];
+            for (term, weight) in &source.terms {
+                *doc.terms.entry(term.clone()).or_default() += weight;
+            }
+            if let Some(meta) = &source.meta {
+                metas.push((
+                    path.file_name().and_then(|n| n.to_str()).unwrap_or(""),
+                    meta,
+                ));
+            }
+        }
+
+        metas.sort_by(|a, b| cmp_meta_filenames(a.0, b.0));
+        let view = fold_meta(anga, metas);
+        for tag in &view.tags {
+            add_terms(&mut doc.terms, tag, Field::Tag);
+            doc.tags.insert(tag_key(tag));
+        }
+        add_terms(
+            &mut doc.terms,
+            view.note.as_deref().unwrap_or(""),
+            Field::Note,
+        );
+        if let Some(keyword) = &view.keyword {
+            add_terms(&mut doc.terms, keyword, Field::Tag);
+        }
+        doc.hidden = view.hidden;
+        doc.len = doc.terms.values().sum();
+
+        for term in doc.terms.keys() {
+            self.postings
+                .entry(term.clone())
+                .or_default()
+                .insert(anga.to_string());
+        }
+        self.docs.insert(anga.to_string(), doc);
+    }
+}
+
+/// Lowercased runs of letters and digits. Single letters are dropped as noise.
+pub fn tokenize(text: &str) -> Vec<String> {
+    text.split(|c: char| !c.is_alphanumeric())
+        .filter(|t| !t.is_empty())
+        .map(|t| t.to_lowercase())
+        .filter(|t| t.chars().count() > 1 || t.chars().all(|c| c.is_ascii_digit()))
+        .collect()
+}
+
+/// Strips tags, scripts and styles from HTML, leaving roughly the visible
+/// text with its entities decoded.
+pub fn html_to_text(html: &str) -> String {
+    let mut text = String::with_capacity(html.len() / 2);
+    let mut hidden = false;
+    for token in html::tokenize(html) {
+        match token {
+            Token::Start { name, .. } => {
+                hidden = matches!(name.as_str(), "script" | "style");
+                text.push(' ');
+            }
+            Token::End(_) => {
+                hidden = false;
+                text.push(' ');
+            }
+            Token::Text(t) if !hidden => text.push_str(&html::decode_entities(t)),
+            Token::Text(_) => {}
+        }
+    }
+    text
+}
+
+fn is_cached_text(name: &str) -> bool {
+    is_html(name) || name.to_lowercase().ends_with(".txt")
+}
+
+fn is_html(name: &str) -> bool {
+    let ext = name.rsplit('.').next().unwrap_or("").to_lowercase();
+    matches!(ext.as_str(), "html" | "htm")
+}
+
+fn list_files(dir: &Path, include: impl Fn(&str) -> bool) -> io::Result<Vec<PathBuf>> {
+    if !dir.exists() {
+        return Ok(Vec::new());
+    }
+    Ok(fs::read_dir(dir)?
+        .filter_map(|e| e.ok())
+        .filter(|e| e.path().is_file())
+        .filter(|e| {
+            e.file_name()
+                .to_str()
+                .map(|n| !n.starts_with('.') && include(n))
+                .unwrap_or(false)
+        })
+        .map(|e| e.path())
+        .collect())
+}
+
+fn file_name(path: &Path) -> String {
+    path.file_name()
+        .and_then(|n| n.to_str())
+        .unwrap_or("")
+        .to_string()
+}
+
+/// The words in a filename once the timestamp prefix and extension are gone,
+/// e.g. `india income tax` for `2025-01-01T120000-India%20Income%20Tax.pdf`.
+fn filename_words(filename: &str) -> String {
+    let decoded = urlencoding::decode(filename)
+        .map(|d| d.into_owned())
+        .unwrap_or_else(|_| filename.to_string());
+    let stem = decoded.rsplit_once('.').map(|(s, _)| s).unwrap_or(&decoded);
+    // Drop `YYYY-mm-ddTHHMMSS` and an optional `_SSSSSSSSS`
+    let rest = match stem.get(..17) {
+        Some(prefix) if prefix.as_bytes().get(10) == Some(&b'T') => {
+            let rest = &stem[17..];
+            rest.strip_prefix('_')
+                .map(|r| r.trim_start_matches(|c: char| c.is_ascii_digit()))
+                .unwrap_or(rest)
+        }
+        _ => stem,
+    };
+    rest.replace(['-', '_'], " ")
+}
@@ -0,0 +1,173 @@
+use std::fmt;
+use thiserror::Error;
+
+const SECTION: &str = "InternetShortcut";
+
+#[derive(Error, Debug, PartialEq, Eq)]
+pub enum ShortcutError {
+    #[error("missing [InternetShortcut] section")]
+    MissingSection,
+    #[error("missing URL in [InternetShortcut] section")]
+    MissingUrl,
+    #[error("invalid text encoding")]
+    Encoding,
+}
+
+/// A `.url` bookmark file, the Windows "Internet Shortcut" INI format:
+///
+/// ```text
+/// [InternetShortcut]
+/// URL=https://deobald.ca/
+/// Title=Steven Deobald
+/// IconFile=https://deobald.ca/favicon.ico
+/// IconIndex=0
+/// ```
+///
+/// Keys are matched case-insensitively. Keys and sections this type doesn't
+/// know about are kept, in order, so a parse/serialize round trip doesn't lose
+/// anything another client wrote.
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub struct UrlShortcut {
+    pub url: String,
+    pub title: Option<String>,
+    pub icon_file: Option<String>,
+    pub icon_index: Option<i32>,
+    /// Other keys in the `[InternetShortcut]` section.
+    pub extra: Vec<(String, String)>,
+    /// Other sections, such as `[{000214A0-0000-0000-C000-000000000046}]`.
+    pub sections: Vec<(String, Vec<(String, String)>)>,
+}
+
+impl UrlShortcut {
+    pub fn new(url: &str) -> Self {
+        UrlShortcut {
+            url: url.to_string(),
+            ..Default::default()
+        }
+    }
+
+    pub fn with_title(mut self, title: &str) -> Self {
+        self.title = Some(title.to_string());
+        self
+    }
+
+    /// Parses file contents, accepting UTF-8 with or without a BOM and UTF-16
+    /// with a BOM, as written by Windows.
+    pub fn from_bytes(bytes: &[u8]) -> Result<Self, ShortcutError> {
+        let text = decode(bytes)?;
+        Self::parse(&text)
+    }
+
+    /// Parses the text of a `.url` file. LF and CRLF line endings are both
+    /// accepted, as are `;` and `#` comment lines.
+    pub fn parse(text: &str) -> Result<Self, ShortcutError> {
+        let text = text.strip_prefix('\u{feff}').unwrap_or(text);
+
+        let mut shortcut = UrlShortcut::default();
+        let mut found_section = false;
+        let mut url = None;
+        // None: before any section, Some(None): [InternetShortcut], Some(Some(i)): other section
+        let mut current: Option<Option<usize>> = None;
+
+        for line in text.lines() {
+            let line = line.trim();
+            if line.is_empty() || line.starts_with(';') || line.starts_with('#') {
+                continue;
+            }
+
+            if let Some(name) = line.strip_prefix('[').and_then(|l| l.strip_suffix(']')) {
+                let name = name.trim();
+                if name.eq_ignore_ascii_case(SECTION) {
+                    found_section = true;
+                    current = Some(None);
+                } else {
+                    shortcut.sections.push((name.to_string(), Vec::new()));
+                    current = Some(Some(shortcut.sections.len() - 1));
+                }
+                continue;
+            }
+
+            let (key, value) = match line.split_once('=') {
+                Some((k, v)) => (k.trim(), v.trim()),
+                None => continue,
+            };
+
+            match current {
+                None => {}
+                Some(Some(i)) => shortcut.sections[i]
+                    .1
+                    .push((key.to_string(), value.to_string())),
+                Some(None) => match key.to_ascii_lowercase().as_str() {
+                    // The first URL wins, as it does for Windows
+                    "url" if url.is_none() => url = Some(value.to_string()),
+                    "title" => shortcut.title = Some(value.to_string()),
+                    "iconfile" => shortcut.icon_file = Some(value.to_string()),
+                    "iconindex" if value.parse::<i32>().is_ok() => {
+                        shortcut.icon_index = value.parse().ok()
+                    }
+                    _ => shortcut.extra.push((key.to_string(), value.to_string())),
+                },
+            }
+        }
+
+        if !found_section {
+            return Err(ShortcutError::MissingSection);
+        }
+        shortcut.url = url
+            .filter(|u| !u.is_empty())
+            .ok_or(ShortcutError::MissingUrl)?;
+        Ok(shortcut)
+    }
+}
+
+impl fmt::Display for UrlShortcut {
+    /// Serializes with LF line endings, matching what the extension has
+    /// always written.
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(f, "[{}]", SECTION)?;
+        writeln!(f, "URL={}", single_line(&self.url))?;
+        if let Some(title) = &self.title {
+            writeln!(f, "Title={}", single_line(title))?;
+        }
+        if let Some(icon_file) = &self.icon_file {
+            writeln!(f, "IconFile={}", single_line(icon_file))?;
+        }
+        if let Some(icon_index) = self.icon_index {
+            writeln!(f, "IconIndex={}", icon_index)?;
+        }
+        for (key, value) in &self.extra {
+            writeln!(f, "{}={}", key, single_line(value))?;
+        }
+        for (name, entries) in &self.sections {
+            writeln!(f, "[{}]", name)?;
+            for (key, value) in entries {
+                writeln!(f, "{}={}", key, single_line(value))?;
+            }
+        }
+        Ok(())
+    }
+}
+
+fn single_line(value: &str) -> String {
+    value
+        .split(['\r', '\n'])
+        .filter(|s| !s.is_empty())
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+fn decode(bytes: &[u8]) -> Result<String, ShortcutError> {
+    let utf16 = |bytes: &[u8], from: fn([u8; 2]) -> u16| {
+        let units: Vec<u16> = bytes.chunks_exact(2).map(|c| from([c[0], c[1]])).collect();
+        String::from_utf16(&units).map_err(|_| ShortcutError::Encoding)
+    };
+
+    match bytes {
+        [0xFF, 0xFE, rest @ ..] => utf16(rest, u16::from_le_bytes),
+        [0xFE, 0xFF, rest @ ..] => utf16(rest, u16::from_be_bytes),
+        [0xEF, 0xBB, 0xBF, rest @ ..] => {
+            String::from_utf8(rest.to_vec()).map_err(|_| ShortcutError::Encoding)
+        }
+        _ => String::from_utf8(bytes.to_vec()).map_err(|_| ShortcutError::Encoding),
+    }
+}
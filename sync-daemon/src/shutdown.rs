@@ -0,0 +1,29 @@
+use std::thread::{self, JoinHandle};
+use std::time::{Duration, Instant};
+
+/// How long in-flight transfers get to finish once shutdown starts.
+pub const DEFAULT_GRACE_PERIOD: Duration = Duration::from_secs(10);
+
+/// Waits up to `grace` for every thread to finish, and returns the names of
+/// those still running when it ran out. Those threads are left to be torn
+/// down with the process.
+pub fn join_within(threads: Vec<(String, JoinHandle<()>)>, grace: Duration) -> Vec<String> {
+    let deadline = Instant::now() + grace;
+    let mut running = threads;
+
+    loop {
+        let (finished, still_running): (Vec<_>, Vec<_>) =
+            running.into_iter().partition(|(_, t)| t.is_finished());
+        for (name, thread) in finished {
+            if thread.join().is_err() {
+                log::error!("{} thread panicked", name);
+            }
+        }
+        running = still_running;
+
+        if running.is_empty() || Instant::now() >= deadline {
+            return running.into_iter().map(|(name, _)| name).collect();
+        }
+        thread::sleep(Duration::from_millis(20));
+    }
+}
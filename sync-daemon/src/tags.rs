@@ -0,0 +1,81 @@
+use crate::anga::parse_filename_timestamp;
+use crate::meta::{fold_meta, group_meta_by_anga, tag_key};
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::io;
+use std::path::Path;
+
+pub const DEFAULT_TAG_LIMIT: usize = 20;
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct TagUsage {
+    pub tag: String,
+    /// Number of anga that currently carry the tag.
+    pub count: usize,
+    /// When the tag was last added to any anga.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub last_used: Option<DateTime<Utc>>,
+}
+
+/// Every tag in use, built from the folded meta of each anga so removed tags
+/// don't count. Tags compare case-insensitively; the most common spelling is
+/// reported. Ordered most used first, then most recently used.
+pub fn tag_catalogue(meta_dir: &Path) -> io::Result<Vec<TagUsage>> {
+    let mut counts: HashMap<String, usize> = HashMap::new();
+    let mut spellings: HashMap<String, HashMap<String, usize>> = HashMap::new();
+    let mut last_used: HashMap<String, DateTime<Utc>> = HashMap::new();
+
+    for (anga, metas) in group_meta_by_anga(meta_dir)? {
+        for (filename, meta) in &metas {
+            if let Some(at) = parse_filename_timestamp(filename) {
+                for tag in &meta.meta.tags {
+                    let latest = last_used.entry(tag_key(tag)).or_insert(at);
+                    *latest = (*latest).max(at);
+                }
+            }
+        }
+
+        let view = fold_meta(&anga, metas.iter().map(|(f, m)| (f.as_str(), m)));
+        for tag in view.tags {
+            let key = tag_key(&tag);
+            *counts.entry(key.clone()).or_default() += 1;
+            *spellings.entry(key).or_default().entry(tag).or_default() += 1;
+        }
+    }
+
+    let mut catalogue: Vec<TagUsage> = counts
+        .into_iter()
+        .map(|(key, count)| {
+            let tag = spellings[&key]
+                .iter()
+                .max_by(|a, b| a.1.cmp(b.1).then_with(|| b.0.cmp(a.0)))
+                .map(|(spelling, _)| spelling.clone())
+                .unwrap_or_else(|| key.clone());
+            TagUsage {
+                tag,
+                count,
+                last_used: last_used.get(&key).copied(),
+            }
+        })
+        .collect();
+
+    catalogue.sort_by(|a, b| {
+        b.count
+            .cmp(&a.count)
+            .then_with(|| b.last_used.cmp(&a.last_used))
+            .then_with(|| a.tag.cmp(&b.tag))
+    });
+    Ok(catalogue)
+}
+
+/// Narrows the catalogue to tags starting with `prefix`, for autocomplete.
+pub fn complete_tags(catalogue: &[TagUsage], prefix: &str, limit: usize) -> Vec<TagUsage> {
+    let prefix = tag_key(prefix);
+    catalogue
+        .iter()
+        .filter(|t| tag_key(&t.tag).starts_with(&prefix))
+        .take(limit)
+        .cloned()
+        .collect()
+}
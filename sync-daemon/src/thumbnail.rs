@@ -0,0 +1,164 @@
+use crate::naming::write_atomically;
+use image::{DynamicImage, ImageFormat};
+use std::fs;
+use std::io::{self, Cursor};
+use std::path::{Path, PathBuf};
+use std::time::SystemTime;
+
+/// An anga's thumbnail, in its cache directory.
+pub const THUMBNAIL_FILENAME: &str = "thumbnail.webp";
+/// An empty file left in place of the thumbnail when an anga can't have
+/// one, so it isn't read again until the anga changes.
+pub const NO_THUMBNAIL_FILENAME: &str = "thumbnail.none";
+/// Thumbnails fit within a square this many pixels wide.
+pub const THUMBNAIL_SIZE: u32 = 256;
+/// Files larger than this aren't decoded.
+pub const MAX_SOURCE_BYTES: u64 = 64 * 1024 * 1024;
+
+fn invalid(e: impl ToString) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, e.to_string())
+}
+
+fn image_format(filename: &str) -> Option<ImageFormat> {
+    let ext = filename.rsplit('.').next().unwrap_or("").to_lowercase();
+    match ext.as_str() {
+        "png" => Some(ImageFormat::Png),
+        "jpg" | "jpeg" => Some(ImageFormat::Jpeg),
+        "gif" => Some(ImageFormat::Gif),
+        "webp" => Some(ImageFormat::WebP),
+        _ => None,
+    }
+}
+
+/// Whether a thumbnail can be made for an anga of this name: PNG, JPEG,
+/// GIF and WebP images.
+pub fn has_thumbnail_type(filename: &str) -> bool {
+    image_format(filename).is_some()
+}
+
+/// Renders a WebP thumbnail of an image, shrunk to fit within
+/// `THUMBNAIL_SIZE`. Returns `None` for other files.
+pub fn render_thumbnail(filename: &str, content: &[u8]) -> io::Result<Option<Vec<u8>>> {
+    let Some(format) = image_format(filename) else {
+        return Ok(None);
+    };
+    let image = image::load_from_memory_with_format(content, format).map_err(invalid)?;
+
+    let image = if image.width() > THUMBNAIL_SIZE || image.height() > THUMBNAIL_SIZE {
+        image.thumbnail(THUMBNAIL_SIZE, THUMBNAIL_SIZE)
+    } else {
+        image
+    };
+    // The WebP encoder only takes 8-bit RGB and RGBA
+    let image = if image.color().has_alpha() {
+        DynamicImage::ImageRgba8(image.to_rgba8())
+    } else {
+        DynamicImage::ImageRgb8(image.to_rgb8())
+    };
+
+    let mut out = Vec::new();
+    image
+        .write_to(&mut Cursor::new(&mut out), ImageFormat::WebP)
+        .map_err(invalid)?;
+    Ok(Some(out))
+}
+
+/// Whether `path` exists and is no older than `modified`.
+fn is_fresh(path: &Path, modified: Option<SystemTime>) -> bool {
+    fs::metadata(path)
+        .and_then(|m| m.modified())
+        .is_ok_and(|t| modified.is_none_or(|m| t >= m))
+}
+
+/// Makes the thumbnail for `filename` in `anga_dir` unless the one under
+/// `cache_dir` is already newer than the anga. Returns the thumbnail's
+/// path, or `None` when the anga has no thumbnail. Anga that can't be
+/// read are noted with `NO_THUMBNAIL_FILENAME` and aren't tried again
+/// until they change.
+pub fn ensure_thumbnail(
+    anga_dir: &Path,
+    cache_dir: &Path,
+    filename: &str,
+) -> io::Result<Option<PathBuf>> {
+    if !has_thumbnail_type(filename) {
+        return Ok(None);
+    }
+    let source = anga_dir.join(filename);
+    let source_meta = fs::metadata(&source)?;
+    let modified = source_meta.modified().ok();
+    let dir = cache_dir.join(filename);
+    let path = dir.join(THUMBNAIL_FILENAME);
+    let none_path = dir.join(NO_THUMBNAIL_FILENAME);
+    if is_fresh(&path, modified) {
+        return Ok(Some(path));
+    }
+    if source_meta.len() > MAX_SOURCE_BYTES || is_fresh(&none_path, modified) {
+        return Ok(None);
+    }
+
+    match render_thumbnail(filename, &fs::read(&source)?) {
+        Ok(Some(thumbnail)) => {
+            fs::create_dir_all(&dir)?;
+            write_atomically(&path, &thumbnail)?;
+            if let Err(e) = fs::remove_file(&none_path) {
+                if e.kind() != io::ErrorKind::NotFound {
+                    return Err(e);
+                }
+            }
+            Ok(Some(path))
+        }
+        Ok(None) => {
+            fs::create_dir_all(&dir)?;
+            fs::write(&none_path, b"")?;
+            Ok(None)
+        }
+        Err(e) => {
+            fs::create_dir_all(&dir)?;
+            fs::write(&none_path, b"")?;
+            Err(e)
+        }
+    }
+}
+
+/// Makes thumbnails for every image in `anga_dir` that doesn't
+/// have an up-to-date one or an up-to-date `NO_THUMBNAIL_FILENAME`, and
+/// returns how many were written. An anga that fails is skipped. Stops
+/// between anga once `stopped` says so.
+pub fn generate_missing(
+    anga_dir: &Path,
+    cache_dir: &Path,
+    stopped: impl Fn() -> bool,
+) -> io::Result<usize> {
+    if !anga_dir.exists() {
+        return Ok(0);
+    }
+
+    let mut written = 0;
+    for entry in fs::read_dir(anga_dir)?.filter_map(|e| e.ok()) {
+        if stopped() {
+            break;
+        }
+        let Some(filename) = entry.file_name().to_str().map(str::to_string) else {
+            continue;
+        };
+        if filename.starts_with('.') || !has_thumbnail_type(&filename) {
+            continue;
+        }
+        let Ok(modified) = entry.metadata().and_then(|m| m.modified()) else {
+            continue;
+        };
+        let dir = cache_dir.join(&filename);
+        let done = [THUMBNAIL_FILENAME, NO_THUMBNAIL_FILENAME]
+            .iter()
+            .any(|name| is_fresh(&dir.join(name), Some(modified)));
+        if !done
+            && matches!(
+                ensure_thumbnail(anga_dir, cache_dir, &filename),
+                Ok(Some(_))
+            )
+        {
+            written += 1;
+        }
+    }
+    Ok(written)
+}
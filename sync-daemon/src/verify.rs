@@ -0,0 +1,101 @@
+use crate::anga::parse_filename_timestamp;
+use crate::bookmarks::read_shortcut;
+use crate::meta::{hidden_anga, read_meta_dir};
+use std::collections::HashSet;
+use std::fs;
+use std::io;
+use std::path::Path;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Severity {
+    /// Worth knowing about, but nothing is broken.
+    Warning,
+    /// The file can't be read back by the daemon or other Kaya clients.
+    Error,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Problem {
+    pub severity: Severity,
+    /// Path relative to the `~/.kaya` directory.
+    pub path: String,
+    pub message: String,
+}
+
+impl Problem {
+    fn new(severity: Severity, path: String, message: impl Into<String>) -> Self {
+        Problem {
+            severity,
+            path,
+            message: message.into(),
+        }
+    }
+}
+
+/// Checks `anga/` and `meta/` for files other Kaya clients would trip over:
+/// bookmarks that don't parse, invalid meta, meta for anga that don't exist,
+/// anga named outside ADR 0001 and downloads that never finished.
+pub fn verify_kaya_dir(kaya_dir: &Path) -> io::Result<Vec<Problem>> {
+    let anga_dir = kaya_dir.join("anga");
+    let meta_dir = kaya_dir.join("meta");
+    let mut problems = Vec::new();
+    let mut anga = HashSet::new();
+
+    if anga_dir.exists() {
+        let mut names: Vec<String> = fs::read_dir(&anga_dir)?
+            .filter_map(|e| e.ok())
+            .filter(|e| e.path().is_file())
+            .filter_map(|e| e.file_name().into_string().ok())
+            .collect();
+        names.sort();
+
+        for name in names {
+            let path = format!("anga/{}", name);
+            if name.starts_with('.') {
+                if name.ends_with(".part") {
+                    problems.push(Problem::new(
+                        Severity::Warning,
+                        path,
+                        "unfinished download, removed on the next start",
+                    ));
+                }
+                continue;
+            }
+
+            if parse_filename_timestamp(&name).is_none() {
+                problems.push(Problem::new(
+                    Severity::Warning,
+                    path.clone(),
+                    "name doesn't start with a timestamp",
+                ));
+            }
+            if name.ends_with(".url") {
+                if let Err(e) = read_shortcut(&anga_dir.join(&name)) {
+                    problems.push(Problem::new(Severity::Error, path, e.to_string()));
+                }
+            }
+            anga.insert(name);
+        }
+    }
+
+    // Purged anga are gone on purpose, so their meta isn't orphaned
+    let hidden = hidden_anga(&meta_dir)?;
+    for (filename, meta) in read_meta_dir(&meta_dir)? {
+        let path = format!("meta/{}", filename);
+        match meta {
+            Ok(meta)
+                if !anga.contains(&meta.anga.filename) && !hidden.contains(&meta.anga.filename) =>
+            {
+                problems.push(Problem::new(
+                    Severity::Warning,
+                    path,
+                    format!("refers to missing anga {}", meta.anga.filename),
+                ))
+            }
+            Ok(_) => {}
+            Err(e) => problems.push(Problem::new(Severity::Error, path, e.to_string())),
+        }
+    }
+
+    Ok(problems)
+}
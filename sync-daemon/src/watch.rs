@@ -0,0 +1,101 @@
+use crate::anga::is_plain_filename;
+use std::collections::HashMap;
+use std::path::{Component, Path, PathBuf};
+use std::time::{Duration, Instant};
+
+/// How long a file has to stay quiet before it's uploaded, so a file being
+/// written in several chunks is only sent once.
+pub const DEFAULT_QUIET_PERIOD: Duration = Duration::from_secs(2);
+
+/// A file under `~/.kaya` that sync cares about.
+#[derive(Debug, Clone, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub enum Change {
+    Anga(String),
+    Meta(String),
+    Cache { anga: String, filename: String },
+}
+
+impl Change {
+    /// Works out which synced file `path` is. Returns `None` for anything
+    /// sync ignores: dotfiles, editor temp files, non-TOML meta, nested
+    /// directories and paths outside `kaya_dir`.
+    pub fn classify(kaya_dir: &Path, path: &Path) -> Option<Change> {
+        let relative = path.strip_prefix(kaya_dir).ok()?;
+        let parts: Vec<&str> = relative
+            .components()
+            .map(|c| match c {
+                Component::Normal(s) => s.to_str(),
+                _ => None,
+            })
+            .collect::<Option<_>>()?;
+
+        let synced =
+            |name: &str| is_plain_filename(name) && !name.ends_with('~') && !name.ends_with(".tmp");
+
+        match parts.as_slice() {
+            ["anga", name] if synced(name) => Some(Change::Anga(name.to_string())),
+            ["meta", name] if synced(name) && name.ends_with(".toml") => {
+                Some(Change::Meta(name.to_string()))
+            }
+            ["cache", anga, name] if synced(anga) && synced(name) => Some(Change::Cache {
+                anga: anga.to_string(),
+                filename: name.to_string(),
+            }),
+            _ => None,
+        }
+    }
+
+    pub fn path(&self, kaya_dir: &Path) -> PathBuf {
+        match self {
+            Change::Anga(name) => kaya_dir.join("anga").join(name),
+            Change::Meta(name) => kaya_dir.join("meta").join(name),
+            Change::Cache { anga, filename } => kaya_dir.join("cache").join(anga).join(filename),
+        }
+    }
+}
+
+/// Collects changes and hands each one back once it has been quiet for the
+/// quiet period. Repeated events for the same file push its deadline back.
+#[derive(Debug)]
+pub struct Debouncer {
+    quiet: Duration,
+    pending: HashMap<Change, Instant>,
+}
+
+impl Debouncer {
+    pub fn new(quiet: Duration) -> Self {
+        Debouncer {
+            quiet,
+            pending: HashMap::new(),
+        }
+    }
+
+    pub fn record(&mut self, change: Change, at: Instant) {
+        self.pending.insert(change, at);
+    }
+
+    /// Removes and returns the changes that have been quiet long enough,
+    /// in a stable order.
+    pub fn take_ready(&mut self, now: Instant) -> Vec<Change> {
+        let mut ready: Vec<Change> = self
+            .pending
+            .iter()
+            .filter(|(_, at)| now.saturating_duration_since(**at) >= self.quiet)
+            .map(|(change, _)| change.clone())
+            .collect();
+        for change in &ready {
+            self.pending.remove(change);
+        }
+        ready.sort();
+        ready
+    }
+
+    /// When the next pending change becomes ready, if any are pending.
+    pub fn next_deadline(&self) -> Option<Instant> {
+        self.pending.values().min().map(|at| *at + self.quiet)
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.pending.is_empty()
+    }
+}
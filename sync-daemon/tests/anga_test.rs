@@ -0,0 +1,102 @@
+use chrono::{TimeZone, Utc};
+use savebutton_sync_daemon::anga::{
+    list_anga, meta_files_for, parse_date_bound, parse_filename_timestamp, read_anga, AngaKind,
+    ListQuery,
+};
+use std::fs;
+use std::path::Path;
+
+fn write_anga(dir: &Path, names: &[&str]) {
+    for name in names {
+        fs::write(dir.join(name), "[InternetShortcut]\nURL=https://x.com/\n").unwrap();
+    }
+}
+
+#[test]
+fn test_parse_filename_timestamp_handles_both_forms() {
+    assert_eq!(
+        parse_filename_timestamp("2026-01-27T171207-bookmark.url"),
+        Some(Utc.with_ymd_and_hms(2026, 1, 27, 17, 12, 7).unwrap())
+    );
+    let nanos = parse_filename_timestamp("2026-01-21T164145_354000000-note.md").unwrap();
+    assert_eq!(nanos.timestamp_subsec_nanos(), 354_000_000);
+    assert_eq!(parse_filename_timestamp("notes.md"), None);
+}
+
+#[test]
+fn test_list_anga_pages_newest_first_with_filters() {
+    let dir = tempfile::tempdir().unwrap();
+    write_anga(
+        dir.path(),
+        &[
+            "2026-01-01T000000-a.url",
+            "2026-01-02T000000-b.md",
+            "2026-01-03T000000-c.url",
+            "2026-01-04T000000-d.url",
+        ],
+    );
+
+    let page = list_anga(
+        dir.path(),
+        &ListQuery {
+            kind: Some(AngaKind::Bookmark),
+            limit: Some(2),
+            ..Default::default()
+        },
+    )
+    .unwrap();
+    let names: Vec<_> = page.items.iter().map(|e| e.filename.as_str()).collect();
+    assert_eq!(page.total, 3);
+    assert_eq!(
+        names,
+        ["2026-01-04T000000-d.url", "2026-01-03T000000-c.url"]
+    );
+
+    let page = list_anga(
+        dir.path(),
+        &ListQuery {
+            since: parse_date_bound("2026-01-02", false),
+            until: parse_date_bound("2026-01-03", true),
+            ..Default::default()
+        },
+    )
+    .unwrap();
+    let names: Vec<_> = page.items.iter().map(|e| e.filename.as_str()).collect();
+    assert_eq!(names, ["2026-01-03T000000-c.url", "2026-01-02T000000-b.md"]);
+}
+
+#[test]
+fn test_read_anga_returns_preview_for_large_binaries() {
+    let dir = tempfile::tempdir().unwrap();
+    fs::write(dir.path().join("2026-01-01T000000-big.png"), vec![0u8; 64]).unwrap();
+
+    let small = read_anga(dir.path(), "2026-01-01T000000-big.png", 1024).unwrap();
+    assert!(!small.preview);
+    assert!(small.base64.is_some());
+
+    let large = read_anga(dir.path(), "2026-01-01T000000-big.png", 16).unwrap();
+    assert!(large.preview);
+    assert_eq!(large.base64.as_deref(), Some("AAAAAAAAAAAAAAAAAAAAAA=="));
+    assert_eq!(large.entry.size, 64);
+
+    assert!(read_anga(dir.path(), "../.config", 1024).is_err());
+}
+
+#[test]
+fn test_meta_files_for_matches_anga_filename() {
+    let dir = tempfile::tempdir().unwrap();
+    fs::write(
+        dir.path().join("2026-01-02T000000-note.toml"),
+        "[anga]\nfilename = \"2026-01-01T000000-a.url\"\n\n[meta]\nnote = 'hi'\n",
+    )
+    .unwrap();
+    fs::write(
+        dir.path().join("2026-01-03T000000-note.toml"),
+        "[anga]\nfilename = \"2026-01-01T000000-other.url\"\n",
+    )
+    .unwrap();
+
+    let found = meta_files_for(dir.path(), "2026-01-01T000000-a.url").unwrap();
+    assert_eq!(found.len(), 1);
+    assert_eq!(found[0].filename, "2026-01-02T000000-note.toml");
+}
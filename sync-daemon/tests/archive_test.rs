@@ -0,0 +1,212 @@
+use savebutton_sync_daemon::archive::{
+    asset_filename, asset_references, build_snapshot, css_references, has_snapshot,
+    link_references, rewrite_css, write_snapshot, ArchiveOptions, SNAPSHOT_FILENAME,
+};
+use std::collections::HashMap;
+use std::fs;
+use url::Url;
+
+const PAGE: &str = r##"<!DOCTYPE html>
+<html><head>
+<title>Example</title>
+<link rel="stylesheet" href="/style.css?v=1&amp;x=2">
+<link rel="canonical" href="https://example.com/post">
+<link rel="icon" href=favicon.ico>
+<!-- <img src="commented.png"> -->
+<script src="https://cdn.example.net/app.js"></script>
+</head><body>
+<img alt="logo" src='img/logo.png'>
+<img src="data:image/png;base64,AAAA">
+<a href="../about">About</a> <a href="#top">Top</a> <a href="mailto:me@example.com">Mail</a>
+</body></html>"##;
+
+fn page_url() -> Url {
+    Url::parse("https://example.com/blog/post").unwrap()
+}
+
+#[test]
+fn test_finds_assets_and_links_resolved_against_the_page() {
+    let assets: Vec<String> = asset_references(PAGE, &page_url())
+        .into_iter()
+        .map(|r| r.url.to_string())
+        .collect();
+    assert_eq!(
+        assets,
+        vec![
+            "https://example.com/style.css?v=1&x=2",
+            "https://example.com/blog/favicon.ico",
+            "https://cdn.example.net/app.js",
+            "https://example.com/blog/img/logo.png",
+        ]
+    );
+
+    let links: Vec<String> = link_references(PAGE, &page_url())
+        .into_iter()
+        .map(|r| r.url.to_string())
+        .collect();
+    assert_eq!(links, vec!["https://example.com/about"]);
+}
+
+#[test]
+fn test_references_follow_the_pages_base() {
+    let html = r#"<head><base href="https://static.example.org/v2/"></head><img src="a.png">"#;
+    let refs = asset_references(html, &page_url());
+    assert_eq!(refs[0].url.as_str(), "https://static.example.org/v2/a.png");
+}
+
+#[test]
+fn test_snapshot_points_at_stored_assets_and_the_live_site() {
+    let html = r#"<head><base href="/static/"></head><img src="a.png"><img src="b.png"><a href="x?a=1&amp;b=2">x</a>"#;
+    let mut stored = HashMap::new();
+    stored.insert(
+        Url::parse("https://example.com/static/a.png").unwrap(),
+        "asset-001.png".to_string(),
+    );
+
+    let snapshot = build_snapshot(html, &page_url(), &stored);
+    assert_eq!(
+        snapshot,
+        r#"<head><base href=""></head><img src="asset-001.png"><img src="https://example.com/static/b.png"><a href="https://example.com/static/x?a=1&amp;b=2">x</a>"#
+    );
+}
+
+#[test]
+fn test_srcset_and_css_urls_are_assets() {
+    let html = r#"<style>
+/* url(commented.png) */
+@import "print.css";
+body { background: url( 'bg.png' ) }
+@font-face { src: url(fonts/a.woff2) format("woff2"), url(data:font/woff;base64,AA) }
+</style>
+<img srcset="small.jpg 1x, large.jpg?a=1&amp;b=2 2x,tiny.jpg">
+<picture><source srcset="w.webp 640w"></picture>
+<div style="background-image: url(&quot;hero.jpg&quot;)"></div>
+<script>var s = "<div style='background:url(script.png)'>";</script>"#;
+
+    let assets: Vec<String> = asset_references(html, &page_url())
+        .into_iter()
+        .map(|r| r.url.to_string())
+        .collect();
+    assert_eq!(
+        assets,
+        vec![
+            "https://example.com/blog/print.css",
+            "https://example.com/blog/bg.png",
+            "https://example.com/blog/fonts/a.woff2",
+            "https://example.com/blog/small.jpg",
+            "https://example.com/blog/large.jpg?a=1&b=2",
+            "https://example.com/blog/tiny.jpg",
+            "https://example.com/blog/w.webp",
+            "https://example.com/blog/hero.jpg",
+        ]
+    );
+}
+
+#[test]
+fn test_snapshot_rewrites_srcset_and_css_urls() {
+    let html = r#"<style>p { background: url("bg.png") }</style><img srcset="a.jpg 1x, b,c.jpg 2x"><p style="background:url(&quot;bg.png&quot;)">"#;
+    let mut stored = HashMap::new();
+    stored.insert(
+        Url::parse("https://example.com/blog/a.jpg").unwrap(),
+        "asset-001.jpg".to_string(),
+    );
+    stored.insert(
+        Url::parse("https://example.com/blog/bg.png").unwrap(),
+        "asset-002.png".to_string(),
+    );
+
+    let snapshot = build_snapshot(html, &page_url(), &stored);
+    assert_eq!(
+        snapshot,
+        r#"<style>p { background: url("asset-002.png") }</style><img srcset="asset-001.jpg 1x, https://example.com/blog/b%2Cc.jpg 2x"><p style="background:url(asset-002.png)">"#
+    );
+}
+
+#[test]
+fn test_stored_stylesheets_point_at_stored_fonts_and_the_live_site() {
+    let css_url = Url::parse("https://cdn.example.net/css/site.css").unwrap();
+    let css =
+        r#"@font-face { src: url(../fonts/a.woff2) } .x { background: url('img/x (1).png') }"#;
+    let refs: Vec<String> = css_references(css, &css_url)
+        .into_iter()
+        .map(|r| r.url.to_string())
+        .collect();
+    assert_eq!(
+        refs,
+        vec![
+            "https://cdn.example.net/fonts/a.woff2",
+            "https://cdn.example.net/css/img/x%20(1).png",
+        ]
+    );
+
+    let mut stored = HashMap::new();
+    stored.insert(
+        Url::parse("https://cdn.example.net/fonts/a.woff2").unwrap(),
+        "asset-002.woff2".to_string(),
+    );
+    assert_eq!(
+        rewrite_css(css, &css_url, &stored),
+        r#"@font-face { src: url(asset-002.woff2) } .x { background: url('https://cdn.example.net/css/img/x%20%281%29.png') }"#
+    );
+}
+
+#[test]
+fn test_asset_filenames_keep_a_sensible_extension() {
+    let url = |u: &str| Url::parse(u).unwrap();
+    assert_eq!(
+        asset_filename(1, &url("https://example.com/a/logo.PNG"), None),
+        "asset-001.png"
+    );
+    assert_eq!(
+        asset_filename(
+            12,
+            &url("https://example.com/css?family=Sans"),
+            Some("text/css; charset=utf-8")
+        ),
+        "asset-012.css"
+    );
+    assert_eq!(
+        asset_filename(3, &url("https://example.com/pixel"), None),
+        "asset-003"
+    );
+}
+
+#[test]
+fn test_write_snapshot_stores_page_and_assets_under_the_bookmark() {
+    let dir = tempfile::tempdir().unwrap();
+    let bookmark = "2026-01-28T205208-example.url";
+
+    assert!(!has_snapshot(dir.path(), bookmark));
+    let path = write_snapshot(
+        dir.path(),
+        bookmark,
+        "<p>hello</p>",
+        &[("asset-001.png".to_string(), vec![1, 2, 3])],
+    )
+    .unwrap();
+
+    assert_eq!(path, dir.path().join(bookmark).join(SNAPSHOT_FILENAME));
+    assert!(has_snapshot(dir.path(), bookmark));
+    assert_eq!(fs::read_to_string(&path).unwrap(), "<p>hello</p>");
+    assert_eq!(
+        fs::read(dir.path().join(bookmark).join("asset-001.png")).unwrap(),
+        vec![1, 2, 3]
+    );
+
+    assert!(write_snapshot(dir.path(), "../escape", "", &[]).is_err());
+    assert!(write_snapshot(
+        dir.path(),
+        bookmark,
+        "",
+        &[(SNAPSHOT_FILENAME.to_string(), Vec::new())]
+    )
+    .is_err());
+}
+
+#[test]
+fn test_archive_options_default_to_off() {
+    let opts: ArchiveOptions = toml::from_str("fetch = false").unwrap();
+    assert!(!opts.enabled);
+    assert!(!opts.fetch);
+    assert!(opts.store_assets);
+}
@@ -0,0 +1,50 @@
+use savebutton_sync_daemon::caller::detect_caller;
+use savebutton_sync_daemon::manifest::BrowserFamily;
+
+fn args(args: &[&str]) -> Vec<String> {
+    args.iter().map(|a| a.to_string()).collect()
+}
+
+#[test]
+fn test_detects_firefox_family_from_manifest_path_and_extension_id() {
+    let launch = args(&[
+        "/home/me/.mozilla/native-messaging-hosts/org.savebutton.nativehost.json",
+        "org.savebutton@savebutton.org",
+    ]);
+
+    let caller = detect_caller(&launch, Some("firefox-bin")).unwrap();
+    assert_eq!(caller.family, BrowserFamily::Firefox);
+    assert_eq!(caller.extension, "org.savebutton@savebutton.org");
+    assert_eq!(caller.browser, "firefox");
+
+    let caller = detect_caller(&launch, Some("librewolf")).unwrap();
+    assert_eq!(caller.browser, "librewolf");
+}
+
+#[test]
+fn test_detects_chromium_family_from_origin() {
+    let origin = "chrome-extension://abcdefghijklmnopabcdefghijklmnop/";
+    let launch = args(&[origin, "--parent-window=0"]);
+
+    let caller = detect_caller(&launch, Some("brave")).unwrap();
+    assert_eq!(caller.family, BrowserFamily::Chromium);
+    assert_eq!(caller.extension, origin);
+    assert_eq!(caller.browser, "brave");
+
+    assert_eq!(
+        detect_caller(&launch, Some("msedge")).unwrap().browser,
+        "edge"
+    );
+    // A parent that doesn't say which browser it is
+    assert_eq!(
+        detect_caller(&launch, Some("sh")).unwrap().browser,
+        "chromium"
+    );
+    assert_eq!(detect_caller(&launch, None).unwrap().browser, "chromium");
+}
+
+#[test]
+fn test_no_caller_without_browser_arguments() {
+    assert!(detect_caller(&[], Some("firefox")).is_none());
+    assert!(detect_caller(&args(&["--daemon"]), None).is_none());
+}
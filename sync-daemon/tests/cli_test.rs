@@ -0,0 +1,127 @@
+use savebutton_sync_daemon::anga::AngaKind;
+use savebutton_sync_daemon::cli::{parse_args, Command};
+use std::path::PathBuf;
+
+fn parse(args: &[&str]) -> Result<Command, String> {
+    let args: Vec<String> = args.iter().map(|a| a.to_string()).collect();
+    parse_args(&args)
+}
+
+#[test]
+fn test_browser_launches_start_the_native_host() {
+    assert_eq!(parse(&[]), Ok(Command::NativeHost));
+    assert_eq!(
+        parse(&[
+            "/home/me/.mozilla/native-messaging-hosts/org.savebutton.nativehost.json",
+            "org.savebutton@savebutton.org",
+        ]),
+        Ok(Command::NativeHost)
+    );
+    assert_eq!(
+        parse(&["chrome-extension://abcdefghijklmnop/"]),
+        Ok(Command::NativeHost)
+    );
+    assert_eq!(parse(&["--daemon"]), Ok(Command::Daemon));
+}
+
+#[test]
+fn test_parses_subcommands_and_options() {
+    assert_eq!(
+        parse(&["add-url", "https://example.com/", "--title", "Example"]),
+        Ok(Command::AddUrl {
+            url: "https://example.com/".to_string(),
+            title: Some("Example".to_string()),
+            archive: false,
+        })
+    );
+    assert_eq!(
+        parse(&["add-url", "--archive", "https://example.com/"]),
+        Ok(Command::AddUrl {
+            url: "https://example.com/".to_string(),
+            title: None,
+            archive: true,
+        })
+    );
+    assert_eq!(
+        parse(&["list", "--kind=bookmark", "--limit", "5"]),
+        Ok(Command::List {
+            kind: Some(AngaKind::Bookmark),
+            since: None,
+            until: None,
+            limit: Some(5),
+        })
+    );
+    assert_eq!(
+        parse(&["search", "rust", "async", "--tag", "a", "--tag", "b"]),
+        Ok(Command::Search {
+            query: "rust async".to_string(),
+            tags: vec!["a".to_string(), "b".to_string()],
+            limit: None,
+        })
+    );
+    assert_eq!(
+        parse(&["find-in-pdfs", "net", "income", "--limit=3"]),
+        Ok(Command::FindInPdfs {
+            phrase: "net income".to_string(),
+            limit: Some(3),
+        })
+    );
+    assert_eq!(
+        parse(&["import-bookmarks", "bookmarks.html", "--dry-run"]),
+        Ok(Command::ImportBookmarks {
+            path: PathBuf::from("bookmarks.html"),
+            dry_run: true
+        })
+    );
+    assert_eq!(
+        parse(&["import-places", "profile", "--history=10"]),
+        Ok(Command::ImportPlaces {
+            path: PathBuf::from("profile"),
+            history_min_visits: Some(10),
+            dry_run: false
+        })
+    );
+    assert_eq!(
+        parse(&["dead-links", "--check"]),
+        Ok(Command::DeadLinks { check: true })
+    );
+    assert_eq!(
+        parse(&["config", "set", "password"]),
+        Ok(Command::ConfigSet {
+            key: "password".to_string(),
+            value: None,
+        })
+    );
+    assert_eq!(parse(&["add-note"]), Ok(Command::AddNote { text: None }));
+    assert_eq!(
+        parse(&[
+            "install-host",
+            "--check",
+            "--chromium-origin",
+            "chrome-extension://abcdefghijklmnopabcdefghijklmnop/",
+        ]),
+        Ok(Command::InstallHost {
+            check: true,
+            chromium_origins: vec![
+                "chrome-extension://abcdefghijklmnopabcdefghijklmnop/".to_string()
+            ],
+        })
+    );
+    assert_eq!(parse(&["verify", "--help"]), Ok(Command::Help));
+}
+
+#[test]
+fn test_rejects_bad_arguments() {
+    assert!(parse(&["lsit"]).is_err());
+    assert!(parse(&["add-url"]).is_err());
+    assert!(parse(&["list", "--kind", "video"]).is_err());
+    assert!(parse(&["list", "--limit", "lots"]).is_err());
+    assert!(parse(&["sync", "--force"]).is_err());
+    assert!(parse(&["config", "set", "colour", "red"]).is_err());
+    assert!(parse(&["search"]).is_err());
+    assert!(parse(&["find-in-pdfs"]).is_err());
+    assert!(parse(&["dead-links", "now"]).is_err());
+    assert!(parse(&["import-bookmarks"]).is_err());
+    assert!(parse(&["import-places", "profile", "--history", "0"]).is_err());
+    assert!(parse(&["install-host", "--chromium-origin", "abcdef"]).is_err());
+}
@@ -0,0 +1,46 @@
+use savebutton_sync_daemon::html::{collapse_whitespace, decode_entities, tokenize, Token};
+
+#[test]
+fn test_tokenize_reads_tags_attributes_and_text() {
+    let tokens = tokenize(
+        "<!DOCTYPE html><P Class=lead id='x' hidden>Fish &amp; chips<br/></p><!-- gone --> 1 < 2",
+    );
+    assert_eq!(
+        tokens,
+        vec![
+            Token::Start {
+                name: "p".to_string(),
+                attributes: vec![
+                    ("class".to_string(), "lead".to_string()),
+                    ("id".to_string(), "x".to_string()),
+                    ("hidden".to_string(), String::new()),
+                ],
+            },
+            Token::Text("Fish &amp; chips"),
+            Token::Start {
+                name: "br".to_string(),
+                attributes: Vec::new(),
+            },
+            Token::End("p".to_string()),
+            Token::Text(" 1 < 2"),
+        ]
+    );
+    assert_eq!(tokens[0].attribute("class"), Some("lead"));
+}
+
+#[test]
+fn test_script_contents_are_not_markup() {
+    let tokens = tokenize("<script>if (a <b) { x = '</p>'; }</SCRIPT><p>after</p>");
+    assert_eq!(tokens[1], Token::Text("if (a <b) { x = '</p>'; }"));
+    assert_eq!(tokens[2], Token::End("script".to_string()));
+    assert_eq!(tokens[4], Token::Text("after"));
+}
+
+#[test]
+fn test_decode_entities() {
+    assert_eq!(
+        decode_entities("Tom &amp; Jerry &#8212; &#x41;&rsquo;s &bogus; & more"),
+        "Tom & Jerry — A’s &bogus; & more"
+    );
+    assert_eq!(collapse_whitespace(" a\u{a0}\n b  "), "a b");
+}
@@ -0,0 +1,177 @@
+use chrono::{TimeZone, Utc};
+use savebutton_sync_daemon::bookmarks::{read_shortcut, BookmarkIndex};
+use savebutton_sync_daemon::import::{
+    import_bookmarks, parse_add_date, parse_netscape_bookmarks, ImportSummary, ImportedBookmark,
+};
+use savebutton_sync_daemon::meta::{read_meta_dir, resolve_meta};
+use savebutton_sync_daemon::normalize::NormalizeOptions;
+use std::fs;
+
+const FIREFOX: &str = r#"<!DOCTYPE NETSCAPE-Bookmark-file-1>
+<!-- This is an automatically generated file.
+     It will be read and overwritten.
+     DO NOT EDIT! -->
+<META HTTP-EQUIV="Content-Type" CONTENT="text/html; charset=UTF-8">
+<meta http-equiv="Content-Security-Policy" content="default-src 'self'; script-src 'none'; img-src data: *; object-src 'none'"></meta>
+<TITLE>Bookmarks</TITLE>
+<H1>Bookmarks Menu</H1>
+
+<DL><p>
+    <DT><H3 ADD_DATE="1600000000" LAST_MODIFIED="1600000000">Reading &amp; Writing</H3>
+    <DL><p>
+        <DT><A HREF="https://example.com/essay" ADD_DATE="1600000100" LAST_MODIFIED="1600000200" TAGS="essays,Longform">An   Essay &amp; More</A>
+        <DD>Read this
+again later.
+        <DT><H3 ADD_DATE="1600000000">Rust</H3>
+        <DL><p>
+            <DT><A HREF="https://doc.rust-lang.org/book/" ADD_DATE="1600000300" SHORTCUTURL="book">The Book</A>
+        </DL><p>
+    </DL><p>
+    <DT><A HREF="javascript:alert(1)">Bookmarklet</A>
+    <DT><H3 ADD_DATE="1600000000" PERSONAL_TOOLBAR_FOLDER="true">Bookmarks Toolbar</H3>
+    <DL><p>
+        <DT><A HREF="place:sort=8&maxResults=10">Most Visited</A>
+        <DT><A HREF="https://deobald.ca/" ADD_DATE="1600000400"></A>
+    </DL><p>
+</DL>
+"#;
+
+#[test]
+fn test_parses_firefox_export() {
+    let bookmarks = parse_netscape_bookmarks(FIREFOX);
+    let at = |secs| Some(Utc.timestamp_opt(secs, 0).unwrap());
+
+    assert_eq!(
+        bookmarks,
+        vec![
+            ImportedBookmark {
+                url: "https://example.com/essay".to_string(),
+                title: Some("An Essay & More".to_string()),
+                added: at(1600000100),
+                tags: vec![
+                    "essays".to_string(),
+                    "Longform".to_string(),
+                    "Reading & Writing".to_string()
+                ],
+                note: Some("Read this\nagain later.".to_string()),
+                keyword: None,
+            },
+            ImportedBookmark {
+                url: "https://doc.rust-lang.org/book/".to_string(),
+                title: Some("The Book".to_string()),
+                added: at(1600000300),
+                tags: vec!["Reading & Writing".to_string(), "Rust".to_string()],
+                keyword: Some("book".to_string()),
+                ..Default::default()
+            },
+            ImportedBookmark {
+                url: "javascript:alert(1)".to_string(),
+                title: Some("Bookmarklet".to_string()),
+                ..Default::default()
+            },
+            ImportedBookmark {
+                url: "place:sort=8&maxResults=10".to_string(),
+                title: Some("Most Visited".to_string()),
+                ..Default::default()
+            },
+            ImportedBookmark {
+                url: "https://deobald.ca/".to_string(),
+                added: at(1600000400),
+                ..Default::default()
+            },
+        ]
+    );
+}
+
+#[test]
+fn test_add_dates_in_any_unit() {
+    let expected = Utc.with_ymd_and_hms(2020, 9, 13, 12, 26, 40).single();
+    assert_eq!(parse_add_date("1600000000"), expected);
+    assert_eq!(parse_add_date("1600000000000"), expected);
+    assert_eq!(parse_add_date("1600000000000000"), expected);
+    assert_eq!(parse_add_date("0"), None);
+    assert_eq!(parse_add_date("soon"), None);
+}
+
+#[test]
+fn test_imports_new_bookmarks_with_their_meta() {
+    let kaya = tempfile::tempdir().unwrap();
+    let anga = kaya.path().join("anga");
+    let meta = kaya.path().join("meta");
+    fs::create_dir_all(&anga).unwrap();
+    fs::write(
+        anga.join("2026-01-01T000000-deobald-ca.url"),
+        "[InternetShortcut]\nURL=http://deobald.ca\n",
+    )
+    .unwrap();
+    let mut bookmarks = parse_netscape_bookmarks(FIREFOX);
+    bookmarks.push(ImportedBookmark {
+        url: "https://example.com/essay?utm_source=feed".to_string(),
+        ..Default::default()
+    });
+    let now = Utc.with_ymd_and_hms(2026, 10, 18, 0, 0, 0).unwrap();
+
+    let mut index = BookmarkIndex::load(&anga, NormalizeOptions::default()).unwrap();
+    let dry = import_bookmarks(&anga, &meta, &mut index, &bookmarks, now, true).unwrap();
+    let expected = ImportSummary {
+        imported: 2,
+        existing: 2,
+        unsupported: 2,
+    };
+    assert_eq!(dry, expected);
+    assert_eq!(fs::read_dir(&anga).unwrap().count(), 1);
+    assert!(!meta.exists());
+
+    let mut index = BookmarkIndex::load(&anga, NormalizeOptions::default()).unwrap();
+    let summary = import_bookmarks(&anga, &meta, &mut index, &bookmarks, now, false).unwrap();
+    assert_eq!(summary, expected);
+
+    let essay = "2020-09-13T122820-an-essay-more.url";
+    let shortcut = read_shortcut(&anga.join(essay)).unwrap();
+    assert_eq!(shortcut.url, "https://example.com/essay");
+    assert_eq!(shortcut.title.as_deref(), Some("An Essay & More"));
+    assert!(anga.join("2020-09-13T123140-the-book.url").is_file());
+
+    let metas = read_meta_dir(&meta).unwrap();
+    let names: Vec<&str> = metas.iter().map(|(n, _)| n.as_str()).collect();
+    assert_eq!(
+        names,
+        vec![
+            "2020-09-13T122820-import.toml",
+            "2020-09-13T123140-import.toml"
+        ]
+    );
+    let view = resolve_meta(&meta, essay).unwrap();
+    assert_eq!(view.tags, vec!["essays", "Longform", "Reading & Writing"]);
+    assert_eq!(view.note.as_deref(), Some("Read this\nagain later."));
+    let book = resolve_meta(&meta, "2020-09-13T123140-the-book.url").unwrap();
+    assert_eq!(book.keyword.as_deref(), Some("book"));
+
+    // Running it again finds everything already there
+    let mut index = BookmarkIndex::load(&anga, NormalizeOptions::default()).unwrap();
+    let again = import_bookmarks(&anga, &meta, &mut index, &bookmarks, now, false).unwrap();
+    assert_eq!(again.imported, 0);
+    assert_eq!(again.existing, 4);
+}
+
+#[test]
+fn test_undated_bookmarks_are_saved_now() {
+    let kaya = tempfile::tempdir().unwrap();
+    let anga = kaya.path().join("anga");
+    let meta = kaya.path().join("meta");
+    let now = Utc.with_ymd_and_hms(2026, 10, 18, 1, 2, 3).unwrap();
+    let bookmarks = vec![ImportedBookmark {
+        url: "https://example.org/page".to_string(),
+        ..Default::default()
+    }];
+
+    let mut index = BookmarkIndex::default();
+    import_bookmarks(&anga, &meta, &mut index, &bookmarks, now, false).unwrap();
+
+    assert!(anga.join("2026-10-18T010203-example-org.url").is_file());
+    assert_eq!(fs::read_dir(&meta).unwrap().count(), 0);
+    assert_eq!(
+        index.lookup("https://example.org/page"),
+        ["2026-10-18T010203-example-org.url".to_string()]
+    );
+}
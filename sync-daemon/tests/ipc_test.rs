@@ -0,0 +1,50 @@
+use savebutton_sync_daemon::ipc::{read_frame, write_frame};
+use std::io::Cursor;
+
+#[test]
+fn test_frames_round_trip() {
+    let mut buffer = Vec::new();
+    write_frame(&mut buffer, br#"{"message":"config_status"}"#).unwrap();
+    write_frame(&mut buffer, b"{}").unwrap();
+
+    let mut reader = Cursor::new(buffer);
+    assert_eq!(
+        read_frame(&mut reader).unwrap().unwrap(),
+        br#"{"message":"config_status"}"#
+    );
+    assert_eq!(read_frame(&mut reader).unwrap().unwrap(), b"{}");
+    assert!(read_frame(&mut reader).unwrap().is_none());
+}
+
+#[test]
+fn test_read_frame_rejects_empty_messages() {
+    let mut reader = Cursor::new(0u32.to_ne_bytes().to_vec());
+    assert!(read_frame(&mut reader).is_err());
+}
+
+#[cfg(unix)]
+#[test]
+fn test_only_one_daemon_listens() {
+    use interprocess::local_socket::traits::Listener as _;
+    use savebutton_sync_daemon::ipc::{connect, listen, socket_path};
+    use std::thread;
+
+    let dir = tempfile::tempdir().unwrap();
+    // A socket file left behind by a crashed daemon is taken over
+    std::fs::write(socket_path(dir.path()), "").unwrap();
+
+    let listener = listen(dir.path()).unwrap().expect("first listener");
+    let server = thread::spawn(move || {
+        let mut stream = listener.accept().unwrap();
+        let request = read_frame(&mut stream).unwrap().unwrap();
+        write_frame(&mut stream, &request).unwrap();
+        listener
+    });
+
+    let mut client = connect(dir.path()).unwrap();
+    write_frame(&mut client, b"ping").unwrap();
+    assert_eq!(read_frame(&mut client).unwrap().unwrap(), b"ping");
+
+    let _listener = server.join().unwrap();
+    assert!(listen(dir.path()).unwrap().is_none());
+}
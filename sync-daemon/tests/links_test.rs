@@ -0,0 +1,189 @@
+use chrono::{Duration as TimeDelta, TimeZone, Utc};
+use savebutton_sync_daemon::links::{
+    dead_links, interleave_by_host, read_link_status, write_link_status, CheckResult,
+    LinkCheckOptions, LinkStatus, Pacer, Verdict, LINK_STATE_DIR,
+};
+use std::fs;
+use std::time::{Duration, Instant};
+use url::Url;
+
+const URL: &str = "http://example.com/post";
+
+fn answered(status: u16) -> CheckResult {
+    CheckResult {
+        status: Some(status),
+        final_url: Some(URL.to_string()),
+        error: None,
+    }
+}
+
+fn unreachable() -> CheckResult {
+    CheckResult {
+        error: Some("dns error".to_string()),
+        ..Default::default()
+    }
+}
+
+#[test]
+fn test_verdicts() {
+    assert_eq!(answered(200).verdict(), Verdict::Alive);
+    assert_eq!(answered(304).verdict(), Verdict::Alive);
+    assert_eq!(answered(404).verdict(), Verdict::Failed);
+    assert_eq!(answered(503).verdict(), Verdict::Failed);
+    assert_eq!(answered(403).verdict(), Verdict::Unknown);
+    assert_eq!(answered(429).verdict(), Verdict::Unknown);
+    assert_eq!(unreachable().verdict(), Verdict::Failed);
+}
+
+#[test]
+fn test_failures_add_up_until_the_link_is_dead() {
+    let opts = LinkCheckOptions::default();
+    let t0 = Utc.with_ymd_and_hms(2026, 1, 1, 0, 0, 0).unwrap();
+    let day = TimeDelta::days(1);
+
+    let alive = LinkStatus::record(None, URL, answered(200), t0);
+    assert_eq!(alive.alive_at, Some(t0));
+    assert_eq!(alive.redirected_to, None);
+
+    let once = LinkStatus::record(Some(&alive), URL, answered(404), t0 + day);
+    let blocked = LinkStatus::record(Some(&once), URL, answered(403), t0 + day * 2);
+    let twice = LinkStatus::record(Some(&blocked), URL, unreachable(), t0 + day * 3);
+    assert_eq!(blocked.failures, 1);
+    assert!(!twice.is_dead(&opts));
+
+    let dead = LinkStatus::record(Some(&twice), URL, answered(410), t0 + day * 4);
+    assert_eq!(dead.failures, 3);
+    assert!(dead.is_dead(&opts));
+    assert_eq!(dead.alive_at, Some(t0));
+    assert_eq!(dead.status, Some(410));
+
+    let back = LinkStatus::record(Some(&dead), URL, answered(200), t0 + day * 5);
+    assert_eq!(back.failures, 0);
+    assert_eq!(back.alive_at, Some(t0 + day * 5));
+
+    // Pointing the bookmark elsewhere starts over
+    let moved = LinkStatus::record(Some(&dead), "https://example.org/", unreachable(), t0);
+    assert_eq!(moved.failures, 1);
+    assert_eq!(moved.alive_at, None);
+}
+
+#[test]
+fn test_redirects_and_due_dates() {
+    let opts = LinkCheckOptions::default();
+    let t0 = Utc.with_ymd_and_hms(2026, 1, 1, 0, 0, 0).unwrap();
+
+    let redirected = LinkStatus::record(
+        None,
+        URL,
+        CheckResult {
+            status: Some(200),
+            final_url: Some("https://example.com/post/".to_string()),
+            error: None,
+        },
+        t0,
+    );
+    assert_eq!(
+        redirected.redirected_to.as_deref(),
+        Some("https://example.com/post/")
+    );
+    assert!(!redirected.is_due(&opts, t0 + TimeDelta::days(6)));
+    assert!(redirected.is_due(&opts, t0 + TimeDelta::days(7)));
+
+    let failed = LinkStatus::record(None, URL, unreachable(), t0);
+    assert!(!failed.is_due(&opts, t0 + TimeDelta::hours(23)));
+    assert!(failed.is_due(&opts, t0 + TimeDelta::hours(24)));
+}
+
+#[test]
+fn test_dead_links_come_from_local_state() {
+    let kaya = tempfile::tempdir().unwrap();
+    let anga = kaya.path().join("anga");
+    let state = kaya.path().join(LINK_STATE_DIR);
+    fs::create_dir_all(&anga).unwrap();
+    let opts = LinkCheckOptions {
+        dead_after: 1,
+        ..Default::default()
+    };
+    let t0 = Utc.with_ymd_and_hms(2026, 1, 1, 0, 0, 0).unwrap();
+
+    let save = |name: &str, result: CheckResult, anga_exists: bool| {
+        if anga_exists {
+            fs::write(
+                anga.join(name),
+                "[InternetShortcut]\nURL=http://example.com/post\n",
+            )
+            .unwrap();
+        }
+        let status = LinkStatus::record(None, URL, result, t0);
+        write_link_status(&state, name, &status).unwrap();
+        status
+    };
+    let older = save("2025-01-01T000000-old.url", answered(404), true);
+    save("2025-06-01T000000-fine.url", answered(200), true);
+    save("2025-07-01T000000-deleted.url", answered(404), false);
+    let newer = save("2026-01-01T000000-new.url", unreachable(), true);
+
+    assert!(state.join("2025-01-01T000000-old.url.toml").is_file());
+    assert!(!kaya.path().join("cache").exists());
+    assert_eq!(
+        read_link_status(&state, "2025-01-01T000000-old.url").unwrap(),
+        Some(older.clone())
+    );
+
+    let dead = dead_links(&anga, &state, &opts).unwrap();
+    let found: Vec<_> = dead
+        .iter()
+        .map(|d| (d.filename.as_str(), &d.status))
+        .collect();
+    assert_eq!(
+        found,
+        vec![
+            ("2026-01-01T000000-new.url", &newer),
+            ("2025-01-01T000000-old.url", &older),
+        ]
+    );
+}
+
+#[test]
+fn test_links_take_turns_by_host() {
+    let link = |n: u32, u: &str| (n, Url::parse(u).unwrap());
+    let order: Vec<u32> = interleave_by_host(vec![
+        link(1, "https://a.example/1"),
+        link(2, "https://a.example/2"),
+        link(3, "https://a.example/3"),
+        link(4, "https://b.example/1"),
+        link(5, "https://c.example/1"),
+        link(6, "https://b.example/2"),
+    ])
+    .into_iter()
+    .map(|(n, _)| n)
+    .collect();
+    assert_eq!(order, vec![1, 4, 5, 2, 6, 3]);
+}
+
+#[test]
+fn test_pacer_spaces_out_requests() {
+    let opts = LinkCheckOptions {
+        requests_per_minute: 60,
+        host_delay_secs: 5,
+        ..Default::default()
+    };
+    let mut pacer = Pacer::new(&opts);
+    let t0 = Instant::now();
+
+    assert_eq!(pacer.reserve("a", t0), t0);
+    assert_eq!(pacer.reserve("b", t0), t0 + Duration::from_secs(1));
+    assert_eq!(pacer.reserve("a", t0), t0 + Duration::from_secs(5));
+    assert_eq!(
+        pacer.reserve("c", t0 + Duration::from_secs(30)),
+        t0 + Duration::from_secs(30)
+    );
+}
+
+#[test]
+fn test_link_options_defaults() {
+    let opts: LinkCheckOptions = toml::from_str("dead_after = 5").unwrap();
+    assert!(opts.enabled);
+    assert_eq!(opts.dead_after, 5);
+    assert_eq!(opts.interval_hours, 7 * 24);
+}
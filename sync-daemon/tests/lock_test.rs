@@ -0,0 +1,17 @@
+use savebutton_sync_daemon::lock::{lock_holder, SyncLock};
+
+#[test]
+fn test_only_one_holder_at_a_time() {
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path().join(".sync.lock");
+
+    let lock = SyncLock::try_acquire(&path)
+        .unwrap()
+        .expect("first acquire");
+    assert_eq!(lock_holder(&path), Some(std::process::id()));
+    assert!(SyncLock::try_acquire(&path).unwrap().is_none());
+
+    drop(lock);
+    assert_eq!(lock_holder(&path), None);
+    assert!(SyncLock::try_acquire(&path).unwrap().is_some());
+}
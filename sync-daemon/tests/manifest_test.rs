@@ -0,0 +1,103 @@
+use savebutton_sync_daemon::manifest::{
+    browser_targets, check_registration, remove_registration, BrowserFamily, HostManifest,
+    Registration,
+};
+use std::fs;
+use std::path::Path;
+
+#[test]
+fn test_firefox_manifest_lists_the_extension() {
+    let generated = HostManifest::firefox(Path::new("/usr/local/bin/savebutton-sync-daemon"));
+    let value: serde_json::Value = serde_json::from_str(&generated.to_json()).unwrap();
+
+    assert_eq!(value["name"], "org.savebutton.nativehost");
+    assert_eq!(value["path"], "/usr/local/bin/savebutton-sync-daemon");
+    assert_eq!(value["type"], "stdio");
+    assert_eq!(value["allowed_extensions"][0], "org.savebutton@savebutton.org");
+    assert!(value.get("allowed_origins").is_none());
+    assert_eq!(
+        serde_json::from_str::<HostManifest>(&generated.to_json()).unwrap(),
+        generated
+    );
+}
+
+#[test]
+fn test_check_registration_tells_current_stale_and_foreign_apart() {
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path().join("org.savebutton.nativehost.json");
+    let expected = HostManifest::firefox(Path::new("/opt/savebutton/savebutton-sync-daemon"));
+
+    assert_eq!(check_registration(&path, &expected), Registration::Missing);
+
+    let old = HostManifest::firefox(Path::new("/usr/local/bin/savebutton-sync-daemon"));
+    fs::write(&path, old.to_json()).unwrap();
+    assert_eq!(
+        check_registration(&path, &expected),
+        Registration::Stale(old)
+    );
+
+    fs::write(&path, expected.to_json()).unwrap();
+    assert_eq!(check_registration(&path, &expected), Registration::Current);
+
+    assert!(remove_registration(&path).unwrap());
+    assert!(!path.exists());
+
+    fs::write(&path, r#"{"name": "com.example.other"}"#).unwrap();
+    assert!(matches!(
+        check_registration(&path, &expected),
+        Registration::Foreign(_)
+    ));
+    assert!(!remove_registration(&path).unwrap());
+    assert!(path.exists());
+}
+
+#[test]
+fn test_chromium_manifest_uses_allowed_origins() {
+    let origins = vec!["chrome-extension://abcdefghijklmnopabcdefghijklmnop/".to_string()];
+    let json =
+        HostManifest::chromium(Path::new("/usr/bin/savebutton-sync-daemon"), &origins).to_json();
+    let value: serde_json::Value = serde_json::from_str(&json).unwrap();
+
+    assert_eq!(value["allowed_origins"][0], origins[0]);
+    assert!(value.get("allowed_extensions").is_none());
+}
+
+#[cfg(target_os = "linux")]
+#[test]
+fn test_browser_targets_only_include_browsers_that_have_been_run() {
+    let home = tempfile::tempdir().unwrap();
+    fs::create_dir_all(home.path().join(".config/BraveSoftware/Brave-Browser")).unwrap();
+    fs::create_dir_all(home.path().join("snap/firefox/common")).unwrap();
+
+    let present: Vec<_> = browser_targets(home.path())
+        .into_iter()
+        .filter(|t| t.is_present())
+        .map(|t| (t.name, t.family))
+        .collect();
+
+    assert_eq!(
+        present,
+        [
+            ("Firefox", BrowserFamily::Firefox),
+            ("Firefox (Snap)", BrowserFamily::Firefox),
+            ("Brave", BrowserFamily::Chromium),
+        ]
+    );
+}
+
+#[cfg(target_os = "linux")]
+#[test]
+fn test_browser_targets_skip_sandboxed_browsers() {
+    let home = tempfile::tempdir().unwrap();
+
+    let unsupported: Vec<_> = browser_targets(home.path())
+        .into_iter()
+        .filter(|t| t.unsupported.is_some())
+        .map(|t| t.name)
+        .collect();
+
+    assert_eq!(
+        unsupported,
+        ["Firefox (Flatpak)", "LibreWolf (Flatpak)", "Firefox (Snap)"]
+    );
+}
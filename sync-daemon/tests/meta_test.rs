@@ -0,0 +1,169 @@
+use savebutton_sync_daemon::meta::{
+    hidden_anga, read_meta_dir, resolve_all_meta, resolve_meta, validate_meta_filename, Meta,
+    MetaError,
+};
+use std::fs;
+
+const ADR_EXAMPLE: &str = r#"[anga]
+filename = "2026-01-28T205208-bookmark.url"
+
+[meta]
+tags = ["podcast", "democracy", "cooperatives"]
+note = '''This is a longer note.
+
+It can be multi-line.'''
+"#;
+
+#[test]
+fn test_parse_adr_0003_example() {
+    let meta = Meta::parse(ADR_EXAMPLE).unwrap();
+    assert_eq!(meta.anga.filename, "2026-01-28T205208-bookmark.url");
+    assert_eq!(meta.meta.tags, ["podcast", "democracy", "cooperatives"]);
+    assert!(meta.meta.note.unwrap().contains("multi-line"));
+}
+
+#[test]
+fn test_unknown_keys_survive_a_round_trip() {
+    let text = "[anga]\nfilename = \"a.url\"\nsha = \"abc\"\n\n[meta]\nrating = 5\n\n[source]\nbrowser = \"firefox\"\n";
+    let meta = Meta::parse(text).unwrap();
+    let reparsed = Meta::parse(&meta.to_toml().unwrap()).unwrap();
+
+    assert_eq!(reparsed, meta);
+    assert_eq!(reparsed.anga.extra["sha"].as_str(), Some("abc"));
+    assert_eq!(reparsed.meta.extra["rating"].as_integer(), Some(5));
+    assert!(reparsed.extra.contains_key("source"));
+}
+
+#[test]
+fn test_parse_rejects_invalid_meta() {
+    assert!(matches!(
+        Meta::parse("[meta]\ntags = [\"x\"]\n"),
+        Err(MetaError::Parse(_))
+    ));
+    assert!(matches!(
+        Meta::parse("[anga]\nfilename = \"../.config\"\n"),
+        Err(MetaError::Invalid(_))
+    ));
+    assert!(matches!(
+        Meta::parse("[anga]\nfilename = \"a.url\"\n[meta]\ntags = [\" \"]\n"),
+        Err(MetaError::Invalid(_))
+    ));
+    assert!(validate_meta_filename("2026-01-01T000000-note.toml").is_ok());
+    assert!(validate_meta_filename("note.txt").is_err());
+}
+
+#[test]
+fn test_read_meta_dir_reports_invalid_files() {
+    let dir = tempfile::tempdir().unwrap();
+    fs::write(dir.path().join("2026-01-01T000000-a.toml"), ADR_EXAMPLE).unwrap();
+    fs::write(dir.path().join("2026-01-02T000000-b.toml"), "not toml [").unwrap();
+
+    let metas = read_meta_dir(dir.path()).unwrap();
+    assert_eq!(metas.len(), 2);
+    assert!(metas[0].1.is_ok());
+    assert!(metas[1].1.is_err());
+}
+
+#[test]
+fn test_resolve_meta_folds_tags_and_notes_in_timestamp_order() {
+    let dir = tempfile::tempdir().unwrap();
+    let write = |name: &str, body: &str| {
+        let text = format!(
+            "[anga]\nfilename = \"2026-01-01T000000-a.url\"\n\n[meta]\n{}",
+            body
+        );
+        fs::write(dir.path().join(name), text).unwrap();
+    };
+    write(
+        "2026-01-02T000000-tags.toml",
+        "tags = [\"podcast\", \"Democracy\"]\nnote = 'first'\n",
+    );
+    write(
+        "2026-01-03T000000-tags.toml",
+        "tags = [\"democracy\", \"coops\"]\nremove_tags = [\"podcast\"]\n",
+    );
+    write("2026-01-03T000000_500000000-note.toml", "note = 'second'\n");
+
+    let view = resolve_meta(dir.path(), "2026-01-01T000000-a.url").unwrap();
+
+    assert_eq!(view.tags, ["Democracy", "coops"]);
+    assert_eq!(view.note.as_deref(), Some("second"));
+    assert_eq!(view.sources.len(), 3);
+    assert_eq!(
+        view.sources.last().unwrap(),
+        "2026-01-03T000000_500000000-note.toml"
+    );
+
+    write("2026-01-04T000000-note.toml", "note = ''\n");
+    let all = resolve_all_meta(dir.path()).unwrap();
+    assert_eq!(all["2026-01-01T000000-a.url"].note, None);
+}
+
+#[test]
+fn test_latest_tombstone_decides_whether_an_anga_is_hidden() {
+    let dir = tempfile::tempdir().unwrap();
+    let write = |name: &str, anga: &str, hidden: bool| {
+        let mut meta = Meta::new(anga);
+        meta.meta.hidden = Some(hidden);
+        fs::write(dir.path().join(name), meta.to_toml().unwrap()).unwrap();
+    };
+    write(
+        "2026-01-02T000000-hidden.toml",
+        "2026-01-01T000000-a.url",
+        true,
+    );
+    write(
+        "2026-01-03T000000-hidden.toml",
+        "2026-01-01T000000-b.url",
+        true,
+    );
+    write(
+        "2026-01-04T000000-unhidden.toml",
+        "2026-01-01T000000-b.url",
+        false,
+    );
+
+    let hidden = hidden_anga(dir.path()).unwrap();
+
+    assert_eq!(hidden.len(), 1);
+    assert!(hidden.contains("2026-01-01T000000-a.url"));
+    assert!(
+        !resolve_meta(dir.path(), "2026-01-01T000000-b.url")
+            .unwrap()
+            .hidden
+    );
+}
+
+#[test]
+fn test_browser_is_folded_into_the_view() {
+    let dir = tempfile::tempdir().unwrap();
+    let mut meta = Meta::new("2026-01-01T000000-a.url");
+    meta.meta.browser = Some("brave".to_string());
+    fs::write(
+        dir.path().join("2026-01-01T000000-browser.toml"),
+        meta.to_toml().unwrap(),
+    )
+    .unwrap();
+
+    let view = resolve_meta(dir.path(), "2026-01-01T000000-a.url").unwrap();
+    assert_eq!(view.browser.as_deref(), Some("brave"));
+}
+
+#[test]
+fn test_latest_keyword_wins_and_empty_clears_it() {
+    let dir = tempfile::tempdir().unwrap();
+    let write = |name: &str, keyword: &str| {
+        let mut meta = Meta::new("2026-01-01T000000-a.url");
+        meta.meta.keyword = Some(keyword.to_string());
+        fs::write(dir.path().join(name), meta.to_toml().unwrap()).unwrap();
+    };
+
+    write("2026-01-01T000000-import.toml", "docs");
+    write("2026-01-02T000000-keyword.toml", "rustdoc");
+    let view = resolve_meta(dir.path(), "2026-01-01T000000-a.url").unwrap();
+    assert_eq!(view.keyword.as_deref(), Some("rustdoc"));
+
+    write("2026-01-03T000000-keyword.toml", "");
+    let view = resolve_meta(dir.path(), "2026-01-01T000000-a.url").unwrap();
+    assert_eq!(view.keyword, None);
+}
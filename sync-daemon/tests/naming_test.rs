@@ -0,0 +1,74 @@
+use chrono::{TimeZone, Timelike, Utc};
+use savebutton_sync_daemon::naming::{
+    bookmark_slug, create_timestamped_file, partial_path, remove_partial_files, slugify,
+    timestamp_prefix, write_atomically,
+};
+use std::fs;
+
+#[test]
+fn test_timestamp_prefix_uses_adr_0001_format() {
+    let at = Utc.with_ymd_and_hms(2026, 1, 27, 17, 12, 7).unwrap();
+    assert_eq!(timestamp_prefix(at), "2026-01-27T171207");
+}
+
+#[test]
+fn test_slugify_produces_url_safe_names() {
+    assert_eq!(slugify("Wakarimasen, LOL!", "x"), "wakarimasen-lol");
+    assert_eq!(slugify("www.deobald.ca", "x"), "www-deobald-ca");
+    assert_eq!(slugify("日本語", "bookmark"), "bookmark");
+
+    let long = slugify(&"word ".repeat(30), "x");
+    assert!(long.len() <= 60);
+    assert!(!long.ends_with('-'));
+}
+
+#[test]
+fn test_bookmark_slug_falls_back_to_the_host() {
+    assert_eq!(
+        bookmark_slug("https://deobald.ca/", Some("Steven Deobald")),
+        "steven-deobald"
+    );
+    assert_eq!(
+        bookmark_slug("https://deobald.ca/", Some("日本語")),
+        "deobald-ca"
+    );
+    assert_eq!(bookmark_slug("not a url", None), "bookmark");
+}
+
+#[test]
+fn test_create_timestamped_file_falls_back_to_nanoseconds_on_collision() {
+    let dir = tempfile::tempdir().unwrap();
+    let at = Utc
+        .with_ymd_and_hms(2026, 1, 21, 16, 41, 45)
+        .unwrap()
+        .with_nanosecond(354_000_000)
+        .unwrap();
+
+    let first = create_timestamped_file(dir.path(), at, "note", "md", b"one").unwrap();
+    let second = create_timestamped_file(dir.path(), at, "note", "md", b"two").unwrap();
+    let third = create_timestamped_file(dir.path(), at, "note", "md", b"three").unwrap();
+
+    assert_eq!(first, "2026-01-21T164145-note.md");
+    assert_eq!(second, "2026-01-21T164145_354000000-note.md");
+    assert_eq!(third, "2026-01-21T164145_354000001-note.md");
+    assert_eq!(fs::read(dir.path().join(&first)).unwrap(), b"one");
+    assert_eq!(fs::read(dir.path().join(&second)).unwrap(), b"two");
+}
+
+#[test]
+fn test_write_atomically_replaces_file_and_cleans_up_partials() {
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path().join("2026-01-01T000000-a.md");
+
+    write_atomically(&path, b"first").unwrap();
+    write_atomically(&path, b"second").unwrap();
+    assert_eq!(fs::read_to_string(&path).unwrap(), "second");
+    assert_eq!(fs::read_dir(dir.path()).unwrap().count(), 1);
+
+    // An interrupted write leaves only the partial file behind
+    let partial = partial_path(&dir.path().join("2026-01-02T000000-b.md")).unwrap();
+    fs::write(&partial, b"trunc").unwrap();
+    assert_eq!(remove_partial_files(dir.path()).unwrap(), 1);
+    assert!(!partial.exists());
+    assert!(path.exists());
+}
@@ -0,0 +1,74 @@
+use savebutton_sync_daemon::bookmarks::BookmarkIndex;
+use savebutton_sync_daemon::normalize::{normalize_url, NormalizeOptions};
+use std::fs;
+
+#[test]
+fn test_normalize_url_treats_common_variants_as_one_page() {
+    let opts = NormalizeOptions::default();
+    let expected = normalize_url("https://x.com/a", &opts);
+
+    assert_eq!(expected, "https://x.com/a");
+    assert_eq!(normalize_url("https://x.com/a/", &opts), expected);
+    assert_eq!(
+        normalize_url("http://x.com/a?utm_source=feed&fbclid=123", &opts),
+        expected
+    );
+    assert_eq!(normalize_url("https://x.com/a#frag", &opts), expected);
+    assert_eq!(normalize_url("https://X.com:443/a", &opts), expected);
+}
+
+#[test]
+fn test_normalize_url_keeps_meaningful_query_params() {
+    let opts = NormalizeOptions::default();
+    assert_eq!(
+        normalize_url("https://x.com/search?q=rust%20lang&utm_medium=email", &opts),
+        "https://x.com/search?q=rust%20lang"
+    );
+}
+
+#[test]
+fn test_normalize_url_converts_idn_hosts_to_punycode() {
+    let opts = NormalizeOptions::default();
+    assert_eq!(
+        normalize_url("https://bücher.example/", &opts),
+        "https://xn--bcher-kva.example"
+    );
+}
+
+#[test]
+fn test_normalize_url_respects_disabled_options() {
+    let opts = NormalizeOptions {
+        ignore_scheme: false,
+        strip_fragment: false,
+        ..Default::default()
+    };
+    assert_eq!(
+        normalize_url("http://x.com/a#frag", &opts),
+        "http://x.com/a#frag"
+    );
+}
+
+#[test]
+fn test_bookmark_index_matches_normalized_urls() {
+    let dir = tempfile::tempdir().unwrap();
+    fs::write(
+        dir.path().join("2026-01-27T171207-x-com.url"),
+        "[InternetShortcut]\nURL=https://x.com/a\n",
+    )
+    .unwrap();
+    fs::write(dir.path().join("2026-01-28T090000-quote.md"), "URL=nope").unwrap();
+
+    let index = BookmarkIndex::load(dir.path(), NormalizeOptions::default()).unwrap();
+
+    assert_eq!(index.len(), 1);
+    assert_eq!(
+        index.lookup("http://x.com/a/?utm_campaign=spring"),
+        ["2026-01-27T171207-x-com.url".to_string()]
+    );
+    assert!(!index.contains("https://x.com/b"));
+
+    let hidden = ["2026-01-27T171207-x-com.url".to_string()].into();
+    let index =
+        BookmarkIndex::load_excluding(dir.path(), NormalizeOptions::default(), &hidden).unwrap();
+    assert!(index.is_empty());
+}
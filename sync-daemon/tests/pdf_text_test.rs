@@ -0,0 +1,158 @@
+use lopdf::content::{Content, Operation};
+use lopdf::{dictionary, Document, Object, Stream};
+use savebutton_sync_daemon::pdf_text::{
+    ensure_pdf_text, extract_missing_pdf_text, extract_pdf_pages, find_in_pdfs, read_pdf_text,
+    write_pdf_text, PdfMatch, PDF_TEXT_FILENAME,
+};
+use std::fs;
+
+/// A PDF with one page per entry, each line of which is shown in
+/// Helvetica.
+fn pdf(pages: &[&[&str]]) -> Vec<u8> {
+    let mut doc = Document::with_version("1.5");
+    let pages_id = doc.new_object_id();
+    let font_id = doc.add_object(dictionary! {
+        "Type" => "Font",
+        "Subtype" => "Type1",
+        "BaseFont" => "Helvetica",
+        "Encoding" => "WinAnsiEncoding",
+    });
+    let resources_id = doc.add_object(dictionary! {
+        "Font" => dictionary! { "F1" => font_id },
+    });
+
+    let mut kids: Vec<Object> = Vec::new();
+    for lines in pages {
+        let mut operations = Vec::new();
+        for (i, line) in lines.iter().enumerate() {
+            operations.extend([
+                Operation::new("BT", vec![]),
+                Operation::new("Tf", vec!["F1".into(), 12.into()]),
+                Operation::new("Td", vec![72.into(), (720 - 20 * i as i64).into()]),
+                Operation::new("Tj", vec![Object::string_literal(*line)]),
+                Operation::new("ET", vec![]),
+            ]);
+        }
+        let content = Content { operations }.encode().unwrap();
+        let content_id = doc.add_object(Stream::new(dictionary! {}, content));
+        let page_id = doc.add_object(dictionary! {
+            "Type" => "Page",
+            "Parent" => pages_id,
+            "Contents" => content_id,
+        });
+        kids.push(page_id.into());
+    }
+    let count = kids.len() as i64;
+    doc.objects.insert(
+        pages_id,
+        Object::Dictionary(dictionary! {
+            "Type" => "Pages",
+            "Kids" => kids,
+            "Count" => count,
+            "Resources" => resources_id,
+            "MediaBox" => vec![0.into(), 0.into(), 612.into(), 792.into()],
+        }),
+    );
+    let catalog_id = doc.add_object(dictionary! { "Type" => "Catalog", "Pages" => pages_id });
+    doc.trailer.set("Root", catalog_id);
+
+    let mut out = Vec::new();
+    doc.save_to(&mut out).unwrap();
+    out
+}
+
+#[test]
+fn test_extracts_text_page_by_page() {
+    let pages = extract_pdf_pages(&pdf(&[
+        &["Notice of Assessment", "Net income"],
+        &[],
+        &["Total tax payable"],
+    ]))
+    .unwrap();
+
+    assert_eq!(
+        pages,
+        vec!["Notice of Assessment\nNet income", "", "Total tax payable"]
+    );
+    assert!(extract_pdf_pages(b"not a pdf").is_err());
+}
+
+#[test]
+fn test_pages_round_trip_through_the_cache() {
+    let cache = tempfile::tempdir().unwrap();
+    let dir = cache.path().join("2026-01-01T000000-paper.pdf");
+    assert_eq!(read_pdf_text(&dir).unwrap(), None);
+
+    let pages = vec!["one".to_string(), String::new(), "three".to_string()];
+    write_pdf_text(&dir, &pages).unwrap();
+    assert_eq!(read_pdf_text(&dir).unwrap(), Some(pages));
+}
+
+#[test]
+fn test_finds_phrases_and_their_pages() {
+    let kaya = tempfile::tempdir().unwrap();
+    let anga = kaya.path().join("anga");
+    let cache = kaya.path().join("cache");
+    fs::create_dir_all(&anga).unwrap();
+
+    let older = "2025-04-30T120000-taxes.pdf";
+    let newer = "2026-01-01T000000-paper.pdf";
+    fs::write(
+        anga.join(older),
+        pdf(&[
+            &["Net", "income: 1,000"],
+            &["Nothing here"],
+            &["NET INCOME"],
+        ]),
+    )
+    .unwrap();
+    fs::write(anga.join(newer), pdf(&[&["We estimate net income"]])).unwrap();
+    fs::write(anga.join("2026-01-01T000000-note.md"), "net income").unwrap();
+
+    assert_eq!(
+        extract_missing_pdf_text(&anga, &cache, || false).unwrap(),
+        2
+    );
+    assert!(cache.join(older).join(PDF_TEXT_FILENAME).is_file());
+    assert_eq!(
+        extract_missing_pdf_text(&anga, &cache, || false).unwrap(),
+        0
+    );
+
+    assert_eq!(
+        find_in_pdfs(&anga, &cache, "  Net   Income ").unwrap(),
+        vec![
+            PdfMatch {
+                filename: newer.to_string(),
+                pages: vec![1],
+            },
+            PdfMatch {
+                filename: older.to_string(),
+                pages: vec![1, 3],
+            },
+        ]
+    );
+    assert_eq!(find_in_pdfs(&anga, &cache, "gross").unwrap(), vec![]);
+
+    // Text left behind by a deleted PDF isn't searched
+    fs::remove_file(anga.join(newer)).unwrap();
+    let found = find_in_pdfs(&anga, &cache, "net income").unwrap();
+    assert_eq!(found.len(), 1);
+    assert_eq!(found[0].filename, older);
+}
+
+#[test]
+fn test_ensure_pdf_text_only_reads_pdfs() {
+    let kaya = tempfile::tempdir().unwrap();
+    let anga = kaya.path().join("anga");
+    let cache = kaya.path().join("cache");
+    fs::create_dir_all(&anga).unwrap();
+    fs::write(anga.join("photo.png"), "").unwrap();
+    fs::write(anga.join("paper.pdf"), pdf(&[&["Abstract"]])).unwrap();
+
+    assert_eq!(ensure_pdf_text(&anga, &cache, "photo.png").unwrap(), None);
+    assert_eq!(
+        ensure_pdf_text(&anga, &cache, "paper.pdf").unwrap(),
+        Some(vec!["Abstract".to_string()])
+    );
+}
@@ -0,0 +1,145 @@
+use chrono::{TimeZone, Utc};
+use rusqlite::Connection;
+use savebutton_sync_daemon::import::ImportedBookmark;
+use savebutton_sync_daemon::places::{read_places, PLACES_FILENAME};
+use std::path::Path;
+
+/// The parts of Firefox's schema the importer reads, with the folders
+/// every profile has.
+const SCHEMA: &str = "
+    PRAGMA journal_mode = WAL;
+    CREATE TABLE moz_places (
+        id INTEGER PRIMARY KEY, url TEXT, title TEXT,
+        visit_count INTEGER DEFAULT 0, hidden INTEGER DEFAULT 0
+    );
+    CREATE TABLE moz_bookmarks (
+        id INTEGER PRIMARY KEY, type INTEGER, fk INTEGER, parent INTEGER,
+        title TEXT, dateAdded INTEGER, guid TEXT
+    );
+    CREATE TABLE moz_keywords (id INTEGER PRIMARY KEY, keyword TEXT, place_id INTEGER);
+    CREATE TABLE moz_historyvisits (id INTEGER PRIMARY KEY, place_id INTEGER, visit_date INTEGER);
+    INSERT INTO moz_bookmarks (id, type, parent, title, guid) VALUES
+        (1, 2, 0, '', 'root________'),
+        (2, 2, 1, 'menu', 'menu________'),
+        (3, 2, 1, 'toolbar', 'toolbar_____'),
+        (4, 2, 1, 'tags', 'tags________'),
+        (5, 2, 1, 'unfiled', 'unfiled_____');
+";
+
+/// Microseconds since the epoch for a day in 2020, as Firefox stores times.
+fn micros(day: u32) -> i64 {
+    Utc.with_ymd_and_hms(2020, 1, day, 0, 0, 0)
+        .unwrap()
+        .timestamp_micros()
+}
+
+fn profile(dir: &Path) -> Connection {
+    let conn = Connection::open(dir.join(PLACES_FILENAME)).unwrap();
+    conn.execute_batch(SCHEMA).unwrap();
+    conn.execute_batch(&format!(
+        "
+        INSERT INTO moz_places (id, url, title, visit_count) VALUES
+            (1, 'https://doc.rust-lang.org/std/', 'std - Rust', 40),
+            (2, 'https://example.com/essay', 'Page Title', 2),
+            (3, 'https://news.example.org/', 'News', 25),
+            (4, 'https://once.example.org/', 'Once', 1),
+            (5, 'place:sort=8', NULL, 0);
+        INSERT INTO moz_bookmarks (id, type, fk, parent, title, dateAdded, guid) VALUES
+            (10, 2, NULL, 3, 'Dev', {d1}, 'dev_________'),
+            (11, 2, NULL, 10, 'Rust', {d1}, 'rust________'),
+            (12, 1, 1, 11, 'Rust std', {d2}, 'b1__________'),
+            (13, 1, 2, 5, NULL, {d3}, 'b2__________'),
+            (14, 1, 1, 2, 'std again', {d4}, 'b3__________'),
+            (15, 1, 5, 3, 'Most Visited', {d4}, 'b4__________'),
+            (20, 2, NULL, 4, 'reference', {d1}, 'tag1________'),
+            (21, 1, 1, 20, NULL, {d1}, 'tagentry1___'),
+            (22, 2, NULL, 4, 'docs', {d1}, 'tag2________'),
+            (23, 1, 1, 22, NULL, {d1}, 'tagentry2___');
+        INSERT INTO moz_keywords (keyword, place_id) VALUES ('std', 1);
+        INSERT INTO moz_historyvisits (place_id, visit_date) VALUES
+            (3, {d5}), (3, {d6}), (4, {d5}), (1, {d1});
+        ",
+        d1 = micros(1),
+        d2 = micros(2),
+        d3 = micros(3),
+        d4 = micros(4),
+        d5 = micros(5),
+        d6 = micros(6),
+    ))
+    .unwrap();
+    conn
+}
+
+#[test]
+fn test_reads_bookmarks_tags_and_keywords() {
+    let dir = tempfile::tempdir().unwrap();
+    // Held open, as a running Firefox would, so recent writes are in the WAL
+    let _firefox = profile(dir.path());
+    assert!(dir.path().join("places.sqlite-wal").is_file());
+
+    let bookmarks = read_places(dir.path(), None).unwrap();
+    let at = |day| Some(Utc.with_ymd_and_hms(2020, 1, day, 0, 0, 0).unwrap());
+    assert_eq!(
+        bookmarks,
+        vec![
+            ImportedBookmark {
+                url: "https://doc.rust-lang.org/std/".to_string(),
+                title: Some("Rust std".to_string()),
+                added: at(2),
+                tags: vec![
+                    "reference".to_string(),
+                    "docs".to_string(),
+                    "Dev".to_string(),
+                    "Rust".to_string()
+                ],
+                keyword: Some("std".to_string()),
+                note: None,
+            },
+            ImportedBookmark {
+                url: "https://example.com/essay".to_string(),
+                title: Some("Page Title".to_string()),
+                added: at(3),
+                ..Default::default()
+            },
+            ImportedBookmark {
+                url: "place:sort=8".to_string(),
+                title: Some("Most Visited".to_string()),
+                added: at(4),
+                ..Default::default()
+            },
+        ]
+    );
+}
+
+#[test]
+fn test_reads_frequently_visited_history() {
+    let dir = tempfile::tempdir().unwrap();
+    let _firefox = profile(dir.path());
+
+    let bookmarks = read_places(&dir.path().join(PLACES_FILENAME), Some(20)).unwrap();
+    let history: Vec<_> = bookmarks.iter().filter(|b| b.tags == ["history"]).collect();
+    assert_eq!(
+        history,
+        vec![&ImportedBookmark {
+            url: "https://news.example.org/".to_string(),
+            title: Some("News".to_string()),
+            added: Some(Utc.with_ymd_and_hms(2020, 1, 5, 0, 0, 0).unwrap()),
+            tags: vec!["history".to_string()],
+            ..Default::default()
+        }]
+    );
+}
+
+#[test]
+fn test_rejects_other_files() {
+    let dir = tempfile::tempdir().unwrap();
+    assert!(read_places(dir.path(), None).is_err());
+
+    let other = dir.path().join("other.sqlite");
+    Connection::open(&other)
+        .unwrap()
+        .execute_batch("CREATE TABLE t (x INTEGER);")
+        .unwrap();
+    let err = read_places(&other, None).unwrap_err();
+    assert!(err.to_string().contains("isn't a Firefox places database"));
+}
@@ -0,0 +1,87 @@
+use savebutton_sync_daemon::preview::{
+    extract_preview, read_preview, write_preview, PagePreview, PreviewOptions, PREVIEW_FILENAME,
+};
+use url::Url;
+
+fn page_url() -> Url {
+    Url::parse("https://example.com/blog/post?utm_source=x").unwrap()
+}
+
+#[test]
+fn test_opengraph_wins_over_twitter_and_html() {
+    let html = r#"<html><head>
+<title>Plain &amp; Simple</title>
+<meta name="description" content="From the description tag">
+<meta name="twitter:title" content="Twitter title">
+<meta name="twitter:card" content="summary_large_image">
+<meta name="twitter:image" content="https://cdn.example.com/twitter.png">
+<meta property="og:title" content="OpenGraph  title">
+<meta property="og:site_name" content="Example">
+<meta property="og:type" content="article">
+<meta property="og:image" content="/images/cover.jpg">
+<link rel="apple-touch-icon" href="/touch.png">
+<link rel="shortcut icon" href="/static/icon.png">
+<link rel="canonical" href="/blog/post">
+</head><body><meta property="og:description" content="too late"></body></html>"#;
+
+    assert_eq!(
+        extract_preview(html, &page_url()),
+        PagePreview {
+            title: Some("OpenGraph title".to_string()),
+            description: Some("From the description tag".to_string()),
+            site_name: Some("Example".to_string()),
+            kind: Some("article".to_string()),
+            card: Some("summary_large_image".to_string()),
+            canonical_url: Some("https://example.com/blog/post".to_string()),
+            image_url: Some("https://example.com/images/cover.jpg".to_string()),
+            favicon_url: Some("https://example.com/static/icon.png".to_string()),
+            image: None,
+            favicon: None,
+        }
+    );
+}
+
+#[test]
+fn test_falls_back_to_title_og_url_and_favicon_ico() {
+    let html = r#"<title> Just a
+title </title><meta property="og:url" content="https://example.com/canonical">"#;
+    let preview = extract_preview(html, &page_url());
+
+    assert_eq!(preview.title.as_deref(), Some("Just a title"));
+    assert_eq!(
+        preview.canonical_url.as_deref(),
+        Some("https://example.com/canonical")
+    );
+    assert_eq!(
+        preview.favicon_url.as_deref(),
+        Some("https://example.com/favicon.ico")
+    );
+    assert_eq!(preview.image_url, None);
+    assert_eq!(preview.description, None);
+}
+
+#[test]
+fn test_preview_round_trips_through_the_cache() {
+    let cache = tempfile::tempdir().unwrap();
+    let dir = cache.path().join("2026-01-01T000000-post.url");
+    assert_eq!(read_preview(&dir).unwrap(), None);
+
+    let preview = PagePreview {
+        title: Some("Post".to_string()),
+        favicon_url: Some("https://example.com/favicon.ico".to_string()),
+        favicon: Some("favicon.ico".to_string()),
+        ..Default::default()
+    };
+    write_preview(&dir, &preview).unwrap();
+
+    assert!(dir.join(PREVIEW_FILENAME).is_file());
+    assert_eq!(read_preview(&dir).unwrap(), Some(preview));
+}
+
+#[test]
+fn test_previews_are_on_by_default() {
+    let opts: PreviewOptions = toml::from_str("store_images = false").unwrap();
+    assert!(opts.enabled);
+    assert!(opts.fetch);
+    assert!(!opts.store_images);
+}
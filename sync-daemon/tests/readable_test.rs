@@ -0,0 +1,103 @@
+use savebutton_sync_daemon::readable::{
+    ensure_readable, extract_missing, extract_readable, read_readable, Readable,
+    READABLE_INFO_FILENAME, READABLE_TEXT_FILENAME,
+};
+use std::fs;
+
+const ARTICLE: &str = r#"<html><head>
+<title>Worker Co-ops &amp; You | The Example Times</title>
+<meta name="description" content="Why cooperatives matter.">
+<style>p { color: red }</style>
+</head><body>
+<header class="site-header"><a href="/">The Example Times</a></header>
+<nav><ul><li>Home</li><li>World</li></ul></nav>
+<div id="content">
+  <div class="post">
+    <h1>Worker Co-ops and You</h1>
+    <p class="byline">By <a href="/jane">Jane Doe</a></p>
+    <p>Cooperatives are businesses owned by the people who work in them, and they have a long history.</p>
+    <p>Members share profits, decisions and risk, which changes how the business behaves in a downturn.</p>
+    <div class="share-buttons">Share on social media</div>
+  </div>
+  <div class="comments"><p>First! Great article, thanks, really.</p></div>
+</div>
+<aside>Related: ten more things</aside>
+<footer>Copyright 2026</footer>
+<script>track();</script>
+</body></html>"#;
+
+#[test]
+fn test_extracts_the_article_without_page_chrome() {
+    let readable = extract_readable(ARTICLE);
+
+    assert_eq!(
+        readable.title.as_deref(),
+        Some("Worker Co-ops & You | The Example Times")
+    );
+    assert_eq!(readable.byline.as_deref(), Some("By Jane Doe"));
+    assert_eq!(
+        readable.excerpt.as_deref(),
+        Some("Why cooperatives matter.")
+    );
+    assert_eq!(
+        readable.text,
+        "Worker Co-ops and You\n\n\
+         By Jane Doe\n\n\
+         Cooperatives are businesses owned by the people who work in them, and they have a long history.\n\n\
+         Members share profits, decisions and risk, which changes how the business behaves in a downturn."
+    );
+}
+
+#[test]
+fn test_prefers_an_article_element_and_falls_back_for_the_excerpt() {
+    let html = r#"<body><div>Menu text</div>
+<article><header><h1>Heading</h1><span class="author">Sam</span></header>
+<p>The first paragraph of the article, long enough to count as prose here.</p></article>
+</body>"#;
+    let readable = extract_readable(html);
+
+    assert_eq!(readable.title.as_deref(), Some("Heading"));
+    assert_eq!(readable.byline.as_deref(), Some("Sam"));
+    assert_eq!(
+        readable.excerpt.as_deref(),
+        Some("The first paragraph of the article, long enough to count as prose here.")
+    );
+    assert!(!readable.text.contains("Menu"));
+    assert!(readable.text.starts_with("Heading"));
+}
+
+#[test]
+fn test_ensure_readable_writes_and_refreshes_next_to_the_snapshot() {
+    let cache = tempfile::tempdir().unwrap();
+    let dir = cache.path().join("2026-01-01T000000-post.url");
+    fs::create_dir_all(&dir).unwrap();
+    fs::create_dir_all(cache.path().join("2026-01-02T000000-empty.url")).unwrap();
+
+    assert_eq!(ensure_readable(&dir).unwrap(), None);
+
+    fs::write(
+        dir.join("index.html"),
+        "<title>Post</title><p>Some words</p>",
+    )
+    .unwrap();
+    assert_eq!(extract_missing(cache.path(), || false).unwrap(), 1);
+    assert_eq!(extract_missing(cache.path(), || false).unwrap(), 0);
+
+    assert_eq!(
+        fs::read_to_string(dir.join(READABLE_TEXT_FILENAME)).unwrap(),
+        "Some words"
+    );
+    assert_eq!(
+        fs::read_to_string(dir.join(READABLE_INFO_FILENAME)).unwrap(),
+        "title = \"Post\"\nexcerpt = \"Some words\"\n"
+    );
+    assert_eq!(
+        read_readable(&dir).unwrap(),
+        Some(Readable {
+            title: Some("Post".to_string()),
+            byline: None,
+            excerpt: Some("Some words".to_string()),
+            text: "Some words".to_string(),
+        })
+    );
+}
@@ -0,0 +1,77 @@
+use chrono::NaiveTime;
+use savebutton_sync_daemon::schedule::{
+    sync_allowed, QuietHours, ScheduleOptions, Scheduler, SyncOutcome, SyncSignal, Wake,
+};
+use std::sync::Arc;
+use std::thread;
+use std::time::Duration;
+
+fn at(hhmm: &str) -> NaiveTime {
+    NaiveTime::parse_from_str(hhmm, "%H:%M").unwrap()
+}
+
+#[test]
+fn test_interval_backs_off_while_idle_and_resets_on_change() {
+    let opts = ScheduleOptions {
+        interval_secs: 60,
+        max_interval_secs: 300,
+        ..Default::default()
+    };
+    let mut scheduler = Scheduler::new(&opts);
+    let secs = |d: Duration| d.as_secs();
+
+    assert_eq!(secs(scheduler.next_interval(&opts, SyncOutcome::Idle)), 120);
+    assert_eq!(
+        secs(scheduler.next_interval(&opts, SyncOutcome::Unreachable)),
+        240
+    );
+    assert_eq!(secs(scheduler.next_interval(&opts, SyncOutcome::Idle)), 300);
+    assert_eq!(
+        secs(scheduler.next_interval(&opts, SyncOutcome::Changed)),
+        60
+    );
+
+    scheduler.next_interval(&opts, SyncOutcome::Idle);
+    scheduler.local_change(&opts);
+    assert_eq!(secs(scheduler.current()), 60);
+}
+
+#[test]
+fn test_quiet_hours_and_metered_connections_block_sync() {
+    let opts: ScheduleOptions = toml::from_str(
+        "unmetered_only = true\n[quiet_hours]\nstart = \"22:00\"\nend = \"07:00\"\n",
+    )
+    .unwrap();
+    assert_eq!(opts.interval_secs, 60);
+
+    assert!(sync_allowed(&opts, at("12:00"), false));
+    assert!(!sync_allowed(&opts, at("12:00"), true));
+    assert!(!sync_allowed(&opts, at("23:30"), false));
+    assert!(!sync_allowed(&opts, at("06:59"), false));
+    assert!(sync_allowed(&opts, at("07:00"), false));
+
+    let daytime = QuietHours {
+        start: at("09:00"),
+        end: at("17:00"),
+    };
+    assert!(daytime.contains(at("12:00")));
+    assert!(!daytime.contains(at("18:00")));
+}
+
+#[test]
+fn test_sync_signal_wakes_and_stops_waiters() {
+    let signal = Arc::new(SyncSignal::new());
+    assert_eq!(signal.wait(Duration::from_millis(10)), Wake::Timeout);
+
+    // A wake sent before anyone waits is not lost
+    signal.wake();
+    assert_eq!(signal.wait(Duration::from_secs(10)), Wake::Woken);
+
+    let waiter = {
+        let signal = signal.clone();
+        thread::spawn(move || signal.wait(Duration::from_secs(10)))
+    };
+    signal.stop();
+    assert_eq!(waiter.join().unwrap(), Wake::Stopped);
+    assert!(signal.is_stopped());
+}
@@ -0,0 +1,191 @@
+use savebutton_sync_daemon::search::{html_to_text, tokenize, SearchIndex, SearchQuery};
+use std::fs;
+use std::path::Path;
+
+fn kaya_dir() -> tempfile::TempDir {
+    let dir = tempfile::tempdir().unwrap();
+    for sub in ["anga", "meta", "cache"] {
+        fs::create_dir_all(dir.path().join(sub)).unwrap();
+    }
+    dir
+}
+
+fn query(text: &str) -> SearchQuery {
+    SearchQuery {
+        text: text.to_string(),
+        ..Default::default()
+    }
+}
+
+fn filenames(index: &SearchIndex, q: &SearchQuery) -> Vec<String> {
+    index.search(q).into_iter().map(|h| h.filename).collect()
+}
+
+fn write(dir: &Path, path: &str, content: &str) {
+    fs::write(dir.join(path), content).unwrap();
+}
+
+#[test]
+fn test_tokenize_lowercases_and_splits_on_punctuation() {
+    assert_eq!(
+        tokenize("Hello, deobald.ca! a 7"),
+        ["hello", "deobald", "ca", "7"]
+    );
+}
+
+#[test]
+fn test_html_to_text_drops_scripts_and_tags() {
+    let text = html_to_text("<p>Co&amp;op</p><script>var x = '<p>';</script><b>votes</b>");
+    assert_eq!(tokenize(&text), ["co", "op", "votes"]);
+}
+
+#[test]
+fn test_html_to_text_decodes_entities_once() {
+    let text = html_to_text("<p>&amp;lt;div&amp;gt; &#x2014;&nbsp;caf&#233;</p>");
+    assert_eq!(text.trim(), "&lt;div&gt; \u{2014}\u{a0}caf\u{e9}");
+}
+
+#[test]
+fn test_search_covers_notes_bookmarks_meta_and_cache() {
+    let dir = kaya_dir();
+    let root = dir.path();
+    write(
+        root,
+        "anga/2026-01-01T000000-quote.md",
+        "Democracy needs cooperatives",
+    );
+    write(
+        root,
+        "anga/2026-01-02T000000-podcast.url",
+        "[InternetShortcut]\nURL=https://example.com/episode\nTitle=Worker Podcast\n",
+    );
+    write(
+        root,
+        "meta/2026-01-03T000000-note.toml",
+        "[anga]\nfilename = \"2026-01-02T000000-podcast.url\"\n\n[meta]\ntags = [\"Guest\"]\nnote = 'I was on this show'\n",
+    );
+    fs::create_dir_all(root.join("cache/2026-01-02T000000-podcast.url")).unwrap();
+    write(
+        root,
+        "cache/2026-01-02T000000-podcast.url/index.html",
+        "<html><body>transcript about syndicalism</body></html>",
+    );
+
+    let mut index = SearchIndex::new(root);
+    index.refresh().unwrap();
+
+    assert_eq!(
+        filenames(&index, &query("democracy")),
+        ["2026-01-01T000000-quote.md"]
+    );
+    assert_eq!(
+        filenames(&index, &query("worker show")),
+        ["2026-01-02T000000-podcast.url"]
+    );
+    assert_eq!(
+        filenames(&index, &query("syndical")),
+        ["2026-01-02T000000-podcast.url"]
+    );
+    assert!(filenames(&index, &query("syndical nothing")).is_empty());
+
+    let tagged = SearchQuery {
+        tags: vec!["guest".to_string()],
+        ..Default::default()
+    };
+    assert_eq!(
+        filenames(&index, &tagged),
+        ["2026-01-02T000000-podcast.url"]
+    );
+}
+
+#[test]
+fn test_search_ranks_title_matches_above_body_matches() {
+    let dir = kaya_dir();
+    let root = dir.path();
+    write(
+        root,
+        "anga/2026-01-01T000000-a.md",
+        "a long note that mentions rust once",
+    );
+    write(
+        root,
+        "anga/2026-01-02T000000-b.url",
+        "[InternetShortcut]\nURL=https://x.com/\nTitle=Rust\n",
+    );
+
+    let mut index = SearchIndex::new(root);
+    index.refresh().unwrap();
+
+    assert_eq!(
+        filenames(&index, &query("rust")),
+        ["2026-01-02T000000-b.url", "2026-01-01T000000-a.md"]
+    );
+}
+
+#[test]
+fn test_refresh_picks_up_new_and_deleted_files() {
+    let dir = kaya_dir();
+    let root = dir.path();
+    let mut index = SearchIndex::new(root);
+    index.refresh().unwrap();
+    assert!(index.is_empty());
+
+    write(root, "anga/2026-01-01T000000-quote.md", "kaya");
+    assert_eq!(index.refresh().unwrap(), 1);
+    assert_eq!(index.len(), 1);
+
+    fs::remove_file(root.join("anga/2026-01-01T000000-quote.md")).unwrap();
+    index.refresh().unwrap();
+    assert!(filenames(&index, &query("kaya")).is_empty());
+}
+
+#[test]
+fn test_search_leaves_out_hidden_anga() {
+    let dir = kaya_dir();
+    let root = dir.path();
+    write(root, "anga/2026-01-01T000000-quote.md", "kaya");
+    write(root, "anga/2026-01-02T000000-quote.md", "kaya");
+    write(
+        root,
+        "meta/2026-01-03T000000-hidden.toml",
+        "[anga]\nfilename = \"2026-01-01T000000-quote.md\"\n\n[meta]\nhidden = true\n",
+    );
+
+    let mut index = SearchIndex::new(root);
+    index.refresh().unwrap();
+
+    assert_eq!(
+        filenames(&index, &query("kaya")),
+        ["2026-01-02T000000-quote.md"]
+    );
+}
+
+#[test]
+fn test_readable_text_stands_in_for_the_snapshot() {
+    let dir = kaya_dir();
+    let root = dir.path();
+    write(
+        root,
+        "anga/2026-01-02T000000-post.url",
+        "[InternetShortcut]\nURL=https://example.com/post\n",
+    );
+    fs::create_dir_all(root.join("cache/2026-01-02T000000-post.url")).unwrap();
+    write(
+        root,
+        "cache/2026-01-02T000000-post.url/index.html",
+        "<nav>subscribe</nav><p>mutual aid</p>",
+    );
+
+    let mut index = SearchIndex::new(root);
+    index.refresh().unwrap();
+    assert_eq!(filenames(&index, &query("subscribe")).len(), 1);
+
+    write(
+        root,
+        "cache/2026-01-02T000000-post.url/readable.txt",
+        "mutual aid",
+    );
+    index.refresh().unwrap();
+    assert!(filenames(&index, &query("subscribe")).is_empty());
+    assert_eq!(filenames(&index, &query("mutual")).len(), 1);
+}
@@ -0,0 +1,71 @@
+use savebutton_sync_daemon::shortcut::{ShortcutError, UrlShortcut};
+
+#[test]
+fn test_parse_extension_written_shortcut() {
+    let shortcut = UrlShortcut::parse("[InternetShortcut]\nURL=https://deobald.ca/\n").unwrap();
+    assert_eq!(shortcut.url, "https://deobald.ca/");
+    assert_eq!(shortcut.title, None);
+}
+
+#[test]
+fn test_parse_handles_bom_crlf_and_other_keys() {
+    let text = "\u{feff}[InternetShortcut]\r\n\
+                url=https://deobald.ca/\r\n\
+                Title=Steven Deobald\r\n\
+                IconFile=https://deobald.ca/favicon.ico\r\n\
+                IconIndex=0\r\n\
+                HotKey=0\r\n\
+                [{000214A0-0000-0000-C000-000000000046}]\r\n\
+                Prop3=19,11\r\n";
+
+    let shortcut = UrlShortcut::parse(text).unwrap();
+
+    assert_eq!(shortcut.url, "https://deobald.ca/");
+    assert_eq!(shortcut.title.as_deref(), Some("Steven Deobald"));
+    assert_eq!(
+        shortcut.icon_file.as_deref(),
+        Some("https://deobald.ca/favicon.ico")
+    );
+    assert_eq!(shortcut.icon_index, Some(0));
+    assert_eq!(shortcut.extra, vec![("HotKey".into(), "0".into())]);
+    assert_eq!(shortcut.sections.len(), 1);
+}
+
+#[test]
+fn test_from_bytes_decodes_utf16_with_bom() {
+    let mut bytes = vec![0xFF, 0xFE];
+    for unit in "[InternetShortcut]\r\nURL=https://x.com/\r\n".encode_utf16() {
+        bytes.extend(unit.to_le_bytes());
+    }
+    assert_eq!(
+        UrlShortcut::from_bytes(&bytes).unwrap().url,
+        "https://x.com/"
+    );
+}
+
+#[test]
+fn test_parse_ignores_url_outside_section() {
+    assert_eq!(
+        UrlShortcut::parse("URL=https://x.com/\n"),
+        Err(ShortcutError::MissingSection)
+    );
+    assert_eq!(
+        UrlShortcut::parse("[Other]\nURL=https://x.com/\n[InternetShortcut]\n"),
+        Err(ShortcutError::MissingUrl)
+    );
+}
+
+#[test]
+fn test_serialize_round_trips() {
+    let shortcut = UrlShortcut::new("https://x.com/a").with_title("A\nmulti-line title");
+    let text = shortcut.to_string();
+
+    assert_eq!(
+        text,
+        "[InternetShortcut]\nURL=https://x.com/a\nTitle=A multi-line title\n"
+    );
+    assert_eq!(
+        UrlShortcut::parse(&text).unwrap().title.as_deref(),
+        Some("A multi-line title")
+    );
+}
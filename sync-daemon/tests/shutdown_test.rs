@@ -0,0 +1,21 @@
+use savebutton_sync_daemon::shutdown::join_within;
+use std::sync::mpsc;
+use std::thread;
+use std::time::Duration;
+
+#[test]
+fn test_join_within_reports_threads_that_outlive_the_grace_period() {
+    let (tx, rx) = mpsc::channel::<()>();
+    let quick = thread::spawn(|| thread::sleep(Duration::from_millis(10)));
+    let stuck = thread::spawn(move || {
+        let _ = rx.recv();
+    });
+
+    let abandoned = join_within(
+        vec![("quick".into(), quick), ("stuck".into(), stuck)],
+        Duration::from_millis(200),
+    );
+
+    assert_eq!(abandoned, ["stuck"]);
+    drop(tx);
+}
@@ -0,0 +1,81 @@
+use savebutton_sync_daemon::tags::{complete_tags, tag_catalogue};
+use std::fs;
+use std::path::Path;
+
+fn write_meta(dir: &Path, name: &str, anga: &str, body: &str) {
+    let text = format!("[anga]\nfilename = \"{}\"\n\n[meta]\n{}", anga, body);
+    fs::write(dir.join(name), text).unwrap();
+}
+
+#[test]
+fn test_tag_catalogue_counts_current_tags_per_anga() {
+    let dir = tempfile::tempdir().unwrap();
+    let root = dir.path();
+    write_meta(
+        root,
+        "2026-01-02T000000-tags.toml",
+        "a.url",
+        "tags = [\"Podcast\", \"rust\"]\n",
+    );
+    write_meta(
+        root,
+        "2026-01-03T000000-tags.toml",
+        "b.url",
+        "tags = [\"podcast\"]\n",
+    );
+    write_meta(
+        root,
+        "2026-01-04T000000-tags.toml",
+        "c.url",
+        "tags = [\"podcast\", \"poetry\"]\n",
+    );
+    write_meta(
+        root,
+        "2026-01-05T000000-tags.toml",
+        "a.url",
+        "remove_tags = [\"rust\"]\n",
+    );
+
+    let catalogue = tag_catalogue(root).unwrap();
+    let tags: Vec<(&str, usize)> = catalogue
+        .iter()
+        .map(|t| (t.tag.as_str(), t.count))
+        .collect();
+
+    assert_eq!(tags, [("podcast", 3), ("poetry", 1)]);
+    assert_eq!(
+        catalogue[0].last_used.unwrap().to_rfc3339(),
+        "2026-01-04T00:00:00+00:00"
+    );
+
+    let completions = complete_tags(&catalogue, "PO", 10);
+    assert_eq!(completions.len(), 2);
+    assert_eq!(complete_tags(&catalogue, "poe", 10)[0].tag, "poetry");
+    assert_eq!(complete_tags(&catalogue, "", 1).len(), 1);
+}
+
+#[test]
+fn test_tags_fold_case_beyond_ascii() {
+    let dir = tempfile::tempdir().unwrap();
+    let root = dir.path();
+    write_meta(
+        root,
+        "2026-01-02T000000-tags.toml",
+        "a.url",
+        "tags = [\"Ärger\", \"ärger\", \"Émile\"]\n",
+    );
+    write_meta(
+        root,
+        "2026-01-03T000000-tags.toml",
+        "a.url",
+        "remove_tags = [\"émile\"]\n",
+    );
+
+    let catalogue = tag_catalogue(root).unwrap();
+    let tags: Vec<(&str, usize)> = catalogue
+        .iter()
+        .map(|t| (t.tag.as_str(), t.count))
+        .collect();
+    assert_eq!(tags, [("Ärger", 1)]);
+    assert_eq!(complete_tags(&catalogue, "är", 10).len(), 1);
+}
@@ -0,0 +1,111 @@
+use image::{ImageFormat, RgbImage, Rgba, RgbaImage};
+use savebutton_sync_daemon::thumbnail::{
+    ensure_thumbnail, generate_missing, has_thumbnail_type, render_thumbnail,
+    NO_THUMBNAIL_FILENAME, THUMBNAIL_FILENAME,
+};
+use std::fs;
+use std::io::{Cursor, Write};
+use std::time::{Duration, SystemTime};
+
+fn encode(image: impl Into<image::DynamicImage>, format: ImageFormat) -> Vec<u8> {
+    let mut out = Vec::new();
+    image
+        .into()
+        .write_to(&mut Cursor::new(&mut out), format)
+        .unwrap();
+    out
+}
+
+fn decode(webp: &[u8]) -> image::DynamicImage {
+    image::load_from_memory_with_format(webp, ImageFormat::WebP).unwrap()
+}
+
+#[test]
+fn test_large_images_shrink_to_fit_and_keep_transparency() {
+    let png = encode(
+        RgbaImage::from_pixel(1024, 512, Rgba([0, 0, 255, 128])),
+        ImageFormat::Png,
+    );
+    let thumbnail = decode(&render_thumbnail("photo.png", &png).unwrap().unwrap());
+
+    assert_eq!((thumbnail.width(), thumbnail.height()), (256, 128));
+    assert!(thumbnail.color().has_alpha());
+}
+
+#[test]
+fn test_small_images_keep_their_size() {
+    let gif = encode(
+        RgbaImage::from_pixel(40, 30, Rgba([1, 2, 3, 255])),
+        ImageFormat::Gif,
+    );
+    let thumbnail = decode(&render_thumbnail("anim.GIF", &gif).unwrap().unwrap());
+    assert_eq!((thumbnail.width(), thumbnail.height()), (40, 30));
+}
+
+#[test]
+fn test_other_files_and_broken_images() {
+    assert!(has_thumbnail_type("a.jpeg"));
+    assert!(!has_thumbnail_type("a.pdf"));
+    assert!(!has_thumbnail_type("a.svg"));
+    assert!(!has_thumbnail_type("a.url"));
+
+    assert_eq!(render_thumbnail("notes.md", b"# hi").unwrap(), None);
+    assert!(render_thumbnail("broken.png", b"not a png").is_err());
+}
+
+#[test]
+fn test_thumbnails_are_kept_in_the_cache() {
+    let kaya = tempfile::tempdir().unwrap();
+    let anga = kaya.path().join("anga");
+    let cache = kaya.path().join("cache");
+    fs::create_dir_all(&anga).unwrap();
+
+    let image = "2026-01-01T000000-photo.jpg";
+    let jpeg = encode(RgbImage::new(500, 500), ImageFormat::Jpeg);
+    fs::write(anga.join(image), jpeg).unwrap();
+    fs::write(anga.join("2026-01-01T000000-paper.pdf"), "%PDF-1.5").unwrap();
+    fs::write(anga.join("2026-01-01T000000-note.md"), "hi").unwrap();
+
+    assert_eq!(generate_missing(&anga, &cache, || true).unwrap(), 0);
+    assert_eq!(generate_missing(&anga, &cache, || false).unwrap(), 1);
+    let path = cache.join(image).join(THUMBNAIL_FILENAME);
+    assert_eq!(decode(&fs::read(&path).unwrap()).width(), 256);
+
+    assert_eq!(generate_missing(&anga, &cache, || false).unwrap(), 0);
+    assert_eq!(ensure_thumbnail(&anga, &cache, image).unwrap(), Some(path));
+    assert_eq!(
+        ensure_thumbnail(&anga, &cache, "2026-01-01T000000-note.md").unwrap(),
+        None
+    );
+}
+
+#[test]
+fn test_unreadable_anga_are_not_read_again_until_they_change() {
+    let kaya = tempfile::tempdir().unwrap();
+    let anga = kaya.path().join("anga");
+    let cache = kaya.path().join("cache");
+    fs::create_dir_all(&anga).unwrap();
+    let broken = "2026-01-01T000000-broken.png";
+    fs::write(anga.join(broken), "not a png").unwrap();
+
+    assert!(ensure_thumbnail(&anga, &cache, broken).is_err());
+    let marker = cache.join(broken).join(NO_THUMBNAIL_FILENAME);
+    assert!(marker.exists());
+    assert_eq!(ensure_thumbnail(&anga, &cache, broken).unwrap(), None);
+    assert_eq!(generate_missing(&anga, &cache, || false).unwrap(), 0);
+
+    let mut file = fs::File::options()
+        .write(true)
+        .truncate(true)
+        .open(anga.join(broken))
+        .unwrap();
+    file.write_all(&encode(RgbImage::new(10, 10), ImageFormat::Png))
+        .unwrap();
+    file.set_modified(SystemTime::now() + Duration::from_secs(60))
+        .unwrap();
+    drop(file);
+
+    assert_eq!(generate_missing(&anga, &cache, || false).unwrap(), 1);
+    assert!(cache.join(broken).join(THUMBNAIL_FILENAME).exists());
+    assert!(!marker.exists());
+}
@@ -0,0 +1,61 @@
+use savebutton_sync_daemon::verify::{verify_kaya_dir, Severity};
+use std::fs;
+
+#[test]
+fn test_verify_reports_unreadable_and_orphaned_files() {
+    let dir = tempfile::tempdir().unwrap();
+    let root = dir.path();
+    let anga = root.join("anga");
+    let meta = root.join("meta");
+    fs::create_dir_all(&anga).unwrap();
+    fs::create_dir_all(&meta).unwrap();
+
+    fs::write(
+        anga.join("2026-01-01T000000-good.url"),
+        "[InternetShortcut]\nURL=https://example.com/\n",
+    )
+    .unwrap();
+    fs::write(anga.join("2026-01-02T000000-bad.url"), "not a shortcut").unwrap();
+    fs::write(anga.join("notes.md"), "hello").unwrap();
+    fs::write(anga.join(".2026-01-03T000000-pic.png.part"), "").unwrap();
+    fs::write(
+        meta.join("2026-01-04T000000-tags.toml"),
+        "[anga]\nfilename = \"2026-01-09T000000-gone.url\"\n\n[meta]\ntags = [\"x\"]\n",
+    )
+    .unwrap();
+    fs::write(
+        meta.join("2026-01-05T000000-hidden.toml"),
+        "[anga]\nfilename = \"2026-01-08T000000-purged.url\"\n\n[meta]\nhidden = true\n",
+    )
+    .unwrap();
+    fs::write(meta.join("2026-01-06T000000-note.toml"), "garbage = [").unwrap();
+
+    let problems: Vec<(Severity, String)> = verify_kaya_dir(root)
+        .unwrap()
+        .into_iter()
+        .map(|p| (p.severity, p.path))
+        .collect();
+
+    assert_eq!(
+        problems,
+        [
+            (
+                Severity::Warning,
+                "anga/.2026-01-03T000000-pic.png.part".to_string()
+            ),
+            (
+                Severity::Error,
+                "anga/2026-01-02T000000-bad.url".to_string()
+            ),
+            (Severity::Warning, "anga/notes.md".to_string()),
+            (
+                Severity::Warning,
+                "meta/2026-01-04T000000-tags.toml".to_string()
+            ),
+            (
+                Severity::Error,
+                "meta/2026-01-06T000000-note.toml".to_string()
+            ),
+        ]
+    );
+}
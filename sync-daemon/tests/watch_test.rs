@@ -0,0 +1,54 @@
+use savebutton_sync_daemon::watch::{Change, Debouncer};
+use std::path::Path;
+use std::time::{Duration, Instant};
+
+#[test]
+fn test_classify_recognizes_synced_files() {
+    let kaya = Path::new("/home/u/.kaya");
+    let classify = |p: &str| Change::classify(kaya, &kaya.join(p));
+
+    assert_eq!(
+        classify("anga/2026-01-01T000000-a.url"),
+        Some(Change::Anga("2026-01-01T000000-a.url".into()))
+    );
+    assert_eq!(
+        classify("meta/2026-01-01T000000-tags.toml"),
+        Some(Change::Meta("2026-01-01T000000-tags.toml".into()))
+    );
+    assert_eq!(
+        classify("cache/2026-01-01T000000-a.url/page.html"),
+        Some(Change::Cache {
+            anga: "2026-01-01T000000-a.url".into(),
+            filename: "page.html".into(),
+        })
+    );
+
+    assert_eq!(classify("anga/.2026-01-01T000000-a.url.swp"), None);
+    assert_eq!(classify("anga/2026-01-01T000000-a.md~"), None);
+    assert_eq!(classify("meta/notes.txt"), None);
+    assert_eq!(classify("anga/sub/file.md"), None);
+    assert_eq!(classify(".config"), None);
+    assert_eq!(Change::classify(kaya, Path::new("/tmp/anga/x.md")), None);
+}
+
+#[test]
+fn test_debouncer_waits_for_files_to_settle() {
+    let start = Instant::now();
+    let quiet = Duration::from_secs(2);
+    let mut debouncer = Debouncer::new(quiet);
+    let a = Change::Anga("a.md".into());
+    let b = Change::Meta("b.toml".into());
+
+    debouncer.record(a.clone(), start);
+    debouncer.record(b.clone(), start);
+    debouncer.record(a.clone(), start + Duration::from_secs(1));
+    assert_eq!(debouncer.next_deadline(), Some(start + quiet));
+
+    assert!(debouncer
+        .take_ready(start + Duration::from_secs(1))
+        .is_empty());
+    assert_eq!(debouncer.take_ready(start + quiet), [b]);
+    assert_eq!(debouncer.take_ready(start + Duration::from_secs(3)), [a]);
+    assert!(debouncer.is_empty());
+    assert_eq!(debouncer.next_deadline(), None);
+}
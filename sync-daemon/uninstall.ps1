@@ -19,14 +19,18 @@ $InstallDir = "$env:ProgramFiles\Save Button"
 
 Write-Host "Uninstalling Save Button Sync Daemon..." -ForegroundColor Cyan
 
-# Remove registry entry
-Write-Host "Removing registry entry..." -ForegroundColor Cyan
+# Remove the manifests and registry keys install-host wrote for every browser
+Write-Host "Removing native messaging registrations..." -ForegroundColor Cyan
+$BinaryPath = Join-Path $InstallDir $BinaryName
+if (Test-Path $BinaryPath) {
+    & $BinaryPath uninstall-host
+}
+
+# Older versions of install.ps1 registered the host machine-wide
 $RegistryPath = "HKLM:\SOFTWARE\Mozilla\NativeMessagingHosts\$ManifestName"
 if (Test-Path $RegistryPath) {
     Remove-Item -Path $RegistryPath -Recurse -Force
     Write-Host "  Registry key removed: $RegistryPath" -ForegroundColor Gray
-} else {
-    Write-Host "  Registry key not found (already removed)" -ForegroundColor Gray
 }
 
 # Remove installation directory
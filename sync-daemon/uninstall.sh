@@ -2,14 +2,11 @@
 set -e
 
 BINARY_NAME="savebutton-sync-daemon"
-MANIFEST_NAME="org.savebutton.nativehost.json"
 
 if [[ "$OSTYPE" == "darwin"* ]]; then
     INSTALL_DIR="/usr/local/bin"
-    MANIFEST_DIR="$HOME/Library/Application Support/Mozilla/NativeMessagingHosts"
 elif [[ "$OSTYPE" == "linux-gnu"* ]]; then
     INSTALL_DIR="/usr/local/bin"
-    MANIFEST_DIR="$HOME/.mozilla/native-messaging-hosts"
 else
     echo "Unsupported OS: $OSTYPE"
     exit 1
@@ -18,6 +15,11 @@ fi
 echo "Uninstalling Save Button Sync Daemon..."
 
 BINARY_PATH="$INSTALL_DIR/$BINARY_NAME"
+if [ -x "$BINARY_PATH" ]; then
+    # Removes the manifests install-host wrote for every browser
+    "$BINARY_PATH" uninstall-host
+fi
+
 if [ -f "$BINARY_PATH" ]; then
     sudo rm "$BINARY_PATH"
     echo "  Removed binary: $BINARY_PATH"
@@ -25,14 +27,6 @@ else
     echo "  Binary not found (already removed): $BINARY_PATH"
 fi
 
-MANIFEST_PATH="$MANIFEST_DIR/$MANIFEST_NAME"
-if [ -f "$MANIFEST_PATH" ]; then
-    rm "$MANIFEST_PATH"
-    echo "  Removed manifest: $MANIFEST_PATH"
-else
-    echo "  Manifest not found (already removed): $MANIFEST_PATH"
-fi
-
 echo ""
 echo "Uninstallation complete!"
 echo ""